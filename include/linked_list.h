@@ -0,0 +1,96 @@
+/*
+ * C interface to production_linked_list::LinkedList, holding opaque
+ * pointers. See src/ffi.rs for the implementation.
+ *
+ * The list never dereferences the pointers it stores. Ownership of the
+ * pointed-to data stays with the caller until ll_free passes every
+ * remaining element to the destructor.
+ *
+ * Every function accepts NULL handles and treats them as empty. An
+ * iterator or cursor borrows its list: free it before changing the list
+ * through any other handle, and before freeing the list.
+ *
+ *     LinkedList *list = ll_new();
+ *     ll_push_back(list, strdup("hello"));
+ *
+ *     LinkedListIter *iter = ll_iter_new(list);
+ *     void *elem;
+ *     while (ll_iter_next(iter, &elem)) {
+ *         puts(elem);
+ *     }
+ *     ll_iter_free(iter);
+ *
+ *     ll_free(list, free);
+ */
+
+#ifndef LINKED_LIST_H
+#define LINKED_LIST_H
+
+#include <stdbool.h>
+#include <stddef.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct LinkedList LinkedList;
+typedef struct LinkedListIter LinkedListIter;
+typedef struct LinkedListCursor LinkedListCursor;
+
+/* Called once for every element still in the list when it is freed. */
+typedef void (*ll_destructor)(void *elem);
+
+LinkedList *ll_new(void);
+
+/* destructor may be NULL. */
+void ll_free(LinkedList *list, ll_destructor destructor);
+
+size_t ll_len(const LinkedList *list);
+
+void ll_push_front(LinkedList *list, void *elem);
+void ll_push_back(LinkedList *list, void *elem);
+
+/*
+ * Return false if the list is empty. Otherwise the element is written to
+ * out, which may be NULL to discard it.
+ */
+bool ll_pop_front(LinkedList *list, void **out);
+bool ll_pop_back(LinkedList *list, void **out);
+
+LinkedListIter *ll_iter_new(const LinkedList *list);
+
+/* Return false once the front and back of the iterator meet. */
+bool ll_iter_next(LinkedListIter *iter, void **out);
+bool ll_iter_next_back(LinkedListIter *iter, void **out);
+
+void ll_iter_free(LinkedListIter *iter);
+
+/*
+ * The cursor starts at the "ghost" position, which sits between the back
+ * and the front of the list. Moving past either end returns to it.
+ */
+LinkedListCursor *ll_cursor_new(LinkedList *list);
+
+void ll_cursor_move_next(LinkedListCursor *cursor);
+void ll_cursor_move_prev(LinkedListCursor *cursor);
+
+/* Return false at the ghost position. */
+bool ll_cursor_current(LinkedListCursor *cursor, void **out);
+bool ll_cursor_index(const LinkedListCursor *cursor, size_t *out);
+
+/* Moves every element before the cursor into a new list. */
+LinkedList *ll_cursor_split_before(LinkedListCursor *cursor);
+
+/*
+ * Moves every element of input in front of the cursor and frees input.
+ * With a NULL cursor nothing happens and input still belongs to the caller.
+ */
+void ll_cursor_splice_before(LinkedListCursor *cursor, LinkedList *input);
+
+void ll_cursor_free(LinkedListCursor *cursor);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* LINKED_LIST_H */
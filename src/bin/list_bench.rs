@@ -0,0 +1,523 @@
+//! Times the same workloads on each list, and on `VecDeque` for comparison.
+//!
+//! ```text
+//! cargo run --release --bin list_bench -- [--n N] [--runs R] [--warmup W] [--filter TEXT]
+//! ```
+//!
+//! Every workload does `n` operations per run (the mixed queue does three
+//! per step). After `warmup` untimed runs it's run `runs` more times and the
+//! ns/op of those runs is printed as CSV on stdout. Workloads a list can't do
+//! are left out, and lists without `Clone` are cloned by rebuilding them
+//! from their iterator, which is what a user of them would have to do.
+
+use std::{
+  collections::VecDeque,
+  env,
+  hint::black_box,
+  process,
+  time::{Duration, Instant},
+};
+
+use learn_rust_with_entirely_too_many_linked_lists::{
+  fifth, fourth, production_linked_list::LinkedList, second, sixth,
+};
+
+type Elem = u64;
+
+/// Runs a workload of `n` operations, returning how long the timed part
+/// took. Setup and dropping whatever's left over aren't timed.
+type Bench = fn(usize) -> Duration;
+
+struct Case {
+  workload: &'static str,
+  list: &'static str,
+  /// Operations per element of `n`.
+  ops_per_n: usize,
+  bench: Bench,
+}
+
+/// Times `f`, dropping its result only after the clock has stopped.
+fn time<R>(f: impl FnOnce() -> R) -> Duration {
+  let start = Instant::now();
+  let result = black_box(f());
+  let elapsed = start.elapsed();
+  drop(result);
+  elapsed
+}
+
+fn elems(n: usize) -> impl DoubleEndedIterator<Item = Elem> {
+  0..n as Elem
+}
+
+fn second_list(n: usize) -> second::List<Elem> {
+  let mut list = second::List::new();
+  elems(n).for_each(|elem| list.push(elem));
+  list
+}
+
+fn fourth_list(n: usize) -> fourth::List<Elem> {
+  let mut list = fourth::List::new();
+  elems(n).for_each(|elem| list.push_back(elem));
+  list
+}
+
+fn fifth_list(n: usize) -> fifth::List<Elem> {
+  let mut list = fifth::List::new();
+  elems(n).for_each(|elem| list.push(elem));
+  list
+}
+
+fn sixth_list(n: usize) -> sixth::List<Elem> {
+  let mut list = sixth::List::new();
+  elems(n).for_each(|elem| list.push(elem));
+  list
+}
+
+fn cases() -> Vec<Case> {
+  let case = |workload, list, bench| Case {
+    workload,
+    list,
+    ops_per_n: 1,
+    bench,
+  };
+  let mixed = |list, bench| Case {
+    workload: "mixed_queue",
+    list,
+    ops_per_n: 3,
+    bench,
+  };
+
+  vec![
+    // second: a stack, so only the front.
+    case("push_front", "second", |n| {
+      let mut list = second::List::new();
+      time(|| {
+        elems(n).for_each(|elem| list.push(black_box(elem)));
+        list
+      })
+    }),
+    case("pop_front", "second", |n| {
+      let mut list = second_list(n);
+      time(|| {
+        (0..n).for_each(|_| {
+          black_box(list.pop());
+        })
+      })
+    }),
+    case("iter", "second", |n| {
+      let list = second_list(n);
+      time(|| list.iter().sum::<Elem>())
+    }),
+    case("clone", "second", |n| {
+      let list = second_list(n);
+      time(|| {
+        // Pushing reverses, so go through a `Vec` to keep the order.
+        let elems: Vec<Elem> = list.iter().copied().collect();
+        let mut copy = second::List::new();
+        elems.into_iter().rev().for_each(|elem| copy.push(elem));
+        copy
+      })
+    }),
+    // fourth
+    case("push_front", "fourth", |n| {
+      let mut list = fourth::List::new();
+      time(|| {
+        elems(n).for_each(|elem| list.push_front(black_box(elem)));
+        list
+      })
+    }),
+    case("push_back", "fourth", |n| {
+      let mut list = fourth::List::new();
+      time(|| {
+        elems(n).for_each(|elem| list.push_back(black_box(elem)));
+        list
+      })
+    }),
+    case("pop_front", "fourth", |n| {
+      let mut list = fourth_list(n);
+      time(|| {
+        (0..n).for_each(|_| {
+          black_box(list.pop_front());
+        })
+      })
+    }),
+    case("pop_back", "fourth", |n| {
+      let mut list = fourth_list(n);
+      time(|| {
+        (0..n).for_each(|_| {
+          black_box(list.pop_back());
+        })
+      })
+    }),
+    mixed("fourth", |n| {
+      let mut list = fourth::List::new();
+      time(|| {
+        for elem in elems(n) {
+          list.push_back(elem);
+          list.push_back(elem);
+          black_box(list.pop_front());
+        }
+        list
+      })
+    }),
+    // fifth: a queue, in at the back and out at the front.
+    case("push_back", "fifth", |n| {
+      let mut list = fifth::List::new();
+      time(|| {
+        elems(n).for_each(|elem| list.push(black_box(elem)));
+        list
+      })
+    }),
+    case("pop_front", "fifth", |n| {
+      let mut list = fifth_list(n);
+      time(|| {
+        (0..n).for_each(|_| {
+          black_box(list.pop());
+        })
+      })
+    }),
+    mixed("fifth", |n| {
+      let mut list = fifth::List::new();
+      time(|| {
+        for elem in elems(n) {
+          list.push(elem);
+          list.push(elem);
+          black_box(list.pop());
+        }
+        list
+      })
+    }),
+    // sixth: also a queue.
+    case("push_back", "sixth", |n| {
+      let mut list = sixth::List::new();
+      time(|| {
+        elems(n).for_each(|elem| list.push(black_box(elem)));
+        list
+      })
+    }),
+    case("pop_front", "sixth", |n| {
+      let mut list = sixth_list(n);
+      time(|| {
+        (0..n).for_each(|_| {
+          black_box(list.pop());
+        })
+      })
+    }),
+    case("iter", "sixth", |n| {
+      let list = sixth_list(n);
+      time(|| list.iter().sum::<Elem>())
+    }),
+    case("clone", "sixth", |n| {
+      let list = sixth_list(n);
+      time(|| {
+        let mut copy = sixth::List::new();
+        list.iter().for_each(|&elem| copy.push(elem));
+        copy
+      })
+    }),
+    mixed("sixth", |n| {
+      let mut list = sixth::List::new();
+      time(|| {
+        for elem in elems(n) {
+          list.push(elem);
+          list.push(elem);
+          black_box(list.pop());
+        }
+        list
+      })
+    }),
+    // production
+    case("push_front", "production", |n| {
+      let mut list = LinkedList::new();
+      time(|| {
+        elems(n).for_each(|elem| list.push_front(black_box(elem)));
+        list
+      })
+    }),
+    case("push_back", "production", |n| {
+      let mut list = LinkedList::new();
+      time(|| {
+        elems(n).for_each(|elem| list.push_back(black_box(elem)));
+        list
+      })
+    }),
+    case("pop_front", "production", |n| {
+      let mut list: LinkedList<Elem> = elems(n).collect();
+      time(|| {
+        (0..n).for_each(|_| {
+          black_box(list.pop_front());
+        })
+      })
+    }),
+    case("pop_back", "production", |n| {
+      let mut list: LinkedList<Elem> = elems(n).collect();
+      time(|| {
+        (0..n).for_each(|_| {
+          black_box(list.pop_back());
+        })
+      })
+    }),
+    case("iter", "production", |n| {
+      let list: LinkedList<Elem> = elems(n).collect();
+      time(|| list.iter().sum::<Elem>())
+    }),
+    case("clone", "production", |n| {
+      let list: LinkedList<Elem> = elems(n).collect();
+      time(|| list.clone())
+    }),
+    mixed("production", |n| {
+      let mut list = LinkedList::new();
+      time(|| {
+        for elem in elems(n) {
+          list.push_back(elem);
+          list.push_back(elem);
+          black_box(list.pop_front());
+        }
+        list
+      })
+    }),
+    // VecDeque
+    case("push_front", "VecDeque", |n| {
+      let mut list = VecDeque::new();
+      time(|| {
+        elems(n).for_each(|elem| list.push_front(black_box(elem)));
+        list
+      })
+    }),
+    case("push_back", "VecDeque", |n| {
+      let mut list = VecDeque::new();
+      time(|| {
+        elems(n).for_each(|elem| list.push_back(black_box(elem)));
+        list
+      })
+    }),
+    case("pop_front", "VecDeque", |n| {
+      let mut list: VecDeque<Elem> = elems(n).collect();
+      time(|| {
+        (0..n).for_each(|_| {
+          black_box(list.pop_front());
+        })
+      })
+    }),
+    case("pop_back", "VecDeque", |n| {
+      let mut list: VecDeque<Elem> = elems(n).collect();
+      time(|| {
+        (0..n).for_each(|_| {
+          black_box(list.pop_back());
+        })
+      })
+    }),
+    case("iter", "VecDeque", |n| {
+      let list: VecDeque<Elem> = elems(n).collect();
+      time(|| list.iter().sum::<Elem>())
+    }),
+    case("clone", "VecDeque", |n| {
+      let list: VecDeque<Elem> = elems(n).collect();
+      time(|| list.clone())
+    }),
+    mixed("VecDeque", |n| {
+      let mut list = VecDeque::new();
+      time(|| {
+        for elem in elems(n) {
+          list.push_back(elem);
+          list.push_back(elem);
+          black_box(list.pop_front());
+        }
+        list
+      })
+    }),
+  ]
+}
+
+#[derive(Debug, PartialEq)]
+struct Stats {
+  min: f64,
+  median: f64,
+  mean: f64,
+  max: f64,
+}
+
+/// `samples` are ns/op, one per run.
+fn stats(mut samples: Vec<f64>) -> Stats {
+  assert!(!samples.is_empty());
+  samples.sort_by(f64::total_cmp);
+
+  let len = samples.len();
+  let median = if len.is_multiple_of(2) {
+    (samples[len / 2 - 1] + samples[len / 2]) / 2.0
+  } else {
+    samples[len / 2]
+  };
+
+  Stats {
+    min: samples[0],
+    median,
+    mean: samples.iter().sum::<f64>() / len as f64,
+    max: samples[len - 1],
+  }
+}
+
+struct Options {
+  n: usize,
+  runs: usize,
+  warmup: usize,
+  filter: Option<String>,
+}
+
+fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Options, String> {
+  let mut options = Options {
+    n: 10_000,
+    runs: 10,
+    warmup: 3,
+    filter: None,
+  };
+
+  let mut args = args.into_iter();
+
+  while let Some(flag) = args.next() {
+    let value = args
+      .next()
+      .ok_or_else(|| format!("`{}` needs a value", flag))?;
+    let number = || {
+      value
+        .parse::<usize>()
+        .map_err(|err| format!("bad value for `{}`: {}", flag, err))
+    };
+
+    match flag.as_str() {
+      "--n" => options.n = number()?,
+      "--runs" => options.runs = number()?,
+      "--warmup" => options.warmup = number()?,
+      "--filter" => options.filter = Some(value),
+      _ => return Err(format!("unknown option `{}`", flag)),
+    }
+  }
+
+  if options.runs == 0 || options.n == 0 {
+    return Err("`--n` and `--runs` must be at least 1".to_string());
+  }
+
+  Ok(options)
+}
+
+fn run(case: &Case, options: &Options) -> Stats {
+  for _ in 0..options.warmup {
+    (case.bench)(options.n);
+  }
+
+  let ops = (options.n * case.ops_per_n) as f64;
+  let samples = (0..options.runs)
+    .map(|_| (case.bench)(options.n).as_nanos() as f64 / ops)
+    .collect();
+
+  stats(samples)
+}
+
+fn main() {
+  let options = parse_args(env::args().skip(1)).unwrap_or_else(|err| {
+    eprintln!("{}", err);
+    process::exit(2);
+  });
+
+  println!("workload,list,n,runs,min_ns_per_op,median_ns_per_op,mean_ns_per_op,max_ns_per_op");
+
+  for case in cases() {
+    if let Some(filter) = options.filter.as_deref() {
+      if !case.workload.contains(filter) && !case.list.contains(filter) {
+        continue;
+      }
+    }
+
+    let stats = run(&case, &options);
+
+    println!(
+      "{},{},{},{},{:.2},{:.2},{:.2},{:.2}",
+      case.workload,
+      case.list,
+      options.n,
+      options.runs,
+      stats.min,
+      stats.median,
+      stats.mean,
+      stats.max
+    );
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn args(args: &[&str]) -> Result<Options, String> {
+    parse_args(args.iter().map(|arg| arg.to_string()))
+  }
+
+  #[test]
+  fn every_case_runs() {
+    let options = Options {
+      n: 100,
+      runs: 2,
+      warmup: 1,
+      filter: None,
+    };
+
+    for case in cases() {
+      let stats = run(&case, &options);
+      assert!(stats.min <= stats.median && stats.median <= stats.max);
+    }
+  }
+
+  #[test]
+  fn every_list_has_the_basic_workloads() {
+    let cases = cases();
+
+    for list in [
+      "second",
+      "fourth",
+      "fifth",
+      "sixth",
+      "production",
+      "VecDeque",
+    ] {
+      let workloads: Vec<_> = cases
+        .iter()
+        .filter(|case| case.list == list)
+        .map(|case| case.workload)
+        .collect();
+
+      assert!(
+        workloads.contains(&"push_front") || workloads.contains(&"push_back"),
+        "{}",
+        list
+      );
+      assert!(workloads.contains(&"pop_front"), "{}", list);
+    }
+  }
+
+  #[test]
+  fn computes_stats() {
+    assert_eq!(
+      stats(vec![4.0, 1.0, 3.0, 2.0]),
+      Stats {
+        min: 1.0,
+        median: 2.5,
+        mean: 2.5,
+        max: 4.0
+      }
+    );
+    assert_eq!(5.0, stats(vec![5.0, 9.0, 1.0]).median);
+  }
+
+  #[test]
+  fn parses_options() {
+    let options = args(&["--n", "5", "--filter", "iter"]).unwrap();
+    assert_eq!(5, options.n);
+    assert_eq!(10, options.runs);
+    assert_eq!(Some("iter"), options.filter.as_deref());
+
+    assert!(args(&["--n"]).is_err());
+    assert!(args(&["--n", "x"]).is_err());
+    assert!(args(&["--runs", "0"]).is_err());
+    assert!(args(&["--fast", "1"]).is_err());
+  }
+}
@@ -0,0 +1,657 @@
+//! A little shell for poking at the lists one operation at a time.
+//!
+//! ```text
+//! cargo run --bin list_repl -- [IMPL] [SCRIPT]
+//! ```
+//!
+//! `IMPL` is one of `second`, `third`, `fourth`, `fifth`, `sixth` or
+//! `production` (the default). `SCRIPT` is a file of commands, one per line,
+//! run before reading from stdin. `save` writes the commands that worked so
+//! far in the same format, so a session can be handed to someone else and
+//! replayed with `load`. Type `help` for the commands.
+
+use std::{
+  env, fs,
+  io::{self, BufRead, Write},
+  process,
+};
+
+use learn_rust_with_entirely_too_many_linked_lists::{
+  dot, fifth, fourth, production_linked_list::LinkedList, second, sixth, third,
+};
+
+type Elem = i64;
+
+const IMPLS: &[&str] = &["second", "third", "fourth", "fifth", "sixth", "production"];
+
+const HELP: &str = "  print                show the list
+  dot                  show the list as a Graphviz graph
+  use IMPL             start over with another implementation
+  load FILE            run the commands in FILE
+  save FILE            write the commands run so far to FILE
+  history              show the commands run so far
+  help                 show this
+  quit                 leave";
+
+/// One list implementation, driven by text commands.
+trait Playground {
+  /// Runs a list command. `Ok(Some(..))` is something to report, like a
+  /// popped element.
+  fn run(&mut self, cmd: &str, arg: Option<&str>) -> Result<Option<String>, String>;
+
+  /// `&mut` only because `fifth::List` can't be looked at without popping.
+  fn show(&mut self) -> String;
+
+  fn dot(&self) -> String;
+
+  /// The list commands, in the same layout as `HELP`.
+  fn help(&self) -> &'static str;
+}
+
+fn elem(arg: Option<&str>) -> Result<Elem, String> {
+  let arg = arg.ok_or("missing element")?;
+  arg
+    .parse()
+    .map_err(|err| format!("bad element {:?}: {}", arg, err))
+}
+
+fn no_arg(cmd: &str, arg: Option<&str>) -> Result<(), String> {
+  match arg {
+    Some(_) => Err(format!("`{}` takes no argument", cmd)),
+    None => Ok(()),
+  }
+}
+
+fn report<E: ToString>(elem: Option<E>) -> Option<String> {
+  Some(elem.map_or_else(|| "nothing".to_string(), |elem| elem.to_string()))
+}
+
+fn unknown(cmd: &str) -> Result<Option<String>, String> {
+  Err(format!("unknown command `{}`, try `help`", cmd))
+}
+
+#[derive(Default)]
+struct Second(second::List<Elem>);
+
+impl Playground for Second {
+  fn run(&mut self, cmd: &str, arg: Option<&str>) -> Result<Option<String>, String> {
+    match cmd {
+      "push" | "push_front" => {
+        self.0.push(elem(arg)?);
+        Ok(None)
+      }
+      "pop" | "pop_front" => no_arg(cmd, arg).map(|_| report(self.0.pop())),
+      "peek" | "front" => no_arg(cmd, arg).map(|_| report(self.0.peek())),
+      _ => unknown(cmd),
+    }
+  }
+
+  fn show(&mut self) -> String {
+    format!("{:#}", self.0)
+  }
+
+  fn dot(&self) -> String {
+    dot::to_dot(&self.0)
+  }
+
+  fn help(&self) -> &'static str {
+    "  push N               push onto the top (also push_front)
+  pop                  pop off the top (also pop_front)
+  peek                 show the top (also front)"
+  }
+}
+
+#[derive(Default)]
+struct Third(third::List<Elem>);
+
+impl Playground for Third {
+  fn run(&mut self, cmd: &str, arg: Option<&str>) -> Result<Option<String>, String> {
+    match cmd {
+      "cons" | "push_front" => {
+        self.0 = self.0.cons(elem(arg)?);
+        Ok(None)
+      }
+      "tail" | "pop_front" => {
+        no_arg(cmd, arg)?;
+        let head = report(self.0.head());
+        self.0 = self.0.tail();
+        Ok(head)
+      }
+      "head" | "front" => no_arg(cmd, arg).map(|_| report(self.0.head())),
+      _ => unknown(cmd),
+    }
+  }
+
+  fn show(&mut self) -> String {
+    format!("{:#}", self.0)
+  }
+
+  fn dot(&self) -> String {
+    dot::to_dot(&self.0)
+  }
+
+  fn help(&self) -> &'static str {
+    "  cons N               put N in front (also push_front)
+  tail                 drop the head (also pop_front)
+  head                 show the head (also front)"
+  }
+}
+
+#[derive(Default)]
+struct Fourth(fourth::List<Elem>);
+
+impl Playground for Fourth {
+  fn run(&mut self, cmd: &str, arg: Option<&str>) -> Result<Option<String>, String> {
+    match cmd {
+      "push_front" => {
+        self.0.push_front(elem(arg)?);
+        Ok(None)
+      }
+      "push_back" => {
+        self.0.push_back(elem(arg)?);
+        Ok(None)
+      }
+      "pop_front" => no_arg(cmd, arg).map(|_| report(self.0.pop_front())),
+      "pop_back" => no_arg(cmd, arg).map(|_| report(self.0.pop_back())),
+      "front" => no_arg(cmd, arg).map(|_| report(self.0.peek_front().map(|elem| *elem))),
+      "back" => no_arg(cmd, arg).map(|_| report(self.0.peek_back().map(|elem| *elem))),
+      _ => unknown(cmd),
+    }
+  }
+
+  fn show(&mut self) -> String {
+    format!("{:#}", self.0)
+  }
+
+  fn dot(&self) -> String {
+    dot::to_dot(&self.0)
+  }
+
+  fn help(&self) -> &'static str {
+    "  push_front N         push_back N
+  pop_front            pop_back
+  front                back"
+  }
+}
+
+#[derive(Default)]
+struct Fifth(fifth::List<Elem>);
+
+impl Fifth {
+  /// The list has no way to look at its elements, so take them all out
+  /// and put them back.
+  fn elems(&mut self) -> Vec<Elem> {
+    let mut elems = Vec::new();
+
+    while let Some(elem) = self.0.pop() {
+      elems.push(elem);
+    }
+
+    for &elem in elems.iter() {
+      self.0.push(elem);
+    }
+
+    elems
+  }
+}
+
+impl Playground for Fifth {
+  fn run(&mut self, cmd: &str, arg: Option<&str>) -> Result<Option<String>, String> {
+    match cmd {
+      "push" | "push_back" => {
+        self.0.push(elem(arg)?);
+        Ok(None)
+      }
+      "pop" | "pop_front" => no_arg(cmd, arg).map(|_| report(self.0.pop())),
+      _ => unknown(cmd),
+    }
+  }
+
+  fn show(&mut self) -> String {
+    let elems: Vec<String> = self.elems().iter().map(Elem::to_string).collect();
+
+    if elems.is_empty() {
+      "[]".to_string()
+    } else {
+      elems.join(" -> ")
+    }
+  }
+
+  fn dot(&self) -> String {
+    dot::to_dot(&self.0)
+  }
+
+  fn help(&self) -> &'static str {
+    "  push N               push onto the back (also push_back)
+  pop                  pop off the front (also pop_front)"
+  }
+}
+
+#[derive(Default)]
+struct Sixth(sixth::List<Elem>);
+
+impl Playground for Sixth {
+  fn run(&mut self, cmd: &str, arg: Option<&str>) -> Result<Option<String>, String> {
+    match cmd {
+      "push" | "push_back" => {
+        self.0.push(elem(arg)?);
+        Ok(None)
+      }
+      "pop" | "pop_front" => no_arg(cmd, arg).map(|_| report(self.0.pop())),
+      "peek" | "front" => no_arg(cmd, arg).map(|_| report(self.0.peek())),
+      _ => unknown(cmd),
+    }
+  }
+
+  fn show(&mut self) -> String {
+    format!("{:#}", self.0)
+  }
+
+  fn dot(&self) -> String {
+    dot::to_dot(&self.0)
+  }
+
+  fn help(&self) -> &'static str {
+    "  push N               push onto the back (also push_back)
+  pop                  pop off the front (also pop_front)
+  peek                 show the front (also front)"
+  }
+}
+
+/// A `CursorMut` can't outlive a command, so this keeps the cursor's index
+/// and walks a fresh one there each time. Anything that changes the list
+/// without going through the cursor puts it back on the ghost, as a new
+/// cursor would be.
+#[derive(Default)]
+struct Production {
+  list: LinkedList<Elem>,
+  cursor: Option<usize>,
+}
+
+impl Production {
+  fn cursor_command(&mut self, arg: Option<&str>) -> Result<Option<String>, String> {
+    let mut cursor = self.list.cursor_mut();
+    if let Some(index) = self.cursor {
+      for _ in 0..=index {
+        cursor.move_next();
+      }
+    }
+
+    let output = match arg {
+      Some("next") => None,
+      Some("prev") => None,
+      Some("reset") => None,
+      Some("current") => report(cursor.current().copied()),
+      Some("peek_next") => report(cursor.peek_next().copied()),
+      Some("peek_prev") => report(cursor.peek_prev().copied()),
+      Some(other) => return Err(format!("unknown cursor command `{}`", other)),
+      None => return Err("`cursor` needs a command, try `help`".to_string()),
+    };
+
+    match arg {
+      Some("next") => cursor.move_next(),
+      Some("prev") => cursor.move_prev(),
+      _ => {}
+    }
+
+    self.cursor = if arg == Some("reset") {
+      None
+    } else {
+      cursor.index()
+    };
+
+    Ok(output)
+  }
+}
+
+impl Playground for Production {
+  fn run(&mut self, cmd: &str, arg: Option<&str>) -> Result<Option<String>, String> {
+    if cmd == "cursor" {
+      return self.cursor_command(arg);
+    }
+
+    let output = match cmd {
+      "push_front" => {
+        self.list.push_front(elem(arg)?);
+        None
+      }
+      "push_back" => {
+        self.list.push_back(elem(arg)?);
+        None
+      }
+      "pop_front" => no_arg(cmd, arg).map(|_| report(self.list.pop_front()))?,
+      "pop_back" => no_arg(cmd, arg).map(|_| report(self.list.pop_back()))?,
+      "front" => return no_arg(cmd, arg).map(|_| report(self.list.front())),
+      "back" => return no_arg(cmd, arg).map(|_| report(self.list.back())),
+      "clear" => {
+        no_arg(cmd, arg)?;
+        self.list.clear();
+        None
+      }
+      "split_before" | "splice_before" => {
+        let splice = if cmd == "splice_before" {
+          let arg = arg.ok_or("missing list, e.g. [1, 2]")?;
+          Some(
+            arg
+              .parse::<LinkedList<Elem>>()
+              .map_err(|err| format!("bad list {:?}: {}", arg, err))?,
+          )
+        } else {
+          no_arg(cmd, arg)?;
+          None
+        };
+
+        let mut cursor = self.list.cursor_mut();
+        if let Some(index) = self.cursor {
+          for _ in 0..=index {
+            cursor.move_next();
+          }
+        }
+
+        let output = match splice {
+          Some(input) => {
+            cursor.splice_before(input);
+            None
+          }
+          None => Some(format!("split off {}", cursor.split_before())),
+        };
+
+        self.cursor = cursor.index();
+        return Ok(output);
+      }
+      _ => return unknown(cmd),
+    };
+
+    self.cursor = None;
+    Ok(output)
+  }
+
+  fn show(&mut self) -> String {
+    let list = format!("{:#}", self.list);
+
+    match self.cursor {
+      Some(index) => format!(
+        "{}    cursor at {} ({})",
+        list,
+        index,
+        self.list.iter().nth(index).unwrap()
+      ),
+      None => format!("{}    cursor at the ghost", list),
+    }
+  }
+
+  fn dot(&self) -> String {
+    dot::to_dot(&self.list)
+  }
+
+  fn help(&self) -> &'static str {
+    "  push_front N         push_back N
+  pop_front            pop_back
+  front                back
+  clear
+  cursor next          cursor prev
+  cursor current       cursor reset
+  cursor peek_next     cursor peek_prev
+  split_before         split off everything before the cursor
+  splice_before LIST   insert LIST before the cursor, e.g. [1, 2]"
+  }
+}
+
+fn playground(name: &str) -> Result<Box<dyn Playground>, String> {
+  Ok(match name {
+    "second" => Box::<Second>::default(),
+    "third" => Box::<Third>::default(),
+    "fourth" => Box::<Fourth>::default(),
+    "fifth" => Box::<Fifth>::default(),
+    "sixth" => Box::<Sixth>::default(),
+    "production" => Box::<Production>::default(),
+    _ => {
+      return Err(format!(
+        "unknown implementation `{}`, pick one of {}",
+        name,
+        IMPLS.join(", ")
+      ))
+    }
+  })
+}
+
+/// What a line asked the shell to do, besides printing.
+#[derive(Debug, PartialEq)]
+enum Flow {
+  Continue,
+  Quit,
+}
+
+struct Repl {
+  name: String,
+  list: Box<dyn Playground>,
+  /// The commands that changed or looked at the list, for `save`.
+  history: Vec<String>,
+}
+
+impl Repl {
+  fn new(name: &str) -> Result<Self, String> {
+    Ok(Self {
+      name: name.to_string(),
+      list: playground(name)?,
+      history: vec![format!("use {}", name)],
+    })
+  }
+
+  /// Runs one line, writing whatever it has to say to `out`.
+  fn execute(&mut self, line: &str, out: &mut dyn Write) -> Result<Flow, String> {
+    let line = line.trim();
+
+    if line.is_empty() || line.starts_with('#') {
+      return Ok(Flow::Continue);
+    }
+
+    let (cmd, arg) = match line.split_once(char::is_whitespace) {
+      Some((cmd, arg)) => (cmd, Some(arg.trim())),
+      None => (line, None),
+    };
+
+    let write =
+      |out: &mut dyn Write, text: &str| writeln!(out, "{}", text).map_err(|err| err.to_string());
+
+    match cmd {
+      "quit" | "exit" => return Ok(Flow::Quit),
+      "help" => {
+        write(out, &format!("{} commands:", self.name))?;
+        write(out, self.list.help())?;
+        write(out, HELP)?;
+      }
+      "print" => write(out, &self.list.show())?,
+      "dot" => write(out, self.list.dot().trim_end())?,
+      "history" => write(out, &self.history.join("\n"))?,
+      "use" => {
+        let name = arg.ok_or("`use` needs an implementation")?;
+        *self = Repl::new(name)?;
+        write(out, &self.list.show())?;
+      }
+      "load" => {
+        let path = arg.ok_or("`load` needs a file")?;
+        let script = fs::read_to_string(path).map_err(|err| format!("{}: {}", path, err))?;
+
+        for (i, line) in script.lines().enumerate() {
+          let flow = self
+            .execute(line, out)
+            .map_err(|err| format!("{}:{}: {}", path, i + 1, err))?;
+
+          if flow == Flow::Quit {
+            return Ok(Flow::Quit);
+          }
+        }
+      }
+      "save" => {
+        let path = arg.ok_or("`save` needs a file")?;
+        let mut script = self.history.join("\n");
+        script.push('\n');
+        fs::write(path, script).map_err(|err| format!("{}: {}", path, err))?;
+        write(out, &format!("saved {} commands", self.history.len()))?;
+      }
+      _ => {
+        if let Some(output) = self.list.run(cmd, arg)? {
+          write(out, &output)?;
+        }
+        self.history.push(line.to_string());
+        write(out, &self.list.show())?;
+      }
+    }
+
+    Ok(Flow::Continue)
+  }
+}
+
+fn main() {
+  let mut args = env::args().skip(1);
+  let name = args.next().unwrap_or_else(|| "production".to_string());
+
+  let mut repl = Repl::new(&name).unwrap_or_else(|err| {
+    eprintln!("{}", err);
+    process::exit(2);
+  });
+
+  let stdout = io::stdout();
+  let mut out = stdout.lock();
+
+  if let Some(script) = args.next() {
+    match repl.execute(&format!("load {}", script), &mut out) {
+      Ok(Flow::Quit) => return,
+      Ok(Flow::Continue) => {}
+      Err(err) => {
+        eprintln!("{}", err);
+        process::exit(1);
+      }
+    }
+  }
+
+  let stdin = io::stdin();
+  let mut lines = stdin.lock().lines();
+
+  loop {
+    write!(out, "{}> ", repl.name).unwrap();
+    out.flush().unwrap();
+
+    let line = match lines.next() {
+      Some(Ok(line)) => line,
+      Some(Err(err)) => {
+        eprintln!("{}", err);
+        process::exit(1);
+      }
+      None => break,
+    };
+
+    match repl.execute(&line, &mut out) {
+      Ok(Flow::Continue) => {}
+      Ok(Flow::Quit) => break,
+      Err(err) => writeln!(out, "error: {}", err).unwrap(),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn run(repl: &mut Repl, line: &str) -> Result<String, String> {
+    let mut out = Vec::new();
+    repl.execute(line, &mut out)?;
+    Ok(String::from_utf8(out).unwrap())
+  }
+
+  #[test]
+  fn every_implementation_pushes_and_pops() {
+    for name in IMPLS {
+      let mut repl = Repl::new(name).unwrap();
+
+      run(&mut repl, "push_front 1")
+        .or_else(|_| run(&mut repl, "push_back 1"))
+        .unwrap();
+      let popped = run(&mut repl, "pop_front").unwrap();
+
+      assert!(popped.starts_with("1\n"), "{}: {:?}", name, popped);
+      assert!(run(&mut repl, "bogus").is_err());
+    }
+  }
+
+  #[test]
+  fn shows_the_list_after_each_command() {
+    let mut repl = Repl::new("fourth").unwrap();
+
+    assert_eq!("1\n", run(&mut repl, "push_back 1").unwrap());
+    assert_eq!("0 <-> 1\n", run(&mut repl, "push_front 0").unwrap());
+    assert_eq!("1\n0\n", run(&mut repl, "pop_back").unwrap());
+
+    let mut repl = Repl::new("fifth").unwrap();
+    run(&mut repl, "push 1").unwrap();
+    assert_eq!("1 -> 2\n", run(&mut repl, "push 2").unwrap());
+    assert_eq!("1\n2\n", run(&mut repl, "pop").unwrap());
+    assert_eq!("2\n[]\n", run(&mut repl, "pop").unwrap());
+  }
+
+  #[test]
+  fn production_cursor() {
+    let mut repl = Repl::new("production").unwrap();
+
+    for i in 1..=4 {
+      run(&mut repl, &format!("push_back {}", i)).unwrap();
+    }
+
+    assert_eq!(
+      "1 <-> 2 <-> 3 <-> 4    cursor at 0 (1)\n",
+      run(&mut repl, "cursor next").unwrap()
+    );
+    run(&mut repl, "cursor next").unwrap();
+    assert_eq!(
+      "split off [1]\n2 <-> 3 <-> 4    cursor at 0 (2)\n",
+      run(&mut repl, "split_before").unwrap()
+    );
+    assert_eq!(
+      "7 <-> 8 <-> 2 <-> 3 <-> 4    cursor at 2 (2)\n",
+      run(&mut repl, "splice_before [7, 8]").unwrap()
+    );
+    assert!(run(&mut repl, "cursor peek_next")
+      .unwrap()
+      .starts_with("3\n"));
+
+    // Going around the list directly puts the cursor back on the ghost.
+    assert!(run(&mut repl, "pop_back")
+      .unwrap()
+      .ends_with("cursor at the ghost\n"));
+
+    assert!(run(&mut repl, "cursor sideways").is_err());
+    assert!(run(&mut repl, "splice_before [1,").is_err());
+  }
+
+  #[test]
+  fn save_and_load() {
+    let path = env::temp_dir().join(format!("list_repl_{}.txt", process::id()));
+    let path = path.to_str().unwrap();
+
+    let mut repl = Repl::new("sixth").unwrap();
+    run(&mut repl, "push 1").unwrap();
+    run(&mut repl, "push 2").unwrap();
+    // Failed commands don't make it into the script.
+    assert!(run(&mut repl, "push x").is_err());
+    run(&mut repl, "pop").unwrap();
+    run(&mut repl, &format!("save {}", path)).unwrap();
+
+    assert_eq!(
+      "use sixth\npush 1\npush 2\npop\n",
+      fs::read_to_string(path).unwrap()
+    );
+
+    let mut repl = Repl::new("production").unwrap();
+    run(&mut repl, &format!("load {}", path)).unwrap();
+    assert_eq!("2\n", run(&mut repl, "print").unwrap());
+
+    fs::write(path, "use second\npush 1\npop 2\n").unwrap();
+    assert_eq!(
+      Err(format!("{}:3: `pop` takes no argument", path)),
+      run(&mut repl, &format!("load {}", path))
+    );
+
+    fs::remove_file(path).unwrap();
+  }
+}
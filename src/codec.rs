@@ -0,0 +1,668 @@
+//! A small binary format for persisting lists.
+//!
+//! Unsigned integers are written as LEB128 varints and signed integers are
+//! zigzag encoded first, so small values take a single byte either way.
+//! Strings and lists are prefixed by their length, which lets the decoder
+//! reject oversized input before it reads (or allocates) anything.
+//!
+//! ```
+//! use learn_rust_with_entirely_too_many_linked_lists::{
+//!   codec::{Decoder, Encode},
+//!   production_linked_list::LinkedList,
+//! };
+//!
+//! let list: LinkedList<u32> = (0..3).collect();
+//!
+//! let mut bytes = Vec::new();
+//! list.encode(&mut bytes).unwrap();
+//!
+//! let decoded: LinkedList<u32> = Decoder::new(bytes.as_slice()).decode().unwrap();
+//! assert_eq!(list, decoded);
+//! ```
+
+use std::{
+  error, fmt,
+  io::{self, Read, Write},
+  marker::PhantomData,
+};
+
+use crate::{production_linked_list::LinkedList, second, sixth};
+
+/// Longest string (in bytes) or list (in elements) a [`Decoder`] accepts
+/// unless told otherwise.
+pub const DEFAULT_MAX_LEN: usize = 1 << 24;
+
+/// A varint never needs more than 10 bytes to hold a u64.
+const MAX_VARINT_LEN: usize = 10;
+
+#[derive(Debug)]
+pub enum DecodeError {
+  Io(io::Error),
+  /// The input ended in the middle of a value.
+  UnexpectedEof,
+  /// A varint did not fit in 64 bits.
+  VarintOverflow,
+  /// A varint did not fit in the integer type being decoded.
+  IntegerOverflow,
+  /// A length prefix was larger than the decoder's limit.
+  LengthTooLarge {
+    len: u64,
+    max: usize,
+  },
+  InvalidBool(u8),
+  InvalidChar(u32),
+  InvalidUtf8,
+  /// A byte that should have said which variant follows didn't name one.
+  InvalidTag(u8),
+}
+
+impl fmt::Display for DecodeError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      DecodeError::Io(err) => write!(f, "i/o error: {}", err),
+      DecodeError::UnexpectedEof => write!(f, "unexpected end of input"),
+      DecodeError::VarintOverflow => write!(f, "varint is longer than 64 bits"),
+      DecodeError::IntegerOverflow => write!(f, "integer does not fit in the target type"),
+      DecodeError::LengthTooLarge { len, max } => {
+        write!(f, "length {} is larger than the limit of {}", len, max)
+      }
+      DecodeError::InvalidBool(byte) => write!(f, "invalid bool byte {:#04x}", byte),
+      DecodeError::InvalidChar(code) => write!(f, "invalid char code point {:#x}", code),
+      DecodeError::InvalidUtf8 => write!(f, "string is not valid utf-8"),
+      DecodeError::InvalidTag(tag) => write!(f, "invalid tag byte {:#04x}", tag),
+    }
+  }
+}
+
+impl error::Error for DecodeError {
+  fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+    match self {
+      DecodeError::Io(err) => Some(err),
+      _ => None,
+    }
+  }
+}
+
+impl From<io::Error> for DecodeError {
+  fn from(err: io::Error) -> Self {
+    if err.kind() == io::ErrorKind::UnexpectedEof {
+      DecodeError::UnexpectedEof
+    } else {
+      DecodeError::Io(err)
+    }
+  }
+}
+
+pub trait Encode {
+  fn encode<W: Write + ?Sized>(&self, writer: &mut W) -> io::Result<()>;
+}
+
+pub trait Decode: Sized {
+  fn decode<R: Read>(decoder: &mut Decoder<R>) -> Result<Self, DecodeError>;
+}
+
+pub fn write_varint<W: Write + ?Sized>(writer: &mut W, mut value: u64) -> io::Result<()> {
+  let mut buf = [0; MAX_VARINT_LEN];
+  let mut i = 0;
+
+  loop {
+    let byte = (value & 0x7f) as u8;
+    value >>= 7;
+
+    if value == 0 {
+      buf[i] = byte;
+      i += 1;
+      break;
+    }
+
+    buf[i] = byte | 0x80;
+    i += 1;
+  }
+
+  writer.write_all(&buf[..i])
+}
+
+fn write_len<W: Write + ?Sized>(writer: &mut W, len: usize) -> io::Result<()> {
+  write_varint(writer, len as u64)
+}
+
+fn zigzag_encode(value: i64) -> u64 {
+  ((value << 1) ^ (value >> 63)) as u64
+}
+
+fn zigzag_decode(value: u64) -> i64 {
+  ((value >> 1) as i64) ^ -((value & 1) as i64)
+}
+
+/// Reads values from any `io::Read` one at a time, so nothing is buffered
+/// beyond the value currently being decoded.
+pub struct Decoder<R> {
+  reader: R,
+  max_len: usize,
+}
+
+impl<R: Read> Decoder<R> {
+  pub fn new(reader: R) -> Self {
+    Self::with_max_len(reader, DEFAULT_MAX_LEN)
+  }
+
+  pub fn with_max_len(reader: R, max_len: usize) -> Self {
+    Self { reader, max_len }
+  }
+
+  pub fn into_inner(self) -> R {
+    self.reader
+  }
+
+  pub fn decode<T: Decode>(&mut self) -> Result<T, DecodeError> {
+    T::decode(self)
+  }
+
+  /// Reads the length prefix of a list and returns an iterator that decodes
+  /// its elements lazily.
+  ///
+  /// Dropping the iterator early leaves the reader in the middle of the list.
+  pub fn seq<T: Decode>(&mut self) -> Result<Seq<'_, R, T>, DecodeError> {
+    let remaining = self.read_len()?;
+
+    Ok(Seq {
+      decoder: self,
+      remaining,
+      _p: PhantomData,
+    })
+  }
+
+  pub fn read_u8(&mut self) -> Result<u8, DecodeError> {
+    let mut buf = [0; 1];
+    self.reader.read_exact(&mut buf)?;
+    Ok(buf[0])
+  }
+
+  pub fn read_varint(&mut self) -> Result<u64, DecodeError> {
+    let mut value = 0u64;
+
+    for i in 0..MAX_VARINT_LEN {
+      let byte = self.read_u8()?;
+
+      // The tenth byte only has room for the single remaining bit.
+      if i == MAX_VARINT_LEN - 1 && byte > 1 {
+        return Err(DecodeError::VarintOverflow);
+      }
+
+      value |= u64::from(byte & 0x7f) << (7 * i);
+
+      if byte & 0x80 == 0 {
+        return Ok(value);
+      }
+    }
+
+    Err(DecodeError::VarintOverflow)
+  }
+
+  /// Reads a length prefix, rejecting it if it is over the decoder's limit.
+  pub fn read_len(&mut self) -> Result<usize, DecodeError> {
+    let len = self.read_varint()?;
+
+    if len > self.max_len as u64 {
+      return Err(DecodeError::LengthTooLarge {
+        len,
+        max: self.max_len,
+      });
+    }
+
+    Ok(len as usize)
+  }
+
+  fn read_array<const N: usize>(&mut self) -> Result<[u8; N], DecodeError> {
+    let mut buf = [0; N];
+    self.reader.read_exact(&mut buf)?;
+    Ok(buf)
+  }
+
+  fn read_bytes(&mut self, len: usize) -> Result<Vec<u8>, DecodeError> {
+    // Let the buffer grow as the bytes actually arrive instead of trusting
+    // the length prefix with an up-front allocation.
+    let mut buf = Vec::new();
+    (&mut self.reader).take(len as u64).read_to_end(&mut buf)?;
+
+    if buf.len() != len {
+      return Err(DecodeError::UnexpectedEof);
+    }
+
+    Ok(buf)
+  }
+}
+
+pub struct Seq<'a, R, T> {
+  decoder: &'a mut Decoder<R>,
+  remaining: usize,
+  _p: PhantomData<T>,
+}
+
+impl<'a, R: Read, T: Decode> Iterator for Seq<'a, R, T> {
+  type Item = Result<T, DecodeError>;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    if self.remaining == 0 {
+      return None;
+    }
+
+    let item = T::decode(self.decoder);
+
+    // Stop at the first error, the rest of the stream can't be trusted.
+    self.remaining = if item.is_ok() { self.remaining - 1 } else { 0 };
+
+    Some(item)
+  }
+
+  fn size_hint(&self) -> (usize, Option<usize>) {
+    (0, Some(self.remaining))
+  }
+}
+
+impl Encode for u8 {
+  fn encode<W: Write + ?Sized>(&self, writer: &mut W) -> io::Result<()> {
+    writer.write_all(&[*self])
+  }
+}
+
+impl Decode for u8 {
+  fn decode<R: Read>(decoder: &mut Decoder<R>) -> Result<Self, DecodeError> {
+    decoder.read_u8()
+  }
+}
+
+impl Encode for i8 {
+  fn encode<W: Write + ?Sized>(&self, writer: &mut W) -> io::Result<()> {
+    writer.write_all(&self.to_le_bytes())
+  }
+}
+
+impl Decode for i8 {
+  fn decode<R: Read>(decoder: &mut Decoder<R>) -> Result<Self, DecodeError> {
+    Ok(i8::from_le_bytes(decoder.read_array()?))
+  }
+}
+
+macro_rules! impl_unsigned {
+  ($($t:ty),*) => {
+    $(
+      impl Encode for $t {
+        fn encode<W: Write + ?Sized>(&self, writer: &mut W) -> io::Result<()> {
+          write_varint(writer, *self as u64)
+        }
+      }
+
+      impl Decode for $t {
+        fn decode<R: Read>(decoder: &mut Decoder<R>) -> Result<Self, DecodeError> {
+          <$t>::try_from(decoder.read_varint()?).map_err(|_| DecodeError::IntegerOverflow)
+        }
+      }
+    )*
+  };
+}
+
+macro_rules! impl_signed {
+  ($($t:ty),*) => {
+    $(
+      impl Encode for $t {
+        fn encode<W: Write + ?Sized>(&self, writer: &mut W) -> io::Result<()> {
+          write_varint(writer, zigzag_encode(*self as i64))
+        }
+      }
+
+      impl Decode for $t {
+        fn decode<R: Read>(decoder: &mut Decoder<R>) -> Result<Self, DecodeError> {
+          let value = zigzag_decode(decoder.read_varint()?);
+          <$t>::try_from(value).map_err(|_| DecodeError::IntegerOverflow)
+        }
+      }
+    )*
+  };
+}
+
+macro_rules! impl_float {
+  ($($t:ty),*) => {
+    $(
+      impl Encode for $t {
+        fn encode<W: Write + ?Sized>(&self, writer: &mut W) -> io::Result<()> {
+          writer.write_all(&self.to_le_bytes())
+        }
+      }
+
+      impl Decode for $t {
+        fn decode<R: Read>(decoder: &mut Decoder<R>) -> Result<Self, DecodeError> {
+          Ok(<$t>::from_le_bytes(decoder.read_array()?))
+        }
+      }
+    )*
+  };
+}
+
+impl_unsigned!(u16, u32, u64, usize);
+impl_signed!(i16, i32, i64, isize);
+impl_float!(f32, f64);
+
+impl Encode for bool {
+  fn encode<W: Write + ?Sized>(&self, writer: &mut W) -> io::Result<()> {
+    writer.write_all(&[*self as u8])
+  }
+}
+
+impl Decode for bool {
+  fn decode<R: Read>(decoder: &mut Decoder<R>) -> Result<Self, DecodeError> {
+    match decoder.read_u8()? {
+      0 => Ok(false),
+      1 => Ok(true),
+      byte => Err(DecodeError::InvalidBool(byte)),
+    }
+  }
+}
+
+impl Encode for char {
+  fn encode<W: Write + ?Sized>(&self, writer: &mut W) -> io::Result<()> {
+    write_varint(writer, u64::from(*self))
+  }
+}
+
+impl Decode for char {
+  fn decode<R: Read>(decoder: &mut Decoder<R>) -> Result<Self, DecodeError> {
+    let code = u32::decode(decoder)?;
+    char::from_u32(code).ok_or(DecodeError::InvalidChar(code))
+  }
+}
+
+impl Encode for str {
+  fn encode<W: Write + ?Sized>(&self, writer: &mut W) -> io::Result<()> {
+    write_len(writer, self.len())?;
+    writer.write_all(self.as_bytes())
+  }
+}
+
+impl Encode for String {
+  fn encode<W: Write + ?Sized>(&self, writer: &mut W) -> io::Result<()> {
+    self.as_str().encode(writer)
+  }
+}
+
+impl Decode for String {
+  fn decode<R: Read>(decoder: &mut Decoder<R>) -> Result<Self, DecodeError> {
+    let len = decoder.read_len()?;
+    let bytes = decoder.read_bytes(len)?;
+    String::from_utf8(bytes).map_err(|_| DecodeError::InvalidUtf8)
+  }
+}
+
+impl<T: Encode + ?Sized> Encode for &T {
+  fn encode<W: Write + ?Sized>(&self, writer: &mut W) -> io::Result<()> {
+    (**self).encode(writer)
+  }
+}
+
+impl<T: Encode> Encode for LinkedList<T> {
+  fn encode<W: Write + ?Sized>(&self, writer: &mut W) -> io::Result<()> {
+    write_len(writer, self.len())?;
+
+    for item in self.iter() {
+      item.encode(writer)?;
+    }
+
+    Ok(())
+  }
+}
+
+impl<T: Decode> Decode for LinkedList<T> {
+  fn decode<R: Read>(decoder: &mut Decoder<R>) -> Result<Self, DecodeError> {
+    decoder.seq()?.collect()
+  }
+}
+
+/// Elements are written from the top of the stack down.
+impl<T: Encode> Encode for second::List<T> {
+  fn encode<W: Write + ?Sized>(&self, writer: &mut W) -> io::Result<()> {
+    write_len(writer, self.iter().count())?;
+
+    for item in self.iter() {
+      item.encode(writer)?;
+    }
+
+    Ok(())
+  }
+}
+
+impl<T: Decode> Decode for second::List<T> {
+  fn decode<R: Read>(decoder: &mut Decoder<R>) -> Result<Self, DecodeError> {
+    // Pushing the elements as they arrive leaves them upside down,
+    // so they have to be flipped once more at the end.
+    let mut reversed = second::List::new();
+
+    for item in decoder.seq()? {
+      reversed.push(item?);
+    }
+
+    let mut list = second::List::new();
+
+    while let Some(item) = reversed.pop() {
+      list.push(item);
+    }
+
+    Ok(list)
+  }
+}
+
+/// Elements are written from the front of the queue to the back.
+impl<T: Encode> Encode for sixth::List<T> {
+  fn encode<W: Write + ?Sized>(&self, writer: &mut W) -> io::Result<()> {
+    write_len(writer, self.iter().count())?;
+
+    for item in self.iter() {
+      item.encode(writer)?;
+    }
+
+    Ok(())
+  }
+}
+
+impl<T: Decode> Decode for sixth::List<T> {
+  fn decode<R: Read>(decoder: &mut Decoder<R>) -> Result<Self, DecodeError> {
+    let mut list = sixth::List::new();
+
+    for item in decoder.seq()? {
+      list.push(item?);
+    }
+
+    Ok(list)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::trickle::Trickle;
+
+  fn encode<T: Encode + ?Sized>(value: &T) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    value.encode(&mut bytes).unwrap();
+    bytes
+  }
+
+  fn round_trip<T: Encode + Decode>(value: &T) -> T {
+    let bytes = encode(value);
+    let mut decoder = Decoder::new(bytes.as_slice());
+    let decoded = decoder.decode().unwrap();
+    assert!(decoder.into_inner().is_empty());
+    decoded
+  }
+
+  #[test]
+  fn varint() {
+    assert_eq!(vec![0x00], encode(&0u64));
+    assert_eq!(vec![0x7f], encode(&127u64));
+    assert_eq!(vec![0x80, 0x01], encode(&128u64));
+    assert_eq!(vec![0xac, 0x02], encode(&300u64));
+    assert_eq!(10, encode(&u64::MAX).len());
+
+    assert_eq!(u64::MAX, round_trip(&u64::MAX));
+  }
+
+  #[test]
+  fn primitives() {
+    assert_eq!(vec![0x01], encode(&-1i32));
+    assert_eq!(vec![0x02], encode(&1i32));
+
+    assert_eq!(i64::MIN, round_trip(&i64::MIN));
+    assert_eq!(i64::MAX, round_trip(&i64::MAX));
+    assert_eq!(-128i8, round_trip(&-128i8));
+    assert_eq!(255u8, round_trip(&255u8));
+    assert_eq!(u16::MAX, round_trip(&u16::MAX));
+    assert_eq!(1.5f32, round_trip(&1.5f32));
+    assert!(round_trip(&f64::NAN).is_nan());
+    assert!(round_trip(&true));
+    assert_eq!('ü', round_trip(&'ü'));
+    assert_eq!("hello, wörld", round_trip(&String::from("hello, wörld")));
+  }
+
+  #[test]
+  fn production_linked_list() {
+    let list: LinkedList<i32> = (-5..5).collect();
+    assert_eq!(list, round_trip(&list));
+
+    let empty: LinkedList<String> = LinkedList::new();
+    assert_eq!(empty, round_trip(&empty));
+  }
+
+  #[test]
+  fn nested_lists() {
+    let list: LinkedList<LinkedList<String>> = (0..3)
+      .map(|i| (0..i).map(|j| j.to_string()).collect())
+      .collect();
+
+    assert_eq!(list, round_trip(&list));
+  }
+
+  #[test]
+  fn second_list_keeps_stack_order() {
+    let mut list = second::List::new();
+    list.push(1);
+    list.push(2);
+    list.push(3);
+
+    let decoded = round_trip(&list);
+
+    assert_eq!(vec![&3, &2, &1], decoded.iter().collect::<Vec<_>>());
+  }
+
+  #[test]
+  fn sixth_list_keeps_queue_order() {
+    let mut list = sixth::List::new();
+    list.push("a".to_string());
+    list.push("b".to_string());
+
+    let mut decoded = round_trip(&list);
+
+    assert_eq!(Some("a".to_string()), decoded.pop());
+    assert_eq!(Some("b".to_string()), decoded.pop());
+    assert_eq!(None, decoded.pop());
+  }
+
+  #[test]
+  fn decodes_one_byte_at_a_time() {
+    let list: LinkedList<u64> = [1, 1000, u64::MAX].into_iter().collect();
+    let bytes = encode(&list);
+
+    let mut decoder = Decoder::new(Trickle(&bytes));
+    let mut seq = decoder.seq::<u64>().unwrap();
+
+    assert_eq!(3, seq.size_hint().1.unwrap());
+    assert_eq!(1, seq.next().unwrap().unwrap());
+    assert_eq!(1000, seq.next().unwrap().unwrap());
+    assert_eq!(u64::MAX, seq.next().unwrap().unwrap());
+    assert!(seq.next().is_none());
+  }
+
+  #[test]
+  fn several_values_in_one_stream() {
+    let mut bytes = Vec::new();
+    "header".encode(&mut bytes).unwrap();
+    (0..4)
+      .collect::<LinkedList<u8>>()
+      .encode(&mut bytes)
+      .unwrap();
+    7u32.encode(&mut bytes).unwrap();
+
+    let mut decoder = Decoder::new(bytes.as_slice());
+
+    assert_eq!("header", decoder.decode::<String>().unwrap());
+    assert_eq!(4, decoder.decode::<LinkedList<u8>>().unwrap().len());
+    assert_eq!(7, decoder.decode::<u32>().unwrap());
+    assert!(matches!(
+      decoder.decode::<u32>(),
+      Err(DecodeError::UnexpectedEof)
+    ));
+  }
+
+  #[test]
+  fn rejects_truncated_input() {
+    let list: LinkedList<String> = ["one", "two"].iter().map(|s| s.to_string()).collect();
+    let bytes = encode(&list);
+
+    for end in 0..bytes.len() {
+      let result = Decoder::new(&bytes[..end]).decode::<LinkedList<String>>();
+      assert!(
+        matches!(result, Err(DecodeError::UnexpectedEof)),
+        "prefix of {} bytes",
+        end
+      );
+    }
+  }
+
+  #[test]
+  fn rejects_oversized_input() {
+    let list: LinkedList<u8> = (0..10).collect();
+    let bytes = encode(&list);
+
+    let result = Decoder::with_max_len(bytes.as_slice(), 9).decode::<LinkedList<u8>>();
+    assert!(matches!(
+      result,
+      Err(DecodeError::LengthTooLarge { len: 10, max: 9 })
+    ));
+
+    // A huge length prefix must fail before anything is allocated.
+    let mut bytes = Vec::new();
+    write_varint(&mut bytes, u64::MAX).unwrap();
+    let result = Decoder::new(bytes.as_slice()).decode::<String>();
+    assert!(matches!(result, Err(DecodeError::LengthTooLarge { .. })));
+  }
+
+  #[test]
+  fn rejects_invalid_values() {
+    let overlong = [0xff; 11];
+    assert!(matches!(
+      Decoder::new(&overlong[..]).decode::<u64>(),
+      Err(DecodeError::VarintOverflow)
+    ));
+
+    let too_big = encode(&(u64::from(u16::MAX) + 1));
+    assert!(matches!(
+      Decoder::new(too_big.as_slice()).decode::<u16>(),
+      Err(DecodeError::IntegerOverflow)
+    ));
+
+    assert!(matches!(
+      Decoder::new(&[2u8][..]).decode::<bool>(),
+      Err(DecodeError::InvalidBool(2))
+    ));
+
+    let surrogate = encode(&0xd800u32);
+    assert!(matches!(
+      Decoder::new(surrogate.as_slice()).decode::<char>(),
+      Err(DecodeError::InvalidChar(0xd800))
+    ));
+
+    let bad_utf8 = [2u8, 0xc3, 0x28];
+    assert!(matches!(
+      Decoder::new(&bad_utf8[..]).decode::<String>(),
+      Err(DecodeError::InvalidUtf8)
+    ));
+  }
+}
@@ -0,0 +1,524 @@
+//! Diffing two lists into an [`EditScript`], and patching a list with one.
+//!
+//! [`diff`] is Myers' O(ND) algorithm, so lists that are nearly the same are
+//! cheap to compare. The script only carries the inserted elements, with the
+//! kept and deleted parts written as counts, which makes it a small thing to
+//! send to a replica holding the old list. It can be sent with the
+//! [`codec`](crate::codec) format.
+//!
+//! ```
+//! use learn_rust_with_entirely_too_many_linked_lists::{
+//!   diff::{apply, diff},
+//!   production_linked_list::LinkedList,
+//! };
+//!
+//! let old: LinkedList<char> = "abcabba".chars().collect();
+//! let new: LinkedList<char> = "cbabac".chars().collect();
+//!
+//! let script = diff(&old, &new);
+//! assert_eq!(5, script.distance());
+//!
+//! let mut replica: LinkedList<char> = "abcabba".chars().collect();
+//! apply(&mut replica, script).unwrap();
+//! assert_eq!(new, replica);
+//! ```
+
+use std::{
+  error, fmt,
+  io::{self, Read, Write},
+};
+
+use crate::{
+  codec::{Decode, DecodeError, Decoder, Encode},
+  production_linked_list::LinkedList,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Edit<T> {
+  /// Leave this many elements as they are.
+  Keep(usize),
+  /// Put these elements in.
+  Insert(LinkedList<T>),
+  /// Remove this many elements.
+  Delete(usize),
+}
+
+/// The edits that turn one list into another, front to back. Consecutive
+/// edits are always of different kinds, and none are empty.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct EditScript<T> {
+  edits: Vec<Edit<T>>,
+}
+
+impl<T> EditScript<T> {
+  pub fn edits(&self) -> &[Edit<T>] {
+    &self.edits
+  }
+
+  pub fn into_edits(self) -> Vec<Edit<T>> {
+    self.edits
+  }
+
+  /// Number of elements inserted plus number deleted.
+  pub fn distance(&self) -> usize {
+    self
+      .edits
+      .iter()
+      .map(|edit| match edit {
+        Edit::Keep(_) => 0,
+        Edit::Insert(elems) => elems.len(),
+        Edit::Delete(n) => *n,
+      })
+      .sum()
+  }
+
+  /// Whether applying the script would leave the list as it is.
+  pub fn is_identity(&self) -> bool {
+    self.distance() == 0
+  }
+
+  /// Length of the list this script was made from.
+  pub fn source_len(&self) -> usize {
+    self
+      .edits
+      .iter()
+      .map(|edit| match edit {
+        Edit::Keep(n) | Edit::Delete(n) => *n,
+        Edit::Insert(_) => 0,
+      })
+      .sum()
+  }
+
+  /// Adds an edit, merging it into the last one if they're the same kind.
+  fn push(&mut self, edit: Edit<T>) {
+    match (self.edits.last_mut(), edit) {
+      (_, Edit::Keep(0) | Edit::Delete(0)) => {}
+      (_, Edit::Insert(elems)) if elems.is_empty() => {}
+      (Some(Edit::Keep(last)), Edit::Keep(n)) => *last += n,
+      (Some(Edit::Delete(last)), Edit::Delete(n)) => *last += n,
+      // A fresh cursor sits on the ghost, so this splices onto the back.
+      (Some(Edit::Insert(last)), Edit::Insert(elems)) => last.cursor_mut().splice_before(elems),
+      (_, edit) => self.edits.push(edit),
+    }
+  }
+}
+
+/// One step of the shortest edit path, before it's grouped into runs.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Step {
+  Keep,
+  /// Insert `b[index]`.
+  Insert(usize),
+  Delete,
+}
+
+/// The edits that turn `a` into `b`, with as few insertions and deletions
+/// as there can be.
+pub fn diff<T: Eq + Clone>(a: &LinkedList<T>, b: &LinkedList<T>) -> EditScript<T> {
+  let a: Vec<&T> = a.iter().collect();
+  let b: Vec<&T> = b.iter().collect();
+
+  let mut script = EditScript { edits: Vec::new() };
+
+  for step in shortest_edit(&a, &b) {
+    script.push(match step {
+      Step::Keep => Edit::Keep(1),
+      Step::Delete => Edit::Delete(1),
+      Step::Insert(index) => {
+        let mut elem = LinkedList::new();
+        elem.push_back(b[index].clone());
+        Edit::Insert(elem)
+      }
+    });
+  }
+
+  script
+}
+
+/// Myers' greedy algorithm. `v[k]` is the furthest `x` reached on diagonal
+/// `k = x - y` with `d` edits. After each `d` the `2d + 1` diagonals it could
+/// reach are kept so the path can be walked back afterwards, which is
+/// O(D^2) memory rather than a copy of all of `v` every time.
+fn shortest_edit<T: Eq>(a: &[T], b: &[T]) -> Vec<Step> {
+  let (n, m) = (a.len() as isize, b.len() as isize);
+  let max = n + m;
+  // Diagonals run from -max to max, with one spare on each side for the
+  // `k - 1` and `k + 1` lookups.
+  let offset = max + 1;
+  let at = |k: isize| (k + offset) as usize;
+
+  let mut v = vec![0isize; 2 * max as usize + 3];
+  let mut trace = Vec::new();
+
+  'search: for d in 0..=max {
+    for k in (-d..=d).step_by(2) {
+      let mut x = if k == -d || (k != d && v[at(k - 1)] < v[at(k + 1)]) {
+        // Down from diagonal k + 1, an insertion.
+        v[at(k + 1)]
+      } else {
+        // Right from diagonal k - 1, a deletion.
+        v[at(k - 1)] + 1
+      };
+      let mut y = x - k;
+
+      while x < n && y < m && a[x as usize] == b[y as usize] {
+        x += 1;
+        y += 1;
+      }
+
+      v[at(k)] = x;
+
+      if x >= n && y >= m {
+        break 'search;
+      }
+    }
+
+    trace.push(v[at(-d)..=at(d)].to_vec());
+  }
+
+  let mut steps = Vec::new();
+  let (mut x, mut y) = (n, m);
+
+  for d in (0..=trace.len() as isize).rev() {
+    // Where each diagonal had got to after `d - 1` edits. Before any, that's
+    // the start, which the search sees as diagonal 1 at `x = 0`.
+    let v = |k: isize| match d {
+      0 => 0,
+      d => trace[d as usize - 1][(k + d - 1) as usize],
+    };
+    let k = x - y;
+
+    let prev_k = if k == -d || (k != d && v(k - 1) < v(k + 1)) {
+      k + 1
+    } else {
+      k - 1
+    };
+    let prev_x = v(prev_k);
+    let prev_y = prev_x - prev_k;
+
+    while x > prev_x && y > prev_y {
+      steps.push(Step::Keep);
+      x -= 1;
+      y -= 1;
+    }
+
+    if d > 0 {
+      if x == prev_x {
+        steps.push(Step::Insert(prev_y as usize));
+      } else {
+        steps.push(Step::Delete);
+      }
+    }
+
+    x = prev_x;
+    y = prev_y;
+  }
+
+  steps.reverse();
+  steps
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ApplyError {
+  /// Elements the script keeps or deletes, which is how long the list it
+  /// was made from was.
+  pub expected_len: usize,
+  pub actual_len: usize,
+}
+
+impl fmt::Display for ApplyError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(
+      f,
+      "edit script is for a list of {} elements, not {}",
+      self.expected_len, self.actual_len
+    )
+  }
+}
+
+impl error::Error for ApplyError {}
+
+/// Edits `list` as `script` says. Kept nodes stay where they are and the
+/// inserted runs are spliced in whole, so nothing is reallocated.
+///
+/// The only check is that the list is as long as the one the script was
+/// made from, which happens before anything is touched.
+pub fn apply<T>(list: &mut LinkedList<T>, script: EditScript<T>) -> Result<(), ApplyError> {
+  if script.source_len() != list.len() {
+    return Err(ApplyError {
+      expected_len: script.source_len(),
+      actual_len: list.len(),
+    });
+  }
+
+  let mut cursor = list.cursor_mut();
+  cursor.move_next();
+
+  for edit in script.edits {
+    match edit {
+      Edit::Keep(n) => {
+        for _ in 0..n {
+          cursor.move_next();
+        }
+      }
+      // Before the cursor is the right place even on the ghost, where it
+      // means the back.
+      Edit::Insert(elems) => cursor.splice_before(elems),
+      Edit::Delete(n) => {
+        // Set aside what's done, so the run to delete starts at the front
+        // and splitting in front of its end cuts off just the run.
+        let done = cursor.split_before();
+
+        for _ in 0..n {
+          cursor.move_next();
+        }
+
+        drop(cursor.split_before());
+
+        // At the front, or on the ghost of a now empty list, this puts
+        // `done` back at the front either way.
+        cursor.splice_before(done);
+      }
+    }
+  }
+
+  Ok(())
+}
+
+const KEEP: u8 = 0;
+const INSERT: u8 = 1;
+const DELETE: u8 = 2;
+
+impl<T: Encode> Encode for EditScript<T> {
+  fn encode<W: Write + ?Sized>(&self, writer: &mut W) -> io::Result<()> {
+    self.edits.len().encode(writer)?;
+
+    for edit in self.edits.iter() {
+      match edit {
+        Edit::Keep(n) => {
+          KEEP.encode(writer)?;
+          n.encode(writer)?;
+        }
+        Edit::Insert(elems) => {
+          INSERT.encode(writer)?;
+          elems.encode(writer)?;
+        }
+        Edit::Delete(n) => {
+          DELETE.encode(writer)?;
+          n.encode(writer)?;
+        }
+      }
+    }
+
+    Ok(())
+  }
+}
+
+impl<T: Decode> Decode for EditScript<T> {
+  fn decode<R: Read>(decoder: &mut Decoder<R>) -> Result<Self, DecodeError> {
+    let len = decoder.read_len()?;
+    let mut script = EditScript { edits: Vec::new() };
+    // The counts come from the input. If these totals fit, so do merged
+    // runs, `source_len` and `distance`.
+    let (mut source_len, mut distance) = (0usize, 0usize);
+
+    for _ in 0..len {
+      let edit = match decoder.read_u8()? {
+        KEEP => Edit::Keep(decoder.decode()?),
+        INSERT => Edit::Insert(decoder.decode()?),
+        DELETE => Edit::Delete(decoder.decode()?),
+        tag => return Err(DecodeError::InvalidTag(tag)),
+      };
+
+      let (source, changed) = match &edit {
+        Edit::Keep(n) => (*n, 0),
+        Edit::Insert(elems) => (0, elems.len()),
+        Edit::Delete(n) => (*n, *n),
+      };
+      source_len = source_len
+        .checked_add(source)
+        .ok_or(DecodeError::IntegerOverflow)?;
+      distance = distance
+        .checked_add(changed)
+        .ok_or(DecodeError::IntegerOverflow)?;
+
+      // Whatever was sent, keep the script in its normal form.
+      script.push(edit);
+    }
+
+    Ok(script)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn list(s: &str) -> LinkedList<char> {
+    s.chars().collect()
+  }
+
+  fn check(a: &str, b: &str) -> EditScript<char> {
+    let script = diff(&list(a), &list(b));
+
+    let mut patched = list(a);
+    apply(&mut patched, script.clone()).unwrap();
+    assert_eq!(list(b), patched, "{:?} -> {:?}", a, b);
+    patched.check_invariants();
+
+    script
+  }
+
+  #[test]
+  fn myers_example() {
+    let script = check("abcabba", "cbabac");
+
+    assert_eq!(5, script.distance());
+    assert_eq!(7, script.source_len());
+  }
+
+  #[test]
+  fn edge_cases() {
+    assert!(check("", "").edits().is_empty());
+    assert_eq!(check("", "abc").edits(), &[Edit::Insert(list("abc"))]);
+    assert_eq!(check("abc", "").edits(), &[Edit::Delete(3)]);
+    assert_eq!(check("abc", "abc").edits(), &[Edit::Keep(3)]);
+    assert!(check("abc", "abc").is_identity());
+    assert_eq!(
+      check("abc", "xyz").distance(),
+      6,
+      "nothing in common means replacing everything"
+    );
+
+    check("abcd", "bc");
+    check("bc", "abcd");
+    check("abc", "aXc");
+    check("aaaa", "aa");
+  }
+
+  #[test]
+  fn runs_are_merged() {
+    let script = check("abcdef", "abXYef");
+
+    let deletes = script
+      .edits()
+      .iter()
+      .filter(|edit| matches!(edit, Edit::Delete(_)))
+      .count();
+    assert_eq!(1, deletes);
+
+    for pair in script.edits().windows(2) {
+      assert_ne!(
+        std::mem::discriminant(&pair[0]),
+        std::mem::discriminant(&pair[1])
+      );
+    }
+  }
+
+  /// Length of the longest common subsequence, the slow way.
+  fn lcs(a: &str, b: &str) -> usize {
+    let (a, b): (Vec<char>, Vec<char>) = (a.chars().collect(), b.chars().collect());
+    let mut table = vec![vec![0; b.len() + 1]; a.len() + 1];
+
+    for i in 0..a.len() {
+      for j in 0..b.len() {
+        table[i + 1][j + 1] = if a[i] == b[j] {
+          table[i][j] + 1
+        } else {
+          table[i][j + 1].max(table[i + 1][j])
+        };
+      }
+    }
+
+    table[a.len()][b.len()]
+  }
+
+  #[test]
+  fn every_pair_of_short_strings() {
+    let words = [
+      "", "a", "b", "ab", "ba", "aab", "abb", "bab", "abab", "bbaa",
+    ];
+
+    for a in words {
+      for b in words {
+        let script = check(a, b);
+        // Shortest means keeping a longest common subsequence.
+        assert_eq!(a.len() + b.len() - 2 * lcs(a, b), script.distance());
+      }
+    }
+  }
+
+  #[test]
+  fn reuses_kept_nodes() {
+    let mut patched: LinkedList<i32> = (0..6).collect();
+    let target: LinkedList<i32> = [0, 1, 9, 4, 5].into_iter().collect();
+    let kept: Vec<*const i32> = patched.iter().map(|x| x as *const i32).collect();
+
+    apply(&mut patched, diff(&(0..6).collect(), &target)).unwrap();
+
+    assert_eq!(target, patched);
+    let after: Vec<*const i32> = patched.iter().map(|x| x as *const i32).collect();
+    assert_eq!(kept[..2], after[..2]);
+    assert_eq!(kept[4..], after[3..]);
+  }
+
+  #[test]
+  fn rejects_a_list_of_the_wrong_length() {
+    let script = diff(&list("abc"), &list("abd"));
+    let mut other = list("ab");
+
+    let err = apply(&mut other, script).unwrap_err();
+
+    assert_eq!(
+      ApplyError {
+        expected_len: 3,
+        actual_len: 2
+      },
+      err
+    );
+    assert_eq!(list("ab"), other);
+  }
+
+  #[test]
+  fn round_trips_through_the_codec() {
+    let script = diff(&list("the quick fox"), &list("a quick brown fox"));
+
+    let mut bytes = Vec::new();
+    script.encode(&mut bytes).unwrap();
+    let decoded: EditScript<char> = Decoder::new(bytes.as_slice()).decode().unwrap();
+    assert_eq!(script, decoded);
+
+    let mut bad = bytes.clone();
+    bad[1] = 7;
+    assert!(matches!(
+      Decoder::new(bad.as_slice()).decode::<EditScript<char>>(),
+      Err(DecodeError::InvalidTag(7))
+    ));
+  }
+
+  #[test]
+  fn decode_rejects_counts_that_overflow() {
+    let script: EditScript<char> = EditScript {
+      edits: vec![Edit::Keep(usize::MAX), Edit::Delete(1)],
+    };
+    let mut bytes = Vec::new();
+    script.encode(&mut bytes).unwrap();
+
+    assert!(matches!(
+      Decoder::new(bytes.as_slice()).decode::<EditScript<char>>(),
+      Err(DecodeError::IntegerOverflow)
+    ));
+
+    // Two runs that would be merged into one.
+    let script: EditScript<char> = EditScript {
+      edits: vec![Edit::Keep(usize::MAX), Edit::Keep(usize::MAX)],
+    };
+    let mut bytes = Vec::new();
+    script.encode(&mut bytes).unwrap();
+
+    assert!(matches!(
+      Decoder::new(bytes.as_slice()).decode::<EditScript<char>>(),
+      Err(DecodeError::IntegerOverflow)
+    ));
+  }
+}
@@ -0,0 +1,1171 @@
+//! A doubly linked list that lives in a file, so it outlasts the process.
+//!
+//! The file is a header followed by equal sized slots, each holding one
+//! node: its `front` and `back` links, written as the offsets of the slots
+//! they point to, and its element in the [`codec`](crate::codec) format.
+//! Offset 0 is the header, so it doubles as the null link. Popped nodes go on
+//! a free list threaded through their `back` links and get reused before the
+//! file grows.
+//!
+//! Every change writes its nodes and the header in an order where following
+//! the `back` links from the header's `front` always gives the list either
+//! before or after the change. So if the process dies halfway through one,
+//! [`DiskList::open`] notices the rest doesn't add up, rebuilds everything
+//! else from that chain and carries on. If a write fails instead, the handle
+//! refuses any more changes, and reopening the file does the same fix up.
+//!
+//! That ordering only holds against the process dying, not the machine:
+//! nothing is synced between the writes, so after a power cut the disk may
+//! have kept some later write and lost an earlier one. Only what was there
+//! when [`DiskList::sync`] last returned is safe from that.
+//!
+//! ```no_run
+//! use learn_rust_with_entirely_too_many_linked_lists::disk_list::DiskList;
+//!
+//! let mut jobs = DiskList::create("jobs.list", 64)?;
+//! jobs.push_back(String::from("resize photos"))?;
+//! jobs.push_back(String::from("send emails"))?;
+//! jobs.sync()?;
+//! drop(jobs);
+//!
+//! let mut jobs = DiskList::<String>::open("jobs.list")?;
+//! assert_eq!(Some(String::from("resize photos")), jobs.pop_front()?);
+//! # Ok::<(), learn_rust_with_entirely_too_many_linked_lists::disk_list::DiskListError>(())
+//! ```
+
+use std::{
+  collections::HashSet,
+  error, fmt,
+  fs::{File, OpenOptions},
+  io::{self, Read, Seek, SeekFrom, Write},
+  marker::PhantomData,
+  path::Path,
+};
+
+use crate::codec::{Decode, DecodeError, Decoder, Encode};
+
+const MAGIC: &[u8; 8] = b"LLDISK\0\x01";
+
+/// Magic, slot capacity, `front`, `back`, `len`, `free`, `end` and a
+/// checksum of all that.
+const HEADER_LEN: u64 = 64;
+
+/// Tag, `front`, `back` and the length of the element.
+const NODE_HEADER_LEN: u64 = 21;
+
+const LIVE: u8 = 1;
+const FREE: u8 = 2;
+
+#[derive(Debug)]
+pub enum DiskListError {
+  Io(io::Error),
+  Decode(DecodeError),
+  /// The file isn't a list, or is broken in a way no crash would leave it.
+  Corrupt(String),
+  /// An element encoded to more bytes than a slot holds.
+  ElementTooLarge {
+    len: usize,
+    capacity: usize,
+  },
+  /// A write failed partway through an earlier change, so the file may not
+  /// match what the handle thinks it holds. Reopen it to have it checked.
+  Poisoned,
+}
+
+impl fmt::Display for DiskListError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      DiskListError::Io(err) => write!(f, "i/o error: {}", err),
+      DiskListError::Decode(err) => write!(f, "bad element: {}", err),
+      DiskListError::Corrupt(message) => write!(f, "corrupt list file: {}", message),
+      DiskListError::ElementTooLarge { len, capacity } => write!(
+        f,
+        "element takes {} bytes but slots only hold {}",
+        len, capacity
+      ),
+      DiskListError::Poisoned => write!(f, "an earlier write failed, reopen the list"),
+    }
+  }
+}
+
+impl error::Error for DiskListError {
+  fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+    match self {
+      DiskListError::Io(err) => Some(err),
+      DiskListError::Decode(err) => Some(err),
+      _ => None,
+    }
+  }
+}
+
+impl From<io::Error> for DiskListError {
+  fn from(err: io::Error) -> Self {
+    DiskListError::Io(err)
+  }
+}
+
+impl From<DecodeError> for DiskListError {
+  fn from(err: DecodeError) -> Self {
+    DiskListError::Decode(err)
+  }
+}
+
+type Result<T> = std::result::Result<T, DiskListError>;
+
+fn corrupt<T>(message: String) -> Result<T> {
+  Err(DiskListError::Corrupt(message))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Header {
+  capacity: u32,
+  front: u64,
+  back: u64,
+  len: u64,
+  /// First slot of the free list.
+  free: u64,
+  /// Where the next new slot goes.
+  end: u64,
+}
+
+impl Header {
+  fn to_bytes(self) -> [u8; HEADER_LEN as usize] {
+    let mut bytes = [0; HEADER_LEN as usize];
+    bytes[0..8].copy_from_slice(MAGIC);
+    bytes[8..12].copy_from_slice(&self.capacity.to_le_bytes());
+    for (i, field) in [self.front, self.back, self.len, self.free, self.end]
+      .into_iter()
+      .enumerate()
+    {
+      bytes[16 + i * 8..24 + i * 8].copy_from_slice(&field.to_le_bytes());
+    }
+    let checksum = fnv1a(&bytes[..56]);
+    bytes[56..64].copy_from_slice(&checksum.to_le_bytes());
+    bytes
+  }
+
+  fn from_bytes(bytes: &[u8; HEADER_LEN as usize]) -> Result<Self> {
+    if &bytes[0..8] != MAGIC {
+      return corrupt(String::from("not a list file"));
+    }
+    if fnv1a(&bytes[..56]) != u64_at(bytes, 56) {
+      return corrupt(String::from("header checksum doesn't match"));
+    }
+
+    Ok(Self {
+      capacity: u32::from_le_bytes(bytes[8..12].try_into().unwrap()),
+      front: u64_at(bytes, 16),
+      back: u64_at(bytes, 24),
+      len: u64_at(bytes, 32),
+      free: u64_at(bytes, 40),
+      end: u64_at(bytes, 48),
+    })
+  }
+}
+
+fn u64_at(bytes: &[u8], at: usize) -> u64 {
+  u64::from_le_bytes(bytes[at..at + 8].try_into().unwrap())
+}
+
+fn fnv1a(bytes: &[u8]) -> u64 {
+  bytes.iter().fold(0xcbf29ce484222325, |hash, &byte| {
+    (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+  })
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Node {
+  tag: u8,
+  front: u64,
+  back: u64,
+  elem_len: u32,
+}
+
+pub struct DiskList<T> {
+  file: File,
+  header: Header,
+  recovered: bool,
+  /// Set when a write fails, since the file might then be a step ahead of
+  /// `header`.
+  poisoned: bool,
+  /// Number of writes left before they all start failing, to fake a crash.
+  #[cfg(test)]
+  writes_left: Option<usize>,
+  _p: PhantomData<T>,
+}
+
+impl<T: Encode + Decode> DiskList<T> {
+  /// Makes a new empty list at `path`, replacing anything already there.
+  /// Each element can take up to `slot_capacity` bytes once encoded.
+  pub fn create<P: AsRef<Path>>(path: P, slot_capacity: usize) -> Result<Self> {
+    let capacity = u32::try_from(slot_capacity).map_err(|_| DiskListError::ElementTooLarge {
+      len: slot_capacity,
+      capacity: u32::MAX as usize,
+    })?;
+
+    let file = OpenOptions::new()
+      .read(true)
+      .write(true)
+      .create(true)
+      .truncate(true)
+      .open(path)?;
+
+    let mut list = Self::with_header(
+      file,
+      Header {
+        capacity,
+        front: 0,
+        back: 0,
+        len: 0,
+        free: 0,
+        end: HEADER_LEN,
+      },
+    );
+    list.write_header()?;
+
+    Ok(list)
+  }
+
+  /// Opens the list at `path`, checking it's consistent first. If it isn't
+  /// because a change was cut short, it's put back together and
+  /// [`recovered`](Self::recovered) says so.
+  pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+    let mut file = OpenOptions::new().read(true).write(true).open(path)?;
+
+    let mut bytes = [0; HEADER_LEN as usize];
+    file
+      .read_exact(&mut bytes)
+      .map_err(|err| match err.kind() {
+        io::ErrorKind::UnexpectedEof => DiskListError::Corrupt(String::from("not a list file")),
+        _ => DiskListError::Io(err),
+      })?;
+
+    let mut list = Self::with_header(file, Header::from_bytes(&bytes)?);
+
+    match list.check() {
+      Ok(()) => {}
+      Err(DiskListError::Corrupt(_)) => {
+        list.repair()?;
+        list.check()?;
+        list.recovered = true;
+      }
+      Err(err) => return Err(err),
+    }
+
+    Ok(list)
+  }
+
+  fn with_header(file: File, header: Header) -> Self {
+    Self {
+      file,
+      header,
+      recovered: false,
+      poisoned: false,
+      #[cfg(test)]
+      writes_left: None,
+      _p: PhantomData,
+    }
+  }
+
+  /// Whether [`open`](Self::open) had to fix the file up.
+  pub fn recovered(&self) -> bool {
+    self.recovered
+  }
+
+  pub fn slot_capacity(&self) -> usize {
+    self.header.capacity as usize
+  }
+
+  pub fn len(&self) -> usize {
+    self.header.len as usize
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.header.len == 0
+  }
+
+  pub fn push_front(&mut self, elem: T) -> Result<()> {
+    self.link(&elem, 0, self.header.front)?;
+    Ok(())
+  }
+
+  pub fn push_back(&mut self, elem: T) -> Result<()> {
+    self.link(&elem, self.header.back, 0)?;
+    Ok(())
+  }
+
+  pub fn pop_front(&mut self) -> Result<Option<T>> {
+    match self.header.front {
+      0 => Ok(None),
+      front => self.unlink(front).map(Some),
+    }
+  }
+
+  pub fn pop_back(&mut self) -> Result<Option<T>> {
+    match self.header.back {
+      0 => Ok(None),
+      back => self.unlink(back).map(Some),
+    }
+  }
+
+  pub fn front(&self) -> Result<Option<T>> {
+    self.elem_at(self.header.front)
+  }
+
+  pub fn back(&self) -> Result<Option<T>> {
+    self.elem_at(self.header.back)
+  }
+
+  /// Empties the list and shrinks the file back down to just the header.
+  pub fn clear(&mut self) -> Result<()> {
+    self.change(|list| {
+      list.header = Header {
+        front: 0,
+        back: 0,
+        len: 0,
+        free: 0,
+        end: HEADER_LEN,
+        ..list.header
+      };
+      list.write_header()?;
+      list
+        .file
+        .set_len(HEADER_LEN)
+        .inspect_err(|_| list.poisoned = true)?;
+      Ok(())
+    })
+  }
+
+  /// Waits until everything written so far is on disk.
+  pub fn sync(&mut self) -> Result<()> {
+    self.file.sync_all()?;
+    Ok(())
+  }
+
+  /// Reads the elements front to back, or back to front.
+  pub fn iter(&self) -> Iter<'_, T> {
+    Iter {
+      list: self,
+      front: self.header.front,
+      back: self.header.back,
+      len: self.len(),
+    }
+  }
+
+  pub fn cursor_mut(&mut self) -> CursorMut<'_, T> {
+    CursorMut {
+      current: 0,
+      list: self,
+      index: None,
+    }
+  }
+
+  /// Walks the list and the free list, making sure the links agree with each
+  /// other and with the header, and that every slot is in one of them.
+  pub fn check(&self) -> Result<()> {
+    let file_len = self.file.metadata()?.len();
+    if self.header.end > file_len {
+      return corrupt(format!(
+        "header says slots run to {} but the file ends at {}",
+        self.header.end, file_len
+      ));
+    }
+    if !(self.header.end - HEADER_LEN).is_multiple_of(self.slot_len()) {
+      return corrupt(format!("end {} isn't on a slot boundary", self.header.end));
+    }
+
+    let slots = (self.header.end - HEADER_LEN) / self.slot_len();
+    let mut seen = HashSet::new();
+
+    let mut prev = 0;
+    let mut current = self.header.front;
+    while current != 0 {
+      let node = self.node_in(current, self.header.end, &seen)?;
+      if node.tag != LIVE {
+        return corrupt(format!("node at {} is linked but free", current));
+      }
+      if node.front != prev {
+        return corrupt(format!(
+          "node at {} links back to {} instead of {}",
+          current, node.front, prev
+        ));
+      }
+      if node.elem_len > self.header.capacity {
+        return corrupt(format!("node at {} overflows its slot", current));
+      }
+      seen.insert(current);
+      prev = current;
+      current = node.back;
+    }
+    if prev != self.header.back {
+      return corrupt(format!(
+        "list ends at {} but the header says {}",
+        prev, self.header.back
+      ));
+    }
+    if seen.len() as u64 != self.header.len {
+      return corrupt(format!(
+        "list has {} nodes but the header says {}",
+        seen.len(),
+        self.header.len
+      ));
+    }
+
+    let mut current = self.header.free;
+    while current != 0 {
+      let node = self.node_in(current, self.header.end, &seen)?;
+      if node.tag != FREE {
+        return corrupt(format!("node at {} is free but not marked so", current));
+      }
+      seen.insert(current);
+      current = node.back;
+    }
+
+    if seen.len() as u64 != slots {
+      return corrupt(format!(
+        "{} of {} slots are in neither list",
+        slots - seen.len() as u64,
+        slots
+      ));
+    }
+
+    Ok(())
+  }
+
+  /// Reads the node at `offset`, after making sure it's a slot before `end`
+  /// that hasn't been walked past already.
+  fn node_in(&self, offset: u64, end: u64, seen: &HashSet<u64>) -> Result<Node> {
+    if offset < HEADER_LEN
+      || offset >= end
+      || !(offset - HEADER_LEN).is_multiple_of(self.slot_len())
+    {
+      return corrupt(format!("link to {} doesn't point at a slot", offset));
+    }
+    if seen.contains(&offset) {
+      return corrupt(format!("node at {} is linked twice", offset));
+    }
+    self.read_node(offset)
+  }
+
+  /// Rebuilds the list from the chain of `back` links starting at the
+  /// header's `front`, which every change keeps whole, and puts every other
+  /// slot on the free list.
+  fn repair(&mut self) -> Result<()> {
+    // A crash while adding a slot leaves the file longer than the header
+    // says, maybe with a node in the chain already pointing there.
+    let file_len = self.file.metadata()?.len();
+    let slots = file_len.saturating_sub(HEADER_LEN) / self.slot_len();
+    let end = HEADER_LEN + slots * self.slot_len();
+
+    let mut chain = Vec::new();
+    let mut seen = HashSet::new();
+    let mut current = self.header.front;
+    while current != 0 {
+      match self.node_in(current, end, &seen) {
+        Ok(node) if node.tag == LIVE && node.elem_len <= self.header.capacity => {
+          seen.insert(current);
+          chain.push(current);
+          current = node.back;
+        }
+        _ => break,
+      }
+    }
+
+    let mut prev = 0u64;
+    for (i, &offset) in chain.iter().enumerate() {
+      let back = chain.get(i + 1).copied().unwrap_or(0);
+      self.write_at(offset + 1, &prev.to_le_bytes())?;
+      self.write_at(offset + 9, &back.to_le_bytes())?;
+      prev = offset;
+    }
+
+    self.header.front = chain.first().copied().unwrap_or(0);
+    self.header.back = prev;
+    self.header.len = chain.len() as u64;
+    self.header.end = end;
+    self.header.free = 0;
+
+    for slot in (0..slots).rev() {
+      let offset = HEADER_LEN + slot * self.slot_len();
+      if !seen.contains(&offset) {
+        self.write_free(offset, self.header.free)?;
+        self.header.free = offset;
+      }
+    }
+
+    self.write_header()?;
+    self.file.set_len(end)?;
+    Ok(())
+  }
+
+  fn slot_len(&self) -> u64 {
+    NODE_HEADER_LEN + self.header.capacity as u64
+  }
+
+  fn encode_elem(&self, elem: &T) -> Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    elem.encode(&mut bytes)?;
+
+    if bytes.len() > self.slot_capacity() {
+      return Err(DiskListError::ElementTooLarge {
+        len: bytes.len(),
+        capacity: self.slot_capacity(),
+      });
+    }
+
+    Ok(bytes)
+  }
+
+  /// Runs one change to the file. If it fails, `header` goes back to how it
+  /// was before, so it never describes a change that didn't finish.
+  fn change<R>(&mut self, f: impl FnOnce(&mut Self) -> Result<R>) -> Result<R> {
+    if self.poisoned {
+      return Err(DiskListError::Poisoned);
+    }
+
+    let header = self.header;
+    let result = f(self);
+    if result.is_err() {
+      self.header = header;
+    }
+    result
+  }
+
+  fn link(&mut self, elem: &T, front: u64, back: u64) -> Result<u64> {
+    self.change(|list| list.write_link(elem, front, back))
+  }
+
+  fn unlink(&mut self, offset: u64) -> Result<T> {
+    self.change(|list| list.write_unlink(offset))
+  }
+
+  /// Writes `elem` into a slot between `front` and `back`, either of which
+  /// can be 0 for the ends, and returns the slot.
+  ///
+  /// The new node is written whole before anything links to it, and the
+  /// write that puts it in the chain from the header's `front` (its
+  /// neighbour's `back`, or the header itself at the front) goes first.
+  fn write_link(&mut self, elem: &T, front: u64, back: u64) -> Result<u64> {
+    let bytes = self.encode_elem(elem)?;
+
+    let offset = match self.header.free {
+      0 => {
+        let offset = self.header.end;
+        self.header.end += self.slot_len();
+        offset
+      }
+      free => {
+        self.header.free = self.read_node(free)?.back;
+        free
+      }
+    };
+
+    let mut slot = Vec::with_capacity(self.slot_len() as usize);
+    slot.push(LIVE);
+    slot.extend_from_slice(&front.to_le_bytes());
+    slot.extend_from_slice(&back.to_le_bytes());
+    slot.extend_from_slice(&(bytes.len() as u32).to_le_bytes());
+    slot.extend_from_slice(&bytes);
+    // The whole slot, so the file is always as long as the header says.
+    slot.resize(self.slot_len() as usize, 0);
+    self.write_at(offset, &slot)?;
+
+    if front == 0 {
+      self.header.front = offset;
+    } else {
+      self.write_at(front + 9, &offset.to_le_bytes())?;
+    }
+    if back == 0 {
+      self.header.back = offset;
+    }
+    self.header.len += 1;
+    self.write_header()?;
+
+    if back != 0 {
+      self.write_at(back + 1, &offset.to_le_bytes())?;
+    }
+
+    Ok(offset)
+  }
+
+  /// Takes the node at `offset` out of the list and frees its slot, in the
+  /// same order as [`write_link`](Self::write_link).
+  fn write_unlink(&mut self, offset: u64) -> Result<T> {
+    let node = self.read_node(offset)?;
+    let elem = self.read_elem(offset, node)?;
+
+    if node.front == 0 {
+      self.header.front = node.back;
+    } else {
+      self.write_at(node.front + 9, &node.back.to_le_bytes())?;
+    }
+    if node.back == 0 {
+      self.header.back = node.front;
+    }
+    self.header.len -= 1;
+    self.write_header()?;
+
+    if node.back != 0 {
+      self.write_at(node.back + 1, &node.front.to_le_bytes())?;
+    }
+
+    self.write_free(offset, self.header.free)?;
+    self.header.free = offset;
+    self.write_header()?;
+
+    Ok(elem)
+  }
+
+  fn write_free(&mut self, offset: u64, next: u64) -> Result<()> {
+    let mut slot = [0; 17];
+    slot[0] = FREE;
+    slot[9..17].copy_from_slice(&next.to_le_bytes());
+    self.write_at(offset, &slot)
+  }
+
+  fn write_header(&mut self) -> Result<()> {
+    self.write_at(0, &self.header.to_bytes())
+  }
+
+  fn write_at(&mut self, offset: u64, bytes: &[u8]) -> Result<()> {
+    let result = self.try_write_at(offset, bytes);
+    if result.is_err() {
+      self.poisoned = true;
+    }
+    result
+  }
+
+  fn try_write_at(&mut self, offset: u64, bytes: &[u8]) -> Result<()> {
+    #[cfg(test)]
+    if let Some(left) = self.writes_left.as_mut() {
+      if *left == 0 {
+        return Err(io::Error::other("simulated crash").into());
+      }
+      *left -= 1;
+    }
+
+    self.file.seek(SeekFrom::Start(offset))?;
+    self.file.write_all(bytes)?;
+    Ok(())
+  }
+
+  fn read_node(&self, offset: u64) -> Result<Node> {
+    let mut bytes = [0; NODE_HEADER_LEN as usize];
+    let mut file = &self.file;
+    file.seek(SeekFrom::Start(offset))?;
+    file.read_exact(&mut bytes)?;
+
+    Ok(Node {
+      tag: bytes[0],
+      front: u64_at(&bytes, 1),
+      back: u64_at(&bytes, 9),
+      elem_len: u32::from_le_bytes(bytes[17..21].try_into().unwrap()),
+    })
+  }
+
+  fn read_elem(&self, offset: u64, node: Node) -> Result<T> {
+    let mut bytes = vec![0; node.elem_len as usize];
+    let mut file = &self.file;
+    file.seek(SeekFrom::Start(offset + NODE_HEADER_LEN))?;
+    file.read_exact(&mut bytes)?;
+
+    Ok(Decoder::new(bytes.as_slice()).decode()?)
+  }
+
+  fn elem_at(&self, offset: u64) -> Result<Option<T>> {
+    match offset {
+      0 => Ok(None),
+      offset => {
+        let node = self.read_node(offset)?;
+        self.read_elem(offset, node).map(Some)
+      }
+    }
+  }
+}
+
+impl<T> fmt::Debug for DiskList<T> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_struct("DiskList")
+      .field("len", &self.header.len)
+      .field("slot_capacity", &self.header.capacity)
+      .finish()
+  }
+}
+
+/// Decodes each element as it gets to it, so reading can fail part of the
+/// way through. After an error it's done.
+pub struct Iter<'a, T> {
+  list: &'a DiskList<T>,
+  front: u64,
+  back: u64,
+  len: usize,
+}
+
+impl<T: Encode + Decode> Iter<'_, T> {
+  fn read(&mut self, offset: u64) -> Result<(Node, T)> {
+    let node = self.list.read_node(offset)?;
+    let elem = self.list.read_elem(offset, node)?;
+    Ok((node, elem))
+  }
+}
+
+impl<T: Encode + Decode> Iterator for Iter<'_, T> {
+  type Item = Result<T>;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    if self.len == 0 {
+      return None;
+    }
+
+    match self.read(self.front) {
+      Ok((node, elem)) => {
+        self.len -= 1;
+        self.front = node.back;
+        Some(Ok(elem))
+      }
+      Err(err) => {
+        self.len = 0;
+        Some(Err(err))
+      }
+    }
+  }
+
+  fn size_hint(&self) -> (usize, Option<usize>) {
+    (self.len, Some(self.len))
+  }
+}
+
+impl<T: Encode + Decode> DoubleEndedIterator for Iter<'_, T> {
+  fn next_back(&mut self) -> Option<Self::Item> {
+    if self.len == 0 {
+      return None;
+    }
+
+    match self.read(self.back) {
+      Ok((node, elem)) => {
+        self.len -= 1;
+        self.back = node.front;
+        Some(Ok(elem))
+      }
+      Err(err) => {
+        self.len = 0;
+        Some(Err(err))
+      }
+    }
+  }
+}
+
+impl<T: Encode + Decode> ExactSizeIterator for Iter<'_, T> {}
+
+/// Works like `production_linked_list::CursorMut`, starting on the ghost
+/// between the back and the front, but reads elements out rather than
+/// lending them, and edits one node at a time.
+pub struct CursorMut<'a, T> {
+  current: u64,
+  list: &'a mut DiskList<T>,
+  index: Option<usize>,
+}
+
+impl<T: Encode + Decode> CursorMut<'_, T> {
+  pub fn index(&self) -> Option<usize> {
+    self.index
+  }
+
+  pub fn move_next(&mut self) -> Result<()> {
+    match self.current {
+      0 => {
+        self.current = self.list.header.front;
+        self.index = (self.current != 0).then_some(0);
+      }
+      current => {
+        self.current = self.list.read_node(current)?.back;
+        self.index = match self.current {
+          0 => None,
+          _ => self.index.map(|index| index + 1),
+        };
+      }
+    }
+    Ok(())
+  }
+
+  pub fn move_prev(&mut self) -> Result<()> {
+    match self.current {
+      0 => {
+        self.current = self.list.header.back;
+        self.index = (self.current != 0).then(|| self.list.len() - 1);
+      }
+      current => {
+        self.current = self.list.read_node(current)?.front;
+        self.index = match self.current {
+          0 => None,
+          _ => self.index.map(|index| index - 1),
+        };
+      }
+    }
+    Ok(())
+  }
+
+  pub fn current(&self) -> Result<Option<T>> {
+    self.list.elem_at(self.current)
+  }
+
+  pub fn peek_next(&self) -> Result<Option<T>> {
+    self.list.elem_at(self.next_offset()?)
+  }
+
+  pub fn peek_prev(&self) -> Result<Option<T>> {
+    self.list.elem_at(self.prev_offset()?)
+  }
+
+  /// Adds `elem` before the current node, or at the back on the ghost.
+  pub fn insert_before(&mut self, elem: T) -> Result<()> {
+    let front = self.prev_offset()?;
+    self.list.link(&elem, front, self.current)?;
+    self.index = self.index.map(|index| index + 1);
+    Ok(())
+  }
+
+  /// Adds `elem` after the current node, or at the front on the ghost.
+  pub fn insert_after(&mut self, elem: T) -> Result<()> {
+    let back = self.next_offset()?;
+    self.list.link(&elem, self.current, back)?;
+    Ok(())
+  }
+
+  /// Removes the current node and moves on to the next one. Does nothing on
+  /// the ghost.
+  pub fn remove_current(&mut self) -> Result<Option<T>> {
+    if self.current == 0 {
+      return Ok(None);
+    }
+
+    let next = self.list.read_node(self.current)?.back;
+    let elem = self.list.unlink(self.current)?;
+    self.current = next;
+    if next == 0 {
+      self.index = None;
+    }
+
+    Ok(Some(elem))
+  }
+
+  fn next_offset(&self) -> Result<u64> {
+    match self.current {
+      0 => Ok(self.list.header.front),
+      current => Ok(self.list.read_node(current)?.back),
+    }
+  }
+
+  fn prev_offset(&self) -> Result<u64> {
+    match self.current {
+      0 => Ok(self.list.header.back),
+      current => Ok(self.list.read_node(current)?.front),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::{
+    env, fs,
+    path::PathBuf,
+    process,
+    sync::atomic::{AtomicUsize, Ordering},
+  };
+
+  /// A path in the temp directory that's gone again once the test is done.
+  struct TempPath(PathBuf);
+
+  impl TempPath {
+    fn new() -> Self {
+      static NEXT: AtomicUsize = AtomicUsize::new(0);
+      let name = format!(
+        "disk_list_{}_{}",
+        process::id(),
+        NEXT.fetch_add(1, Ordering::Relaxed)
+      );
+      Self(env::temp_dir().join(name))
+    }
+  }
+
+  impl Drop for TempPath {
+    fn drop(&mut self) {
+      let _ = fs::remove_file(&self.0);
+    }
+  }
+
+  fn contents(list: &DiskList<u32>) -> Vec<u32> {
+    list.iter().collect::<Result<_>>().unwrap()
+  }
+
+  #[test]
+  fn basics() {
+    let path = TempPath::new();
+    let mut list = DiskList::create(&path.0, 8).unwrap();
+
+    assert_eq!(None, list.pop_front().unwrap());
+    assert_eq!(None, list.pop_back().unwrap());
+
+    list.push_back(2).unwrap();
+    list.push_back(3).unwrap();
+    list.push_front(1).unwrap();
+
+    assert_eq!(3, list.len());
+    assert_eq!(Some(1), list.front().unwrap());
+    assert_eq!(Some(3), list.back().unwrap());
+    assert_eq!(vec![1, 2, 3], contents(&list));
+    assert_eq!(
+      vec![3, 2, 1],
+      list.iter().rev().collect::<Result<Vec<_>>>().unwrap()
+    );
+
+    assert_eq!(Some(1), list.pop_front().unwrap());
+    assert_eq!(Some(3), list.pop_back().unwrap());
+    assert_eq!(Some(2), list.pop_back().unwrap());
+    assert_eq!(None, list.pop_front().unwrap());
+    assert!(list.is_empty());
+    list.check().unwrap();
+  }
+
+  #[test]
+  fn survives_reopening() {
+    let path = TempPath::new();
+
+    let mut list = DiskList::create(&path.0, 32).unwrap();
+    for word in ["a", "queue", "that", "outlives", "us"] {
+      list.push_back(String::from(word)).unwrap();
+    }
+    list.pop_front().unwrap();
+    list.sync().unwrap();
+    drop(list);
+
+    let list = DiskList::<String>::open(&path.0).unwrap();
+    assert!(!list.recovered());
+    assert_eq!(
+      vec!["queue", "that", "outlives", "us"],
+      list.iter().collect::<Result<Vec<_>>>().unwrap()
+    );
+  }
+
+  #[test]
+  fn reuses_freed_slots() {
+    let path = TempPath::new();
+    let mut list = DiskList::create(&path.0, 8).unwrap();
+
+    for i in 0..10 {
+      list.push_back(i).unwrap();
+    }
+    let full = fs::metadata(&path.0).unwrap().len();
+
+    for i in 0..100 {
+      list.pop_front().unwrap();
+      list.push_back(10 + i).unwrap();
+    }
+
+    assert_eq!(full, fs::metadata(&path.0).unwrap().len());
+    assert_eq!((100..110).collect::<Vec<_>>(), contents(&list));
+    list.check().unwrap();
+
+    list.clear().unwrap();
+    assert_eq!(HEADER_LEN, fs::metadata(&path.0).unwrap().len());
+    list.push_back(1).unwrap();
+    assert_eq!(vec![1], contents(&list));
+  }
+
+  #[test]
+  fn cursor() {
+    let path = TempPath::new();
+    let mut list = DiskList::create(&path.0, 8).unwrap();
+    for i in [1, 3, 5] {
+      list.push_back(i).unwrap();
+    }
+
+    let mut cursor = list.cursor_mut();
+    assert_eq!(None, cursor.index());
+    assert_eq!(Some(1), cursor.peek_next().unwrap());
+    assert_eq!(Some(5), cursor.peek_prev().unwrap());
+
+    cursor.move_next().unwrap();
+    cursor.insert_before(0).unwrap();
+    cursor.insert_after(2).unwrap();
+    assert_eq!(Some(1), cursor.index());
+    assert_eq!(Some(1), cursor.current().unwrap());
+
+    cursor.move_next().unwrap();
+    cursor.move_next().unwrap();
+    assert_eq!(Some(3), cursor.current().unwrap());
+    assert_eq!(Some(3), cursor.remove_current().unwrap());
+    assert_eq!(Some(5), cursor.current().unwrap());
+    assert_eq!(Some(3), cursor.index());
+
+    assert_eq!(Some(5), cursor.remove_current().unwrap());
+    assert_eq!(None, cursor.index());
+    assert_eq!(None, cursor.remove_current().unwrap());
+    cursor.insert_before(6).unwrap();
+    cursor.insert_after(9).unwrap();
+
+    cursor.move_prev().unwrap();
+    assert_eq!(Some(4), cursor.index());
+    assert_eq!(Some(6), cursor.current().unwrap());
+
+    assert_eq!(vec![9, 0, 1, 2, 6], contents(&list));
+    list.check().unwrap();
+  }
+
+  #[test]
+  fn rejects_what_it_cant_use() {
+    let path = TempPath::new();
+    let mut list = DiskList::create(&path.0, 4).unwrap();
+
+    assert!(matches!(
+      list.push_back(String::from("too long")),
+      Err(DiskListError::ElementTooLarge {
+        len: 9,
+        capacity: 4
+      })
+    ));
+    assert!(list.is_empty());
+    drop(list);
+
+    fs::write(&path.0, b"not a list").unwrap();
+    assert!(matches!(
+      DiskList::<u32>::open(&path.0),
+      Err(DiskListError::Corrupt(_))
+    ));
+
+    let mut list = DiskList::<u32>::create(&path.0, 4).unwrap();
+    list.push_back(1).unwrap();
+    drop(list);
+    let mut bytes = fs::read(&path.0).unwrap();
+    bytes[20] ^= 1;
+    fs::write(&path.0, bytes).unwrap();
+    assert!(matches!(
+      DiskList::<u32>::open(&path.0),
+      Err(DiskListError::Corrupt(_))
+    ));
+  }
+
+  type Op = fn(&mut DiskList<u32>) -> Result<()>;
+
+  /// Runs `op` on a list of 1, 2, 3 (with a free slot, so both ways of
+  /// allocating get hit), crashing after every possible number of writes,
+  /// and checks reopening always gives the list from before or after.
+  fn crash_during(op: Op) {
+    let path = TempPath::new();
+
+    let setup = |path: &Path| {
+      let mut list = DiskList::create(path, 8).unwrap();
+      for i in [0, 1, 2, 3] {
+        list.push_back(i).unwrap();
+      }
+      list.pop_front().unwrap();
+      list
+    };
+
+    let before = contents(&setup(&path.0));
+    let mut list = setup(&path.0);
+    op(&mut list).unwrap();
+    let after = contents(&list);
+    drop(list);
+
+    for writes in 0.. {
+      let mut list = setup(&path.0);
+      list.writes_left = Some(writes);
+      let finished = op(&mut list).is_ok();
+      drop(list);
+
+      let mut list = DiskList::<u32>::open(&path.0).unwrap();
+      let recovered = contents(&list);
+      assert!(
+        recovered == before || recovered == after,
+        "crashed after {} writes and got {:?}",
+        writes,
+        recovered
+      );
+
+      // And the recovered list works.
+      list.push_back(9).unwrap();
+      list.push_front(9).unwrap();
+      list.check().unwrap();
+
+      if finished {
+        assert_eq!(after, recovered);
+        break;
+      }
+    }
+  }
+
+  #[test]
+  fn crash_consistency() {
+    let ops: [Op; 7] = [
+      |list| list.push_front(7),
+      |list| list.push_back(7),
+      |list| list.pop_front().map(drop),
+      |list| list.pop_back().map(drop),
+      |list| list.clear(),
+      |list| {
+        let mut cursor = list.cursor_mut();
+        cursor.move_next()?;
+        cursor.move_next()?;
+        cursor.insert_before(7)
+      },
+      |list| {
+        let mut cursor = list.cursor_mut();
+        cursor.move_next()?;
+        cursor.move_next()?;
+        cursor.remove_current().map(drop)
+      },
+    ];
+
+    for op in ops {
+      crash_during(op);
+    }
+  }
+
+  #[test]
+  fn failed_write_poisons_the_handle() {
+    let path = TempPath::new();
+    let mut list = DiskList::<u32>::create(&path.0, 8).unwrap();
+    list.push_back(1).unwrap();
+
+    list.writes_left = Some(1);
+    assert!(matches!(list.push_back(2), Err(DiskListError::Io(_))));
+    assert_eq!(1, list.len());
+    assert_eq!(Some(1), list.back().unwrap());
+
+    list.writes_left = None;
+    assert!(matches!(list.push_back(3), Err(DiskListError::Poisoned)));
+    assert!(matches!(list.pop_front(), Err(DiskListError::Poisoned)));
+    assert!(matches!(list.clear(), Err(DiskListError::Poisoned)));
+    drop(list);
+
+    let mut list = DiskList::<u32>::open(&path.0).unwrap();
+    assert_eq!(vec![1], contents(&list));
+    list.push_back(3).unwrap();
+    assert_eq!(vec![1, 3], contents(&list));
+  }
+
+  #[test]
+  fn drains_to_empty_and_back() {
+    let path = TempPath::new();
+    let mut list = DiskList::create(&path.0, 8).unwrap();
+
+    for round in 0..3 {
+      for i in 0..50 {
+        if i % 2 == 0 {
+          list.push_front(i + round).unwrap();
+        } else {
+          list.push_back(i + round).unwrap();
+        }
+      }
+      while list.pop_back().unwrap().is_some() {}
+      list.check().unwrap();
+    }
+
+    drop(list);
+    let list = DiskList::<u32>::open(&path.0).unwrap();
+    assert!(list.is_empty());
+    assert!(!list.recovered());
+  }
+}
@@ -0,0 +1,293 @@
+//! Graphviz output for looking at how the nodes of a list are linked.
+//!
+//! Nodes are keyed by their address, so adding several lists that share
+//! nodes (like the persistent `third::List`) to one [`Graph`] draws the
+//! shared part once, with the lists converging on it.
+//!
+//! ```
+//! use learn_rust_with_entirely_too_many_linked_lists::{dot::Graph, third::List};
+//!
+//! let tail = List::new().cons(2).cons(1);
+//! let a = tail.cons(10);
+//! let b = tail.cons(20);
+//!
+//! let mut graph = Graph::new();
+//! graph.add("a", &a).add("b", &b);
+//!
+//! // `dot -Tsvg` can render this.
+//! let text = graph.to_string();
+//! assert_eq!(3, text.matches("[label=\"next\"]").count());
+//! ```
+
+use std::{
+  collections::{HashMap, HashSet},
+  fmt::{self, Write},
+};
+
+/// Implemented by every list in the crate whose elements are `Display`.
+pub trait ToDot {
+  /// Adds the list's nodes and links to `graph`, labelling its head and
+  /// tail markers with `name`.
+  fn add_to_graph(&self, graph: &mut Graph, name: &str);
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum Edge {
+  Next,
+  Prev,
+}
+
+#[derive(Default)]
+pub struct Graph {
+  /// Maps a node's address to its position in `nodes`.
+  ids: HashMap<usize, usize>,
+  nodes: Vec<String>,
+  edges: HashSet<(usize, usize, Edge)>,
+  edge_order: Vec<(usize, usize, Edge)>,
+  markers: Vec<(String, Option<usize>)>,
+}
+
+impl Graph {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  pub fn add<L: ToDot + ?Sized>(&mut self, name: &str, list: &L) -> &mut Self {
+    list.add_to_graph(self, name);
+    self
+  }
+
+  /// Adds a node, returning false if a node at the same address was
+  /// already in the graph.
+  pub(crate) fn node<N>(&mut self, node: *const N, label: impl fmt::Display) -> bool {
+    let addr = node as usize;
+
+    if self.ids.contains_key(&addr) {
+      return false;
+    }
+
+    self.ids.insert(addr, self.nodes.len());
+    self.nodes.push(label.to_string());
+    true
+  }
+
+  /// Links two nodes by address. They only need to have been added with
+  /// [`Graph::node`] by the time the graph is rendered.
+  pub(crate) fn edge<N>(&mut self, from: *const N, to: *const N, edge: Edge) {
+    let key = (from as usize, to as usize, edge);
+
+    if self.edges.insert(key) {
+      self.edge_order.push(key);
+    }
+  }
+
+  /// Adds a marker such as `name.head` pointing at `node`, if there is one.
+  pub(crate) fn marker<N>(&mut self, name: &str, which: &str, node: Option<*const N>) {
+    self.markers.push((
+      format!("{}.{}", name, which),
+      node.map(|node| node as usize),
+    ));
+  }
+
+  fn id(&self, addr: usize) -> usize {
+    self.ids[&addr]
+  }
+}
+
+fn escape(s: &str) -> String {
+  let mut escaped = String::with_capacity(s.len());
+
+  for c in s.chars() {
+    match c {
+      '"' | '\\' => {
+        escaped.push('\\');
+        escaped.push(c);
+      }
+      '\n' => escaped.push_str("\\n"),
+      _ => escaped.push(c),
+    }
+  }
+
+  escaped
+}
+
+impl fmt::Display for Graph {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    writeln!(f, "digraph {{")?;
+    writeln!(f, "  rankdir=LR;")?;
+    writeln!(f, "  node [shape=box];")?;
+
+    for (id, label) in self.nodes.iter().enumerate() {
+      writeln!(f, "  n{} [label=\"{}\"];", id, escape(label))?;
+    }
+
+    for &(from, to, edge) in self.edge_order.iter() {
+      let attrs = match edge {
+        Edge::Next => "label=\"next\"",
+        Edge::Prev => "label=\"prev\", style=dashed",
+      };
+      writeln!(f, "  n{} -> n{} [{}];", self.id(from), self.id(to), attrs)?;
+    }
+
+    for (marker, node) in self.markers.iter() {
+      let marker = escape(marker);
+      writeln!(f, "  \"{}\" [shape=plaintext];", marker)?;
+
+      if let Some(node) = node {
+        writeln!(f, "  \"{}\" -> n{};", marker, self.id(*node))?;
+      }
+    }
+
+    f.write_char('}')?;
+    f.write_char('\n')
+  }
+}
+
+/// Renders a single list, with its markers named `list`.
+pub fn to_dot<L: ToDot + ?Sized>(list: &L) -> String {
+  let mut graph = Graph::new();
+  graph.add("list", list);
+  graph.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{
+    fifth, first, fourth, production_linked_list::LinkedList, second, seventh, sixth, third,
+  };
+
+  fn count(haystack: &str, needle: &str) -> usize {
+    haystack.matches(needle).count()
+  }
+
+  fn count_nodes(text: &str) -> usize {
+    text
+      .lines()
+      .filter(|line| line.contains("[label=") && !line.contains("->"))
+      .count()
+  }
+
+  #[test]
+  fn empty_list() {
+    let text = to_dot(&LinkedList::<i32>::new());
+
+    assert_eq!(0, count(&text, "[label="));
+    assert!(text.contains("\"list.head\" [shape=plaintext];"));
+    assert!(text.contains("\"list.tail\" [shape=plaintext];"));
+    assert_eq!(0, count(&text, "->"));
+  }
+
+  #[test]
+  fn production_linked_list() {
+    let list: LinkedList<i32> = (1..=3).collect();
+
+    assert_eq!(
+      to_dot(&list),
+      "digraph {
+  rankdir=LR;
+  node [shape=box];
+  n0 [label=\"1\"];
+  n1 [label=\"2\"];
+  n2 [label=\"3\"];
+  n0 -> n1 [label=\"next\"];
+  n1 -> n0 [label=\"prev\", style=dashed];
+  n1 -> n2 [label=\"next\"];
+  n2 -> n1 [label=\"prev\", style=dashed];
+  \"list.head\" [shape=plaintext];
+  \"list.head\" -> n0;
+  \"list.tail\" [shape=plaintext];
+  \"list.tail\" -> n2;
+}
+"
+    );
+  }
+
+  #[test]
+  fn escapes_labels() {
+    let list: LinkedList<&str> = ["say \"hi\"", "a\\b"].into_iter().collect();
+    let text = to_dot(&list);
+
+    assert!(text.contains(r#"n0 [label="say \"hi\""];"#));
+    assert!(text.contains(r#"n1 [label="a\\b"];"#));
+  }
+
+  #[test]
+  fn third_shares_tails() {
+    let tail = third::List::new().cons(2).cons(1);
+    let a = tail.cons(10);
+    let b = tail.cons(20).cons(30);
+
+    let mut graph = Graph::new();
+    graph.add("a", &a).add("b", &b).add("tail", &tail);
+    let text = graph.to_string();
+
+    // 1 and 2 are only drawn once.
+    assert_eq!(5, count_nodes(&text));
+    assert_eq!(4, count(&text, "[label=\"next\"]"));
+    // 10 and 20 both point at the shared 1.
+    assert!(text.contains("n0 -> n1 [label=\"next\"];"));
+    assert!(text.contains("n4 -> n1 [label=\"next\"];"));
+    assert!(text.contains("\"tail.head\" -> n1;"));
+  }
+
+  #[test]
+  fn fourth_shows_strong_counts() {
+    let mut list = fourth::List::new();
+    list.push_back(1);
+    list.push_back(2);
+    list.push_back(3);
+
+    let text = to_dot(&list);
+
+    // The ends are held by the list itself and one neighbour, the middle
+    // node by both of its neighbours.
+    assert!(text.contains("n0 [label=\"1 (rc=2)\"];"));
+    assert!(text.contains("n1 [label=\"2 (rc=2)\"];"));
+    assert!(text.contains("n2 [label=\"3 (rc=2)\"];"));
+    assert_eq!(2, count(&text, "[label=\"prev\""));
+    assert!(text.contains("\"list.tail\" -> n2;"));
+  }
+
+  #[test]
+  fn singly_linked_lists() {
+    let mut list = first::List::new();
+    list.push(1);
+    list.push(2);
+    let text = to_dot(&list);
+    assert_eq!(1, count(&text, "[label=\"next\"]"));
+    assert!(text.contains("\"list.head\" -> n0;"));
+
+    let mut list = second::List::new();
+    list.push(1);
+    list.push(2);
+    assert_eq!(1, count(&to_dot(&list), "[label=\"next\"]"));
+
+    let mut list = fifth::List::new();
+    list.push(1);
+    list.push(2);
+    list.push(3);
+    let text = to_dot(&list);
+    assert_eq!(2, count(&text, "[label=\"next\"]"));
+    assert!(text.contains("\"list.tail\" -> n2;"));
+
+    let mut list = sixth::List::new();
+    list.push(1);
+    list.push(2);
+    let text = to_dot(&list);
+    assert_eq!(0, count(&text, "prev"));
+    assert!(text.contains("\"list.tail\" -> n1;"));
+  }
+
+  #[test]
+  fn seventh_follows_prev() {
+    seventh::List::push(None, 1, |list| {
+      seventh::List::push(Some(list), 2, |list| {
+        let text = to_dot(list);
+
+        assert!(text.contains("n0 [label=\"2\"];"));
+        assert!(text.contains("n0 -> n1 [label=\"prev\", style=dashed];"));
+      })
+    })
+  }
+}
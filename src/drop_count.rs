@@ -0,0 +1,380 @@
+//! A value that keeps count of how many of it were made and dropped, for
+//! checking the lists free every element exactly once.
+//!
+//! Each [`Counted`] gets an id from its [`DropCounter`], which remembers the
+//! ids still alive. Dropping one twice panics straight away, and anything
+//! left alive at the end shows up in [`DropCounter::assert_all_dropped`].
+
+use std::{
+  cell::{Cell, RefCell},
+  collections::BTreeSet,
+  fmt,
+};
+
+#[derive(Default)]
+pub(crate) struct DropCounter {
+  next_id: Cell<usize>,
+  live: RefCell<BTreeSet<usize>>,
+  dropped: Cell<usize>,
+  panic_on: Cell<Option<usize>>,
+}
+
+impl DropCounter {
+  pub(crate) fn new() -> Self {
+    Self::default()
+  }
+
+  pub(crate) fn make(&self) -> Counted<'_> {
+    let id = self.next_id.get();
+    self.next_id.set(id + 1);
+    self.live.borrow_mut().insert(id);
+
+    Counted { id, counter: self }
+  }
+
+  /// `n` new values, with consecutive ids.
+  pub(crate) fn make_n(&self, n: usize) -> Vec<Counted<'_>> {
+    (0..n).map(|_| self.make()).collect()
+  }
+
+  pub(crate) fn created(&self) -> usize {
+    self.next_id.get()
+  }
+
+  pub(crate) fn dropped(&self) -> usize {
+    self.dropped.get()
+  }
+
+  pub(crate) fn live(&self) -> usize {
+    self.live.borrow().len()
+  }
+
+  /// Makes dropping the value with this id panic, once. It still counts as
+  /// dropped.
+  pub(crate) fn panic_on_drop(&self, id: usize) {
+    self.panic_on.set(Some(id));
+  }
+
+  #[track_caller]
+  pub(crate) fn assert_all_dropped(&self) {
+    assert!(
+      self.live.borrow().is_empty(),
+      "leaked {:?}",
+      self.live.borrow()
+    );
+    assert_eq!(self.created(), self.dropped());
+  }
+}
+
+pub(crate) struct Counted<'a> {
+  id: usize,
+  counter: &'a DropCounter,
+}
+
+impl Counted<'_> {
+  pub(crate) fn id(&self) -> usize {
+    self.id
+  }
+}
+
+/// A clone is a new value, with its own id.
+impl Clone for Counted<'_> {
+  fn clone(&self) -> Self {
+    self.counter.make()
+  }
+}
+
+impl fmt::Debug for Counted<'_> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "Counted({})", self.id)
+  }
+}
+
+impl PartialEq for Counted<'_> {
+  fn eq(&self, other: &Self) -> bool {
+    self.id == other.id
+  }
+}
+
+impl Drop for Counted<'_> {
+  fn drop(&mut self) {
+    assert!(
+      self.counter.live.borrow_mut().remove(&self.id),
+      "{} dropped twice",
+      self.id
+    );
+    self.counter.dropped.set(self.counter.dropped.get() + 1);
+
+    if self.counter.panic_on.get() == Some(self.id) {
+      self.counter.panic_on.set(None);
+      panic!("dropping {} panics", self.id);
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{fifth, fourth, production_linked_list::LinkedList, second, sixth, third};
+  use std::{
+    mem::ManuallyDrop,
+    panic::{self, AssertUnwindSafe},
+  };
+
+  /// Drops `value`, expecting one of its elements to panic on the way.
+  fn drop_panicking<T>(value: T) {
+    let result = panic::catch_unwind(AssertUnwindSafe(|| drop(value)));
+    assert!(result.is_err(), "nothing panicked");
+  }
+
+  #[test]
+  fn counts_and_catches_double_drops() {
+    let counter = DropCounter::new();
+
+    let a = counter.make();
+    let b = a.clone();
+    assert_ne!(a.id(), b.id());
+    assert_eq!(2, counter.live());
+
+    drop(a);
+    assert_eq!(1, counter.dropped());
+
+    let mut b = ManuallyDrop::new(b);
+    unsafe { ManuallyDrop::drop(&mut b) };
+    let result = panic::catch_unwind(AssertUnwindSafe(|| unsafe { ManuallyDrop::drop(&mut b) }));
+    assert!(result.is_err());
+
+    counter.assert_all_dropped();
+  }
+
+  #[test]
+  fn panics_on_command() {
+    let counter = DropCounter::new();
+    let values = counter.make_n(3);
+
+    counter.panic_on_drop(1);
+    drop_panicking(values);
+
+    counter.assert_all_dropped();
+  }
+
+  #[test]
+  fn second() {
+    let counter = DropCounter::new();
+    let mut list = second::List::new();
+
+    counter.make_n(5).into_iter().for_each(|v| list.push(v));
+    drop(list.pop());
+    drop(list.pop());
+    assert_eq!(2, counter.dropped());
+    drop(list);
+    counter.assert_all_dropped();
+
+    let mut list = second::List::new();
+    counter.make_n(4).into_iter().for_each(|v| list.push(v));
+    let mut iter = list.into_iter();
+    drop(iter.next());
+    drop(iter);
+    counter.assert_all_dropped();
+
+    let mut list = second::List::new();
+    counter.make_n(4).into_iter().for_each(|v| list.push(v));
+    counter.panic_on_drop(counter.created() - 2);
+    drop_panicking(list);
+    counter.assert_all_dropped();
+  }
+
+  #[test]
+  fn third_shared_tails() {
+    let counter = DropCounter::new();
+    let mut values = counter.make_n(5).into_iter();
+
+    let tail = third::List::new()
+      .cons(values.next().unwrap())
+      .cons(values.next().unwrap())
+      .cons(values.next().unwrap());
+    let a = tail.cons(values.next().unwrap());
+    let b = tail.cons(values.next().unwrap());
+
+    // Everything in `tail` is still held by `a` and `b`.
+    drop(tail);
+    assert_eq!(0, counter.dropped());
+
+    drop(a);
+    assert_eq!(1, counter.dropped());
+
+    // Walking down the list drops nothing either.
+    let rest = b.tail().tail();
+    drop(b);
+    assert_eq!(3, counter.dropped());
+
+    drop(rest);
+    counter.assert_all_dropped();
+
+    let list = third::List::new()
+      .cons(counter.make())
+      .cons(counter.make())
+      .cons(counter.make());
+    counter.panic_on_drop(counter.created() - 2);
+    drop_panicking(list);
+    counter.assert_all_dropped();
+  }
+
+  #[test]
+  fn fourth_breaks_its_cycles() {
+    let counter = DropCounter::new();
+    let mut list = fourth::List::new();
+
+    for (i, v) in counter.make_n(6).into_iter().enumerate() {
+      if i % 2 == 0 {
+        list.push_front(v);
+      } else {
+        list.push_back(v);
+      }
+    }
+
+    drop(list.pop_front());
+    drop(list.pop_back());
+    assert_eq!(2, counter.dropped());
+    drop(list);
+    counter.assert_all_dropped();
+
+    let mut list = fourth::List::new();
+    counter
+      .make_n(5)
+      .into_iter()
+      .for_each(|v| list.push_back(v));
+    let mut iter = list.into_iter();
+    drop(iter.next());
+    drop(iter.next_back());
+    drop(iter);
+    counter.assert_all_dropped();
+
+    let mut list = fourth::List::new();
+    counter
+      .make_n(4)
+      .into_iter()
+      .for_each(|v| list.push_back(v));
+    counter.panic_on_drop(counter.created() - 3);
+    drop_panicking(list);
+    counter.assert_all_dropped();
+  }
+
+  #[test]
+  fn fifth() {
+    let counter = DropCounter::new();
+    let mut list = fifth::List::new();
+
+    counter.make_n(5).into_iter().for_each(|v| list.push(v));
+    drop(list.pop());
+    assert_eq!(1, counter.dropped());
+    drop(list);
+    counter.assert_all_dropped();
+
+    let mut list = fifth::List::new();
+    counter.make_n(4).into_iter().for_each(|v| list.push(v));
+    counter.panic_on_drop(counter.created() - 3);
+    drop_panicking(list);
+    counter.assert_all_dropped();
+  }
+
+  #[test]
+  fn sixth() {
+    let counter = DropCounter::new();
+    let mut list = sixth::List::new();
+
+    counter.make_n(5).into_iter().for_each(|v| list.push(v));
+    drop(list.pop());
+    assert_eq!(1, counter.dropped());
+    drop(list);
+    counter.assert_all_dropped();
+
+    let mut list = sixth::List::new();
+    counter.make_n(4).into_iter().for_each(|v| list.push(v));
+    let mut iter = list.into_iter();
+    drop(iter.next());
+    drop(iter);
+    counter.assert_all_dropped();
+
+    let mut list = sixth::List::new();
+    counter.make_n(4).into_iter().for_each(|v| list.push(v));
+    counter.panic_on_drop(counter.created() - 3);
+    drop_panicking(list);
+    counter.assert_all_dropped();
+  }
+
+  #[test]
+  fn production_linked_list() {
+    let counter = DropCounter::new();
+
+    let mut list: LinkedList<_> = counter.make_n(6).into_iter().collect();
+    drop(list.pop_front());
+    drop(list.pop_back());
+    assert_eq!(2, counter.dropped());
+    list.clear();
+    counter.assert_all_dropped();
+    counter
+      .make_n(2)
+      .into_iter()
+      .for_each(|v| list.push_front(v));
+    drop(list);
+    counter.assert_all_dropped();
+
+    let list: LinkedList<_> = counter.make_n(5).into_iter().collect();
+    let mut iter = list.into_iter();
+    drop(iter.next());
+    drop(iter.next_back());
+    drop(iter);
+    counter.assert_all_dropped();
+
+    let list: LinkedList<_> = counter.make_n(3).into_iter().collect();
+    let copy = list.clone();
+    assert_eq!(6, counter.live());
+    drop(list);
+    assert_eq!(3, counter.live());
+    drop(copy);
+    counter.assert_all_dropped();
+  }
+
+  #[test]
+  fn production_cursor_split_and_splice() {
+    let counter = DropCounter::new();
+
+    // At the front, in the middle and at the ghost.
+    for moves in [1, 3, 0] {
+      let mut list: LinkedList<_> = counter.make_n(5).into_iter().collect();
+      let mut cursor = list.cursor_mut();
+      for _ in 0..moves {
+        cursor.move_next();
+      }
+
+      let front = cursor.split_before();
+      cursor.splice_before(counter.make_n(2).into_iter().collect());
+      cursor.splice_before(LinkedList::new());
+      assert_eq!(7, counter.live());
+
+      drop(front);
+      drop(list);
+      counter.assert_all_dropped();
+    }
+  }
+
+  #[test]
+  fn production_drop_and_clear_survive_a_panic() {
+    let counter = DropCounter::new();
+
+    let list: LinkedList<_> = counter.make_n(5).into_iter().collect();
+    counter.panic_on_drop(counter.created() - 3);
+    drop_panicking(list);
+    counter.assert_all_dropped();
+
+    let mut list: LinkedList<_> = counter.make_n(5).into_iter().collect();
+    counter.panic_on_drop(counter.created() - 5);
+    let result = panic::catch_unwind(AssertUnwindSafe(|| list.clear()));
+    assert!(result.is_err());
+    // `clear` stops at the panic, but the rest stay in the list.
+    assert_eq!(4, list.len());
+    drop(list);
+    counter.assert_all_dropped();
+  }
+}
@@ -0,0 +1,30 @@
+//! The loop the lists' `Drop` impls share.
+
+use std::mem;
+
+/// Pops and drops everything `pop` hands out of `state`.
+///
+/// If an element panics while it's being dropped, a guard carries on
+/// popping the rest while unwinding, instead of leaking them. A second
+/// panic in there aborts, the same as for `Vec`.
+pub(crate) fn drop_all_with_guard<S, T>(state: &mut S, pop: fn(&mut S) -> Option<T>) {
+  struct DropGuard<'a, S, T> {
+    state: &'a mut S,
+    pop: fn(&mut S) -> Option<T>,
+  }
+
+  impl<S, T> Drop for DropGuard<'_, S, T> {
+    fn drop(&mut self) {
+      while (self.pop)(self.state).is_some() {}
+    }
+  }
+
+  while let Some(elem) = pop(state) {
+    let guard = DropGuard {
+      state: &mut *state,
+      pop,
+    };
+    drop(elem);
+    mem::forget(guard);
+  }
+}
@@ -0,0 +1,389 @@
+//! C bindings for a `LinkedList` of opaque pointers.
+//!
+//! The matching declarations live in `include/linked_list.h`. The list never
+//! looks at the pointers it stores, so ownership of whatever they point to
+//! stays with the caller until `ll_free` hands them to the destructor.
+//!
+//! All functions accept a null list, iterator or cursor and treat it as an
+//! empty one. Apart from that, the usual C rules apply: every handle must
+//! come from the matching constructor, must not be used after it has been
+//! freed, and an iterator or cursor must be freed before its list is
+//! modified through anything else or freed.
+#![allow(clippy::missing_safety_doc)]
+
+use std::{ffi::c_void, ptr};
+
+use crate::production_linked_list::{CursorMut, Iter, LinkedList};
+
+pub type LlList = LinkedList<*mut c_void>;
+
+/// Called once for every element left in a list when it's freed.
+pub type LlDestructor = Option<unsafe extern "C" fn(*mut c_void)>;
+
+/// The iterator and cursor borrow their list for as long as C says so, which
+/// the borrow checker can't see, hence the `'static`.
+pub struct LlIter(Iter<'static, *mut c_void>);
+pub struct LlCursor(CursorMut<'static, *mut c_void>);
+
+#[no_mangle]
+pub extern "C" fn ll_new() -> *mut LlList {
+  Box::into_raw(Box::new(LinkedList::new()))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn ll_free(list: *mut LlList, destructor: LlDestructor) {
+  if list.is_null() {
+    return;
+  }
+
+  let list = Box::from_raw(list);
+
+  if let Some(destructor) = destructor {
+    for elem in *list {
+      destructor(elem);
+    }
+  }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn ll_len(list: *const LlList) -> usize {
+  list.as_ref().map_or(0, LinkedList::len)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn ll_push_front(list: *mut LlList, elem: *mut c_void) {
+  if let Some(list) = list.as_mut() {
+    list.push_front(elem);
+  }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn ll_push_back(list: *mut LlList, elem: *mut c_void) {
+  if let Some(list) = list.as_mut() {
+    list.push_back(elem);
+  }
+}
+
+/// Writes the popped element to `out` (if it isn't null). Returns false if
+/// the list was empty, since null is a perfectly fine element.
+#[no_mangle]
+pub unsafe extern "C" fn ll_pop_front(list: *mut LlList, out: *mut *mut c_void) -> bool {
+  write_out(list.as_mut().and_then(LinkedList::pop_front), out)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn ll_pop_back(list: *mut LlList, out: *mut *mut c_void) -> bool {
+  write_out(list.as_mut().and_then(LinkedList::pop_back), out)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn ll_iter_new(list: *const LlList) -> *mut LlIter {
+  match list.as_ref() {
+    Some(list) => Box::into_raw(Box::new(LlIter(list.iter()))),
+    None => ptr::null_mut(),
+  }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn ll_iter_next(iter: *mut LlIter, out: *mut *mut c_void) -> bool {
+  write_out(iter.as_mut().and_then(|iter| iter.0.next().copied()), out)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn ll_iter_next_back(iter: *mut LlIter, out: *mut *mut c_void) -> bool {
+  write_out(
+    iter.as_mut().and_then(|iter| iter.0.next_back().copied()),
+    out,
+  )
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn ll_iter_free(iter: *mut LlIter) {
+  if !iter.is_null() {
+    drop(Box::from_raw(iter));
+  }
+}
+
+/// The cursor starts at the "ghost" position between the back and the front.
+#[no_mangle]
+pub unsafe extern "C" fn ll_cursor_new(list: *mut LlList) -> *mut LlCursor {
+  match list.as_mut() {
+    Some(list) => Box::into_raw(Box::new(LlCursor(list.cursor_mut()))),
+    None => ptr::null_mut(),
+  }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn ll_cursor_move_next(cursor: *mut LlCursor) {
+  if let Some(cursor) = cursor.as_mut() {
+    cursor.0.move_next();
+  }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn ll_cursor_move_prev(cursor: *mut LlCursor) {
+  if let Some(cursor) = cursor.as_mut() {
+    cursor.0.move_prev();
+  }
+}
+
+/// Returns false when the cursor is at the ghost position.
+#[no_mangle]
+pub unsafe extern "C" fn ll_cursor_current(cursor: *mut LlCursor, out: *mut *mut c_void) -> bool {
+  write_out(
+    cursor
+      .as_mut()
+      .and_then(|cursor| cursor.0.current().copied()),
+    out,
+  )
+}
+
+/// Returns false when the cursor is at the ghost position.
+#[no_mangle]
+pub unsafe extern "C" fn ll_cursor_index(cursor: *const LlCursor, out: *mut usize) -> bool {
+  write_out(cursor.as_ref().and_then(|cursor| cursor.0.index()), out)
+}
+
+/// Moves everything before the cursor into a new list.
+#[no_mangle]
+pub unsafe extern "C" fn ll_cursor_split_before(cursor: *mut LlCursor) -> *mut LlList {
+  match cursor.as_mut() {
+    Some(cursor) => Box::into_raw(Box::new(cursor.0.split_before())),
+    None => ptr::null_mut(),
+  }
+}
+
+/// Moves every element of `input` in front of the cursor and frees `input`.
+/// With a null cursor nothing happens, and `input` still belongs to the
+/// caller.
+#[no_mangle]
+pub unsafe extern "C" fn ll_cursor_splice_before(cursor: *mut LlCursor, input: *mut LlList) {
+  let Some(cursor) = cursor.as_mut() else {
+    return;
+  };
+
+  if !input.is_null() {
+    cursor.0.splice_before(*Box::from_raw(input));
+  }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn ll_cursor_free(cursor: *mut LlCursor) {
+  if !cursor.is_null() {
+    drop(Box::from_raw(cursor));
+  }
+}
+
+unsafe fn write_out<T>(value: Option<T>, out: *mut T) -> bool {
+  match value {
+    Some(value) => {
+      if !out.is_null() {
+        out.write(value);
+      }
+      true
+    }
+    None => false,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::sync::atomic::{AtomicUsize, Ordering};
+
+  // Go through the exported symbols rather than the Rust functions, the same
+  // way C code linking against the library would.
+  mod sys {
+    use std::ffi::c_void;
+
+    // Opaque handles, the way bindgen would translate the header.
+    #[repr(C)]
+    pub struct LinkedList {
+      _private: [u8; 0],
+    }
+    #[repr(C)]
+    pub struct LinkedListIter {
+      _private: [u8; 0],
+    }
+    #[repr(C)]
+    pub struct LinkedListCursor {
+      _private: [u8; 0],
+    }
+
+    pub type Destructor = Option<unsafe extern "C" fn(*mut c_void)>;
+
+    extern "C" {
+      pub fn ll_new() -> *mut LinkedList;
+      pub fn ll_free(list: *mut LinkedList, destructor: Destructor);
+      pub fn ll_len(list: *const LinkedList) -> usize;
+      pub fn ll_push_front(list: *mut LinkedList, elem: *mut c_void);
+      pub fn ll_push_back(list: *mut LinkedList, elem: *mut c_void);
+      pub fn ll_pop_front(list: *mut LinkedList, out: *mut *mut c_void) -> bool;
+      pub fn ll_pop_back(list: *mut LinkedList, out: *mut *mut c_void) -> bool;
+      pub fn ll_iter_new(list: *const LinkedList) -> *mut LinkedListIter;
+      pub fn ll_iter_next(iter: *mut LinkedListIter, out: *mut *mut c_void) -> bool;
+      pub fn ll_iter_next_back(iter: *mut LinkedListIter, out: *mut *mut c_void) -> bool;
+      pub fn ll_iter_free(iter: *mut LinkedListIter);
+      pub fn ll_cursor_new(list: *mut LinkedList) -> *mut LinkedListCursor;
+      pub fn ll_cursor_move_next(cursor: *mut LinkedListCursor);
+      pub fn ll_cursor_move_prev(cursor: *mut LinkedListCursor);
+      pub fn ll_cursor_current(cursor: *mut LinkedListCursor, out: *mut *mut c_void) -> bool;
+      pub fn ll_cursor_index(cursor: *const LinkedListCursor, out: *mut usize) -> bool;
+      pub fn ll_cursor_split_before(cursor: *mut LinkedListCursor) -> *mut LinkedList;
+      pub fn ll_cursor_splice_before(cursor: *mut LinkedListCursor, input: *mut LinkedList);
+      pub fn ll_cursor_free(cursor: *mut LinkedListCursor);
+    }
+  }
+
+  fn boxed(value: i32) -> *mut c_void {
+    Box::into_raw(Box::new(value)) as *mut c_void
+  }
+
+  unsafe fn read(elem: *mut c_void) -> i32 {
+    *(elem as *const i32)
+  }
+
+  unsafe fn collect(list: *const sys::LinkedList) -> Vec<i32> {
+    let iter = sys::ll_iter_new(list);
+    let mut out = ptr::null_mut();
+    let mut values = Vec::new();
+
+    while sys::ll_iter_next(iter, &mut out) {
+      values.push(read(out));
+    }
+
+    sys::ll_iter_free(iter);
+    values
+  }
+
+  static FREED: AtomicUsize = AtomicUsize::new(0);
+
+  unsafe extern "C" fn free_boxed(elem: *mut c_void) {
+    drop(Box::from_raw(elem as *mut i32));
+    FREED.fetch_add(1, Ordering::SeqCst);
+  }
+
+  #[test]
+  fn push_pop_and_free() {
+    unsafe {
+      let list = sys::ll_new();
+      let mut out = ptr::null_mut();
+
+      assert_eq!(0, sys::ll_len(list));
+      assert!(!sys::ll_pop_front(list, &mut out));
+      assert!(!sys::ll_pop_back(list, &mut out));
+
+      sys::ll_push_back(list, boxed(2));
+      sys::ll_push_front(list, boxed(1));
+      sys::ll_push_back(list, boxed(3));
+      sys::ll_push_back(list, ptr::null_mut());
+
+      assert_eq!(4, sys::ll_len(list));
+
+      // Null is a valid element, which is why popping reports success apart
+      // from the value.
+      out = ptr::NonNull::dangling().as_ptr();
+      assert!(sys::ll_pop_back(list, &mut out));
+      assert!(out.is_null());
+
+      assert!(sys::ll_pop_front(list, &mut out));
+      assert_eq!(1, read(out));
+      free_boxed(out);
+
+      assert_eq!(vec![2, 3], collect(list));
+
+      FREED.store(0, Ordering::SeqCst);
+      sys::ll_free(list, Some(free_boxed));
+      assert_eq!(2, FREED.load(Ordering::SeqCst));
+    }
+  }
+
+  #[test]
+  fn free_without_destructor() {
+    unsafe {
+      let mut values = [1, 2, 3];
+      let list = sys::ll_new();
+
+      for value in values.iter_mut() {
+        sys::ll_push_back(list, value as *mut i32 as *mut c_void);
+      }
+
+      let iter = sys::ll_iter_new(list);
+      let mut out = ptr::null_mut();
+      assert!(sys::ll_iter_next_back(iter, &mut out));
+      assert_eq!(3, read(out));
+      sys::ll_iter_free(iter);
+
+      sys::ll_free(list, None);
+      assert_eq!([1, 2, 3], values);
+    }
+  }
+
+  #[test]
+  fn cursor() {
+    unsafe {
+      let list = sys::ll_new();
+      let mut values = [0, 1, 2, 3, 4];
+
+      for value in values.iter_mut() {
+        sys::ll_push_back(list, value as *mut i32 as *mut c_void);
+      }
+
+      let cursor = sys::ll_cursor_new(list);
+      let mut out = ptr::null_mut();
+      let mut index = 0;
+
+      assert!(!sys::ll_cursor_current(cursor, &mut out));
+      assert!(!sys::ll_cursor_index(cursor, &mut index));
+
+      sys::ll_cursor_move_prev(cursor);
+      assert!(sys::ll_cursor_current(cursor, &mut out));
+      assert_eq!(4, read(out));
+      assert!(sys::ll_cursor_index(cursor, &mut index));
+      assert_eq!(4, index);
+
+      sys::ll_cursor_move_prev(cursor);
+      sys::ll_cursor_move_prev(cursor);
+      assert!(sys::ll_cursor_index(cursor, &mut index));
+      assert_eq!(2, index);
+
+      let front = sys::ll_cursor_split_before(cursor);
+      assert!(sys::ll_cursor_index(cursor, &mut index));
+      assert_eq!(0, index);
+
+      sys::ll_cursor_move_next(cursor);
+      sys::ll_cursor_move_next(cursor);
+      sys::ll_cursor_splice_before(cursor, front);
+      assert!(sys::ll_cursor_index(cursor, &mut index));
+      assert_eq!(4, index);
+
+      sys::ll_cursor_free(cursor);
+
+      assert_eq!(vec![2, 3, 0, 1, 4], collect(list));
+      sys::ll_free(list, None);
+    }
+  }
+
+  #[test]
+  fn null_handles() {
+    unsafe {
+      let mut out = ptr::null_mut();
+
+      assert_eq!(0, sys::ll_len(ptr::null()));
+      sys::ll_push_back(ptr::null_mut(), ptr::null_mut());
+      assert!(!sys::ll_pop_front(ptr::null_mut(), &mut out));
+      assert!(sys::ll_iter_new(ptr::null()).is_null());
+      assert!(!sys::ll_iter_next(ptr::null_mut(), &mut out));
+      assert!(sys::ll_cursor_new(ptr::null_mut()).is_null());
+      sys::ll_free(ptr::null_mut(), Some(free_boxed));
+
+      // Splicing into a null cursor leaves the input with the caller.
+      let mut value = 7;
+      let input = sys::ll_new();
+      sys::ll_push_back(input, &mut value as *mut i32 as *mut c_void);
+      sys::ll_cursor_splice_before(ptr::null_mut(), input);
+      assert_eq!(vec![7], collect(input));
+      sys::ll_free(input, None);
+    }
+  }
+}
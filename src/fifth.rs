@@ -1,3 +1,11 @@
+use std::{fmt, mem};
+
+use crate::{
+  dot::{Edge, Graph, ToDot},
+  memory::MemoryUsage,
+  traits::Queue,
+};
+
 pub struct List<T> {
   head: Link<T>,
   tail: *mut Node<T>,
@@ -55,6 +63,57 @@ impl<T> List<T> {
   }
 }
 
+impl<T: fmt::Display> ToDot for List<T> {
+  fn add_to_graph(&self, graph: &mut Graph, name: &str) {
+    let mut current = self.head.as_deref();
+
+    graph.marker(name, "head", current.map(|node| node as *const Node<T>));
+    graph.marker(
+      name,
+      "tail",
+      Some(self.tail as *const Node<T>).filter(|tail| !tail.is_null()),
+    );
+
+    while let Some(node) = current {
+      graph.node(node, &node.elem);
+
+      if let Some(next) = node.next.as_deref() {
+        graph.edge(node, next, Edge::Next);
+      }
+
+      current = node.next.as_deref();
+    }
+  }
+}
+
+impl<T> MemoryUsage for List<T> {
+  fn node_count(&self) -> usize {
+    let mut count = 0;
+    let mut current = self.head.as_deref();
+
+    while let Some(node) = current {
+      count += 1;
+      current = node.next.as_deref();
+    }
+
+    count
+  }
+
+  fn heap_bytes(&self) -> usize {
+    self.node_count() * mem::size_of::<Node<T>>()
+  }
+}
+
+impl<T> Queue<T> for List<T> {
+  fn enqueue(&mut self, elem: T) {
+    List::push(self, elem);
+  }
+
+  fn dequeue(&mut self) -> Option<T> {
+    List::pop(self)
+  }
+}
+
 #[cfg(test)]
 mod tests {
   use super::*;
@@ -88,4 +147,6 @@ mod tests {
     assert_eq!(Some(7), list.pop());
     assert_eq!(None, list.pop());
   }
+
+  crate::traits::conformance::queue_conformance!(List<i32>);
 }
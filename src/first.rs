@@ -1,3 +1,11 @@
+use std::mem;
+
+use crate::{
+  dot::{Edge, Graph, ToDot},
+  memory::MemoryUsage,
+  traits::Stack,
+};
+
 /// Because List if a struct with a single field, its size is the same as the field.
 pub struct List {
   head: Link,
@@ -13,6 +21,12 @@ struct Node {
   next: Link,
 }
 
+impl Default for List {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
 impl List {
   pub fn new() -> Self {
     Self { head: Link::Empty }
@@ -49,6 +63,57 @@ impl Drop for List {
   }
 }
 
+impl ToDot for List {
+  fn add_to_graph(&self, graph: &mut Graph, name: &str) {
+    let mut current = match &self.head {
+      Link::More(node) => Some(&**node),
+      Link::Empty => None,
+    };
+
+    graph.marker(name, "head", current.map(|node| node as *const Node));
+
+    while let Some(node) = current {
+      graph.node(node, node.elem);
+
+      current = match &node.next {
+        Link::More(next) => {
+          graph.edge(node, &**next, Edge::Next);
+          Some(next)
+        }
+        Link::Empty => None,
+      };
+    }
+  }
+}
+
+impl MemoryUsage for List {
+  fn node_count(&self) -> usize {
+    let mut count = 0;
+    let mut current = &self.head;
+
+    while let Link::More(node) = current {
+      count += 1;
+      current = &node.next;
+    }
+
+    count
+  }
+
+  fn heap_bytes(&self) -> usize {
+    self.node_count() * mem::size_of::<Node>()
+  }
+}
+
+impl Stack<i32> for List {
+  fn push(&mut self, elem: i32) {
+    List::push(self, elem);
+  }
+
+  fn pop(&mut self) -> Option<i32> {
+    List::pop(self)
+  }
+}
+
 #[cfg(test)]
 mod tests {
   use super::*;
@@ -74,4 +139,6 @@ mod tests {
     assert_eq!(Some(3), list.pop());
     assert_eq!(None, list.pop());
   }
+
+  crate::traits::conformance::stack_conformance!(List);
 }
@@ -1,6 +1,16 @@
 use std::{
   cell::{Ref, RefCell, RefMut},
+  fmt,
   rc::Rc,
+  str::FromStr,
+};
+
+use crate::{
+  dot::{Edge, Graph, ToDot},
+  drop_guard,
+  list_fmt::{self, ParseListError},
+  memory::{self, MemoryUsage},
+  traits::{Deque, Queue, Stack},
 };
 
 pub struct List<T> {
@@ -64,8 +74,7 @@ impl<T> List<T> {
     match head.borrow_mut().next.take() {
       Some(new_head) => {
         let _ = new_head.borrow_mut().prev.take();
-        self.head = Some(Rc::clone(&new_head));
-        self.tail = self.head.clone();
+        self.head = Some(new_head);
       }
       None => {
         // List is empty since we removed the only element it had.
@@ -148,7 +157,121 @@ impl<T> List<T> {
 
 impl<T> Drop for List<T> {
   fn drop(&mut self) {
-    while self.pop_front().is_some() {}
+    drop_guard::drop_all_with_guard(self, Self::pop_front);
+  }
+}
+
+impl<T: fmt::Display> fmt::Display for List<T> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    // There's no borrowing iterator, so hold on to every node while
+    // the elements are written out.
+    let mut nodes = Vec::new();
+    let mut current = self.head.clone();
+
+    while let Some(node) = current {
+      current = node.borrow().next.clone();
+      nodes.push(node);
+    }
+
+    let elems = nodes
+      .iter()
+      .map(|node| Ref::map(node.borrow(), |node| &node.elem));
+
+    list_fmt::write_list(f, elems, list_fmt::DOUBLY_LINKED)
+  }
+}
+
+impl<T: fmt::Display> ToDot for List<T> {
+  fn add_to_graph(&self, graph: &mut Graph, name: &str) {
+    graph.marker(name, "head", self.head.as_ref().map(Rc::as_ptr));
+    graph.marker(name, "tail", self.tail.as_ref().map(Rc::as_ptr));
+
+    let mut current = self.head.clone();
+
+    while let Some(node) = current {
+      let node_ref = node.borrow();
+
+      // Leave out the reference held by `current`.
+      let label = format!("{} (rc={})", node_ref.elem, Rc::strong_count(&node) - 1);
+      graph.node(Rc::as_ptr(&node), label);
+
+      if let Some(prev) = &node_ref.prev {
+        graph.edge(Rc::as_ptr(&node), Rc::as_ptr(prev), Edge::Prev);
+      }
+      if let Some(next) = &node_ref.next {
+        graph.edge(Rc::as_ptr(&node), Rc::as_ptr(next), Edge::Next);
+      }
+
+      let next = node_ref.next.clone();
+      drop(node_ref);
+      current = next;
+    }
+  }
+}
+
+impl<T> MemoryUsage for List<T> {
+  fn node_count(&self) -> usize {
+    let mut count = 0;
+    let mut current = self.head.clone();
+
+    while let Some(node) = current {
+      count += 1;
+      current = node.borrow().next.clone();
+    }
+
+    count
+  }
+
+  fn heap_bytes(&self) -> usize {
+    self.node_count() * memory::rc_allocation_size::<RefCell<Node<T>>>()
+  }
+}
+
+impl<T> Stack<T> for List<T> {
+  fn push(&mut self, elem: T) {
+    List::push_front(self, elem);
+  }
+
+  fn pop(&mut self) -> Option<T> {
+    List::pop_front(self)
+  }
+}
+
+impl<T> Queue<T> for List<T> {
+  fn enqueue(&mut self, elem: T) {
+    List::push_back(self, elem);
+  }
+
+  fn dequeue(&mut self) -> Option<T> {
+    List::pop_front(self)
+  }
+}
+
+impl<T> Deque<T> for List<T> {
+  fn push_front(&mut self, elem: T) {
+    List::push_front(self, elem);
+  }
+
+  fn push_back(&mut self, elem: T) {
+    List::push_back(self, elem);
+  }
+
+  fn pop_front(&mut self) -> Option<T> {
+    List::pop_front(self)
+  }
+
+  fn pop_back(&mut self) -> Option<T> {
+    List::pop_back(self)
+  }
+}
+
+impl<T: FromStr> FromStr for List<T> {
+  type Err = ParseListError<T::Err>;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    let mut list = List::new();
+    list_fmt::parse_list(s, |elem| list.push_back(elem))?;
+    Ok(list)
   }
 }
 
@@ -241,5 +364,45 @@ mod tests {
     assert_eq!(Some(3), iter.next_back());
   }
 
-  // NOTE: should test more permutations. Not going to because i can't be bothered.
+  #[test]
+  fn display_and_from_str() {
+    let mut list: List<i32> = "[1, 2, 3]".parse().unwrap();
+
+    assert_eq!("[1, 2, 3]", list.to_string());
+    assert_eq!("1 <-> 2 <-> 3", format!("{:#}", list));
+
+    assert_eq!(Some(3), list.pop_back());
+    assert_eq!(Some(1), list.pop_front());
+
+    let list: List<i32> = "4 <-> 5".parse().unwrap();
+    assert_eq!("[4, 5]", list.to_string());
+
+    assert!(matches!(
+      "[4, 5,]".parse::<List<i32>>(),
+      Err(ParseListError::EmptyElement { index: 2, .. })
+    ));
+  }
+
+  #[test]
+  fn pop_front_keeps_the_tail() {
+    let mut list = List::new();
+
+    list.push_back(1);
+    list.push_back(2);
+    list.push_back(3);
+
+    assert_eq!(Some(1), list.pop_front());
+    assert_eq!(3, *list.peek_back().unwrap());
+    assert_eq!(Some(3), list.pop_back());
+    assert_eq!(Some(2), list.pop_back());
+    assert_eq!(None, list.pop_back());
+  }
+
+  // More permutations are covered by the model-based tests in `model.rs`.
+
+  crate::traits::conformance::stack_conformance!(List<i32>);
+
+  crate::traits::conformance::queue_conformance!(List<i32>);
+
+  crate::traits::conformance::deque_conformance!(List<i32>);
 }
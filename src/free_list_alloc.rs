@@ -0,0 +1,710 @@
+//! A memory allocator for a fixed region of bytes, the way small embedded
+//! allocators work: the free parts of the region form a singly linked list,
+//! with each free block's size and `next` pointer written at its start.
+//!
+//! The list is kept in address order, so freeing a block can merge it with
+//! the free blocks right before and after it. Allocated blocks carry no
+//! header at all, `deallocate` works out their size from the `Layout` the
+//! same way `allocate` did.
+//!
+//! [`FreeList`] is the allocator itself, [`GlobalFreeList`] wraps it in a
+//! spin lock so it can be a `#[global_allocator]`:
+//!
+//! ```no_run
+//! use learn_rust_with_entirely_too_many_linked_lists::free_list_alloc::{Fit, GlobalFreeList};
+//!
+//! const HEAP_LEN: usize = 1 << 20;
+//! static mut HEAP: [u8; HEAP_LEN] = [0; HEAP_LEN];
+//!
+//! #[global_allocator]
+//! static ALLOC: GlobalFreeList =
+//!   unsafe { GlobalFreeList::new(&raw mut HEAP as *mut u8, HEAP_LEN, Fit::First) };
+//! ```
+
+use std::{
+  alloc::{GlobalAlloc, Layout},
+  cell::UnsafeCell,
+  error, fmt,
+  marker::PhantomData,
+  mem,
+  ops::{Deref, DerefMut},
+  ptr::{self, NonNull},
+  sync::atomic::{AtomicBool, Ordering},
+};
+
+/// Every block starts on a multiple of this and is a multiple of it long,
+/// so whatever's left over when a block is split can hold a header.
+const UNIT: usize = mem::size_of::<FreeBlock>().next_power_of_two();
+
+/// How `allocate` picks between the free blocks that are big enough.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Fit {
+  /// The first one, nearest the start of the region. Quick, but tends to
+  /// leave slivers at the front.
+  #[default]
+  First,
+  /// The one with the least left over, which keeps big blocks whole for
+  /// longer but always walks the whole list.
+  Best,
+}
+
+struct FreeBlock {
+  /// In bytes, header included.
+  size: usize,
+  next: *mut FreeBlock,
+}
+
+pub struct FreeList {
+  head: *mut FreeBlock,
+  /// The region, trimmed to whole units.
+  start: *mut u8,
+  capacity: usize,
+  used: usize,
+  fit: Fit,
+}
+
+// The list owns its region, which nothing else touches.
+unsafe impl Send for FreeList {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Stats {
+  pub capacity: usize,
+  pub used: usize,
+  pub free: usize,
+  pub free_blocks: usize,
+  pub largest_free_block: usize,
+}
+
+impl Stats {
+  /// How much of the free memory can't be had in one allocation, from 0
+  /// when it's all one block to nearly 1 when it's in crumbs.
+  pub fn fragmentation(&self) -> f64 {
+    if self.free == 0 {
+      0.0
+    } else {
+      1.0 - self.largest_free_block as f64 / self.free as f64
+    }
+  }
+}
+
+/// Size and alignment of the block that backs an allocation of `layout`.
+fn block_layout(layout: Layout) -> Option<(usize, usize)> {
+  let size = layout.size().max(1).checked_next_multiple_of(UNIT)?;
+  Some((size, layout.align().max(UNIT)))
+}
+
+impl FreeList {
+  /// Manages the `len` bytes at `region`, minus whatever it takes to line
+  /// the start and end up to whole units.
+  ///
+  /// # Safety
+  ///
+  /// The region has to be valid for reads and writes, and left alone by
+  /// everything else, for as long as the list or anything it allocated is
+  /// in use.
+  pub unsafe fn new(region: *mut u8, len: usize, fit: Fit) -> Self {
+    let offset = region.align_offset(UNIT).min(len);
+    let capacity = (len - offset) / UNIT * UNIT;
+    let start = region.add(offset);
+
+    let head = if capacity == 0 {
+      ptr::null_mut()
+    } else {
+      let head = start.cast::<FreeBlock>();
+      head.write(FreeBlock {
+        size: capacity,
+        next: ptr::null_mut(),
+      });
+      head
+    };
+
+    Self {
+      head,
+      start,
+      capacity,
+      used: 0,
+      fit,
+    }
+  }
+
+  /// Manages a region that's never freed, like a leaked `Box<[u8]>`.
+  pub fn from_static(region: &'static mut [u8], fit: Fit) -> Self {
+    // SAFETY: the exclusive borrow lasts forever.
+    unsafe { Self::new(region.as_mut_ptr(), region.len(), fit) }
+  }
+
+  pub fn fit(&self) -> Fit {
+    self.fit
+  }
+
+  pub fn set_fit(&mut self, fit: Fit) {
+    self.fit = fit;
+  }
+
+  /// A block for `layout`, or `None` if no free block is big enough.
+  pub fn allocate(&mut self, layout: Layout) -> Option<NonNull<u8>> {
+    let (size, align) = block_layout(layout)?;
+
+    unsafe {
+      // The link that points at the chosen block, the block, and the
+      // padding needed in front of it to line it up.
+      let mut chosen: Option<(*mut *mut FreeBlock, *mut FreeBlock, usize)> = None;
+      let mut slot: *mut *mut FreeBlock = &mut self.head;
+
+      while !(*slot).is_null() {
+        let block = *slot;
+        let padding = block.cast::<u8>().align_offset(align);
+
+        if padding
+          .checked_add(size)
+          .is_some_and(|needed| needed <= (*block).size)
+        {
+          let leftover = (*block).size - padding - size;
+          let better = match chosen {
+            None => true,
+            Some((_, best, best_padding)) => leftover < (*best).size - best_padding - size,
+          };
+          if better {
+            chosen = Some((slot, block, padding));
+          }
+          if self.fit == Fit::First || leftover == 0 {
+            break;
+          }
+        }
+
+        slot = &mut (*block).next;
+      }
+
+      let (slot, block, padding) = chosen?;
+      let next = (*block).next;
+      let tail_size = (*block).size - padding - size;
+      let allocation = block.cast::<u8>().add(padding);
+
+      // What comes after the allocation: the rest of the block, or
+      // whatever came after it.
+      let after = if tail_size > 0 {
+        let tail = allocation.add(size).cast::<FreeBlock>();
+        tail.write(FreeBlock {
+          size: tail_size,
+          next,
+        });
+        tail
+      } else {
+        next
+      };
+
+      if padding > 0 {
+        // The padding stays behind as a smaller free block.
+        (*block).size = padding;
+        (*block).next = after;
+      } else {
+        *slot = after;
+      }
+
+      self.used += size;
+      Some(NonNull::new_unchecked(allocation))
+    }
+  }
+
+  /// Gives a block back, merging it with any free neighbours.
+  ///
+  /// # Safety
+  ///
+  /// `ptr` has to have come from `allocate` on this list with the same
+  /// `layout`, and not been deallocated since. Freeing a block twice is
+  /// caught if the free list still covers it, but not always.
+  ///
+  /// # Panics
+  ///
+  /// If the block is outside the region or already free.
+  pub unsafe fn deallocate(&mut self, ptr: NonNull<u8>, layout: Layout) {
+    if let Err(error) = self.try_deallocate(ptr, layout) {
+      panic!("{}", error);
+    }
+  }
+
+  /// [`deallocate`](Self::deallocate), but the blocks it can tell are bad
+  /// come back as an error and leave the list untouched.
+  ///
+  /// # Safety
+  ///
+  /// The same as `deallocate`.
+  pub unsafe fn try_deallocate(
+    &mut self,
+    ptr: NonNull<u8>,
+    layout: Layout,
+  ) -> Result<(), DeallocError> {
+    let (size, _) = block_layout(layout).ok_or(DeallocError::OutsideRegion)?;
+    let block = ptr.as_ptr().cast::<FreeBlock>();
+    let (address, end) = (block as usize, block as usize + size);
+
+    if address < self.start as usize || end > self.start as usize + self.capacity {
+      return Err(DeallocError::OutsideRegion);
+    }
+
+    let mut prev: *mut FreeBlock = ptr::null_mut();
+    let mut next = self.head;
+    while !next.is_null() && (next as usize) < address {
+      prev = next;
+      next = (*next).next;
+    }
+
+    if !(prev.is_null() || prev as usize + (*prev).size <= address)
+      || !(next.is_null() || end <= next as usize)
+    {
+      return Err(DeallocError::AlreadyFree { address });
+    }
+
+    block.write(FreeBlock { size, next });
+
+    if !next.is_null() && end == next as usize {
+      (*block).size += (*next).size;
+      (*block).next = (*next).next;
+    }
+
+    if prev.is_null() {
+      self.head = block;
+    } else if prev as usize + (*prev).size == address {
+      (*prev).size += (*block).size;
+      (*prev).next = (*block).next;
+    } else {
+      (*prev).next = block;
+    }
+
+    self.used -= size;
+    Ok(())
+  }
+
+  pub fn stats(&self) -> Stats {
+    let (mut free, mut free_blocks, mut largest_free_block) = (0, 0, 0);
+
+    for (_, size) in self.free_blocks() {
+      free += size;
+      free_blocks += 1;
+      largest_free_block = largest_free_block.max(size);
+    }
+
+    Stats {
+      capacity: self.capacity,
+      used: self.used,
+      free,
+      free_blocks,
+      largest_free_block,
+    }
+  }
+
+  /// The offset from the start of the region and size of each free block,
+  /// in address order.
+  pub fn free_blocks(&self) -> FreeBlocks<'_> {
+    FreeBlocks {
+      current: self.head,
+      start: self.start,
+      _p: PhantomData,
+    }
+  }
+}
+
+impl fmt::Debug for FreeList {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_struct("FreeList")
+      .field("fit", &self.fit)
+      .field("stats", &self.stats())
+      .finish()
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeallocError {
+  /// The block isn't inside this list's region.
+  OutsideRegion,
+  /// Some of the block is already on the free list.
+  AlreadyFree { address: usize },
+}
+
+impl fmt::Display for DeallocError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      DeallocError::OutsideRegion => write!(f, "deallocating memory from outside the region"),
+      DeallocError::AlreadyFree { address } => {
+        write!(f, "block at {:#x} is already free", address)
+      }
+    }
+  }
+}
+
+impl error::Error for DeallocError {}
+
+pub struct FreeBlocks<'a> {
+  current: *mut FreeBlock,
+  start: *mut u8,
+  _p: PhantomData<&'a FreeList>,
+}
+
+impl Iterator for FreeBlocks<'_> {
+  type Item = (usize, usize);
+
+  fn next(&mut self) -> Option<Self::Item> {
+    unsafe {
+      let block = self.current.as_ref()?;
+      let offset = self.current as usize - self.start as usize;
+      self.current = block.next;
+      Some((offset, block.size))
+    }
+  }
+}
+
+/// A [`FreeList`] behind a spin lock, which sets itself up on first use so
+/// it can be built in a `static`.
+pub struct GlobalFreeList {
+  locked: AtomicBool,
+  region: *mut u8,
+  len: usize,
+  fit: Fit,
+  list: UnsafeCell<Option<FreeList>>,
+}
+
+// Everything goes through the lock.
+unsafe impl Sync for GlobalFreeList {}
+
+impl GlobalFreeList {
+  /// # Safety
+  ///
+  /// As for [`FreeList::new`], for as long as this is in use.
+  pub const unsafe fn new(region: *mut u8, len: usize, fit: Fit) -> Self {
+    Self {
+      locked: AtomicBool::new(false),
+      region,
+      len,
+      fit,
+      list: UnsafeCell::new(None),
+    }
+  }
+
+  pub fn stats(&self) -> Stats {
+    self.lock().stats()
+  }
+
+  pub fn lock(&self) -> FreeListGuard<'_> {
+    while self
+      .locked
+      .compare_exchange_weak(false, true, Ordering::Acquire, Ordering::Relaxed)
+      .is_err()
+    {
+      std::hint::spin_loop();
+    }
+
+    // SAFETY: holding the lock makes this the only reference.
+    let list = unsafe { &mut *self.list.get() };
+    if list.is_none() {
+      *list = Some(unsafe { FreeList::new(self.region, self.len, self.fit) });
+    }
+
+    FreeListGuard { owner: self }
+  }
+}
+
+/// Unlocks the [`GlobalFreeList`] when dropped, even by a panic.
+pub struct FreeListGuard<'a> {
+  owner: &'a GlobalFreeList,
+}
+
+impl Deref for FreeListGuard<'_> {
+  type Target = FreeList;
+
+  fn deref(&self) -> &FreeList {
+    unsafe { (*self.owner.list.get()).as_ref().unwrap() }
+  }
+}
+
+impl DerefMut for FreeListGuard<'_> {
+  fn deref_mut(&mut self) -> &mut FreeList {
+    unsafe { (*self.owner.list.get()).as_mut().unwrap() }
+  }
+}
+
+impl Drop for FreeListGuard<'_> {
+  fn drop(&mut self) {
+    self.owner.locked.store(false, Ordering::Release);
+  }
+}
+
+unsafe impl GlobalAlloc for GlobalFreeList {
+  unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+    self
+      .lock()
+      .allocate(layout)
+      .map_or(ptr::null_mut(), NonNull::as_ptr)
+  }
+
+  unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+    // Panicking out of the allocator isn't allowed, and would leave the
+    // lock held anyway.
+    let result = self
+      .lock()
+      .try_deallocate(NonNull::new_unchecked(ptr), layout);
+    if result.is_err() {
+      std::process::abort();
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::rng::Rng;
+
+  /// A 16-aligned buffer, so offsets and sizes come out the same every run.
+  fn buffer(len: usize) -> Vec<u128> {
+    vec![0; len / 16]
+  }
+
+  fn list(region: &mut [u128], fit: Fit) -> FreeList {
+    unsafe { FreeList::new(region.as_mut_ptr().cast(), region.len() * 16, fit) }
+  }
+
+  fn bytes(size: usize) -> Layout {
+    Layout::from_size_align(size, 1).unwrap()
+  }
+
+  fn offset(list: &FreeList, ptr: NonNull<u8>) -> usize {
+    ptr.as_ptr() as usize - list.start as usize
+  }
+
+  #[test]
+  fn allocate_and_coalesce() {
+    let mut region = buffer(1024);
+    let mut list = list(&mut region, Fit::First);
+    assert_eq!(vec![(0, 1024)], list.free_blocks().collect::<Vec<_>>());
+
+    let a = list.allocate(bytes(100)).unwrap();
+    let b = list.allocate(bytes(16)).unwrap();
+    let c = list.allocate(bytes(1)).unwrap();
+    assert_eq!(
+      (0, 112, 128),
+      (offset(&list, a), offset(&list, b), offset(&list, c))
+    );
+    assert_eq!(144, list.stats().used);
+
+    // Freeing the middle leaves a hole, then each side merges into it.
+    unsafe {
+      list.deallocate(b, bytes(16));
+      assert_eq!(
+        vec![(112, 16), (144, 880)],
+        list.free_blocks().collect::<Vec<_>>()
+      );
+      list.deallocate(c, bytes(1));
+      assert_eq!(vec![(112, 912)], list.free_blocks().collect::<Vec<_>>());
+      list.deallocate(a, bytes(100));
+    }
+
+    assert_eq!(vec![(0, 1024)], list.free_blocks().collect::<Vec<_>>());
+    assert_eq!(0, list.stats().used);
+  }
+
+  #[test]
+  fn first_fit_and_best_fit() {
+    // Holes of 64 and 32 bytes, in that order, then the rest.
+    let setup = |list: &mut FreeList| unsafe {
+      let blocks: Vec<_> = [64, 16, 32, 16]
+        .into_iter()
+        .map(|size| (list.allocate(bytes(size)).unwrap(), size))
+        .collect();
+      list.deallocate(blocks[0].0, bytes(blocks[0].1));
+      list.deallocate(blocks[2].0, bytes(blocks[2].1));
+    };
+
+    let mut region = buffer(512);
+    let mut first = list(&mut region, Fit::First);
+    setup(&mut first);
+    let ptr = first.allocate(bytes(32)).unwrap();
+    assert_eq!(0, offset(&first, ptr));
+
+    let mut region = buffer(512);
+    let mut best = list(&mut region, Fit::Best);
+    setup(&mut best);
+    let ptr = best.allocate(bytes(32)).unwrap();
+    assert_eq!(80, offset(&best, ptr));
+  }
+
+  #[test]
+  fn alignment() {
+    // Start the region on a 256 byte boundary so the padding is always there.
+    let mut region = buffer(1024 + 256);
+    let skip = region.as_ptr().align_offset(256);
+    let mut list = list(&mut region[skip..skip + 1024 / 16], Fit::First);
+
+    let small = list.allocate(bytes(8)).unwrap();
+    let aligned = Layout::from_size_align(64, 256).unwrap();
+    let big = list.allocate(aligned).unwrap();
+
+    assert_eq!(0, big.as_ptr() as usize % 256);
+    // The padding in front stays free.
+    let padding = offset(&list, big) - 16;
+    assert_eq!(Some((16, padding)), list.free_blocks().next());
+
+    unsafe {
+      list.deallocate(big, aligned);
+      list.deallocate(small, bytes(8));
+    }
+    assert_eq!(vec![(0, 1024)], list.free_blocks().collect::<Vec<_>>());
+  }
+
+  #[test]
+  fn runs_out() {
+    let mut region = buffer(128);
+    let mut list = list(&mut region, Fit::First);
+
+    assert!(list.allocate(bytes(129)).is_none());
+    let all = list.allocate(bytes(128)).unwrap();
+    assert!(list.allocate(bytes(1)).is_none());
+    assert_eq!(None, list.free_blocks().next());
+
+    unsafe { list.deallocate(all, bytes(128)) };
+    assert!(list
+      .allocate(Layout::from_size_align(1, 1 << 20).unwrap())
+      .is_none());
+
+    // A region too small for even one block is just always full.
+    let mut tiny = [0u8; 8];
+    let mut list = unsafe { FreeList::new(tiny.as_mut_ptr(), tiny.len(), Fit::First) };
+    assert!(list.allocate(bytes(1)).is_none());
+    assert_eq!(0, list.stats().capacity);
+  }
+
+  #[test]
+  #[should_panic(expected = "already free")]
+  fn catches_double_free() {
+    let mut region = buffer(256);
+    let mut list = list(&mut region, Fit::First);
+
+    let a = list.allocate(bytes(16)).unwrap();
+    let _b = list.allocate(bytes(16)).unwrap();
+    unsafe {
+      list.deallocate(a, bytes(16));
+      list.deallocate(a, bytes(16));
+    }
+  }
+
+  #[test]
+  fn try_deallocate_reports_bad_blocks() {
+    let mut region = buffer(256);
+    let mut list = list(&mut region, Fit::First);
+    let mut elsewhere = buffer(16);
+
+    let a = list.allocate(bytes(16)).unwrap();
+    let outside = NonNull::new(elsewhere.as_mut_ptr().cast()).unwrap();
+    unsafe {
+      assert_eq!(
+        Err(DeallocError::OutsideRegion),
+        list.try_deallocate(outside, bytes(16))
+      );
+      assert_eq!(Ok(()), list.try_deallocate(a, bytes(16)));
+      assert_eq!(
+        Err(DeallocError::AlreadyFree {
+          address: a.as_ptr() as usize
+        }),
+        list.try_deallocate(a, bytes(16))
+      );
+    }
+    assert_eq!(vec![(0, 256)], list.free_blocks().collect::<Vec<_>>());
+  }
+
+  #[test]
+  fn fragmentation() {
+    let mut region = buffer(1024);
+    let mut list = list(&mut region, Fit::First);
+    assert_eq!(0.0, list.stats().fragmentation());
+
+    let blocks: Vec<_> = (0..64).map(|_| list.allocate(bytes(16)).unwrap()).collect();
+    assert_eq!(0.0, list.stats().fragmentation());
+
+    // Every other block free: plenty of room, but nothing over 16 bytes.
+    for block in blocks.iter().step_by(2) {
+      unsafe { list.deallocate(*block, bytes(16)) };
+    }
+    let stats = list.stats();
+    assert_eq!(
+      (512, 32, 16),
+      (stats.free, stats.free_blocks, stats.largest_free_block)
+    );
+    assert!(stats.fragmentation() > 0.95);
+    assert!(list.allocate(bytes(32)).is_none());
+
+    for block in blocks.iter().skip(1).step_by(2) {
+      unsafe { list.deallocate(*block, bytes(16)) };
+    }
+    assert_eq!(0.0, list.stats().fragmentation());
+    assert_eq!(1, list.stats().free_blocks);
+  }
+
+  #[test]
+  fn random_workload_never_overlaps() {
+    for fit in [Fit::First, Fit::Best] {
+      let mut region = buffer(1 << 14);
+      let mut list = list(&mut region, fit);
+      let mut live: Vec<(NonNull<u8>, Layout)> = Vec::new();
+      let mut rng = Rng(0x9e3779b97f4a7c15);
+
+      for _ in 0..5_000 {
+        if rng.below(3) != 0 || live.is_empty() {
+          let layout = Layout::from_size_align(rng.below(200) + 1, 1 << rng.below(7)).unwrap();
+          if let Some(ptr) = list.allocate(layout) {
+            assert_eq!(0, ptr.as_ptr() as usize % layout.align());
+            // Stamp it, to check nothing else hands out the same bytes.
+            unsafe { ptr.as_ptr().write_bytes(live.len() as u8, layout.size()) };
+            live.push((ptr, layout));
+          }
+        } else {
+          let (ptr, layout) = live.swap_remove(rng.below(live.len()));
+          unsafe { list.deallocate(ptr, layout) };
+        }
+
+        let mut spans: Vec<(usize, usize)> = list
+          .free_blocks()
+          .map(|(offset, size)| (offset, offset + size))
+          .chain(live.iter().map(|&(ptr, layout)| {
+            let start = offset(&list, ptr);
+            (start, start + block_layout(layout).unwrap().0)
+          }))
+          .collect();
+        spans.sort();
+        assert!(
+          spans.windows(2).all(|pair| pair[0].1 <= pair[1].0),
+          "{:?}",
+          spans
+        );
+      }
+
+      for (ptr, layout) in live.drain(..) {
+        unsafe { list.deallocate(ptr, layout) };
+      }
+      assert_eq!(vec![(0, 1 << 14)], list.free_blocks().collect::<Vec<_>>());
+    }
+  }
+
+  #[test]
+  fn global_alloc() {
+    let mut region = buffer(4096);
+    let alloc = unsafe { GlobalFreeList::new(region.as_mut_ptr().cast(), 4096, Fit::Best) };
+
+    unsafe {
+      let layout = Layout::array::<u64>(10).unwrap();
+      let ptr = alloc.alloc(layout).cast::<u64>();
+      assert!(!ptr.is_null());
+      for i in 0..10 {
+        ptr.add(i).write(i as u64);
+      }
+
+      let grown = alloc.realloc(ptr.cast(), layout, 400).cast::<u64>();
+      assert_eq!(9, grown.add(9).read());
+      assert_eq!(400, alloc.stats().used);
+
+      alloc.dealloc(grown.cast(), Layout::from_size_align(400, 8).unwrap());
+      assert!(alloc
+        .alloc(Layout::from_size_align(8192, 8).unwrap())
+        .is_null());
+    }
+
+    assert_eq!(0, alloc.stats().used);
+  }
+}
@@ -0,0 +1,126 @@
+//! Entry points for fuzzers. They take arbitrary bytes and panic if they
+//! manage to break the list, so any fuzzer can drive them. With
+//! `cargo fuzz` the whole target is:
+//!
+//! ```ignore
+//! #![no_main]
+//! use learn_rust_with_entirely_too_many_linked_lists::fuzz;
+//! use libfuzzer_sys::fuzz_target;
+//!
+//! fuzz_target!(|data: &[u8]| fuzz::run_cursor_ops(data));
+//! ```
+
+use crate::production_linked_list::LinkedList;
+
+/// Decodes `data` into operations on a `CursorMut` over a `LinkedList<u8>`,
+/// checking the list's links and the cursor's position after each one.
+///
+/// Each operation is one byte, `byte % 4` picking it:
+///
+/// - `0`: `move_next`
+/// - `1`: `move_prev`
+/// - `2`: `split_before`
+/// - `3`: `splice_before`, of a list made of the next `n % 8` bytes, where
+///   `n` is the byte after the operation
+///
+/// Running out of bytes in the middle of an operation just ends it early.
+pub fn run_cursor_ops(data: &[u8]) {
+  let mut list = LinkedList::new();
+  let mut cursor = list.cursor_mut();
+  let mut bytes = data.iter().copied();
+
+  while let Some(byte) = bytes.next() {
+    match byte % 4 {
+      0 => {
+        let next = cursor.peek_next().copied();
+        cursor.move_next();
+        assert_eq!(next, cursor.current().copied(), "moved to the wrong node");
+      }
+      1 => {
+        let prev = cursor.peek_prev().copied();
+        cursor.move_prev();
+        assert_eq!(prev, cursor.current().copied(), "moved to the wrong node");
+      }
+      2 => {
+        let index = cursor.index();
+        let front = cursor.split_before();
+
+        front.check_invariants();
+        assert_eq!(
+          index.unwrap_or(front.len()),
+          front.len(),
+          "split off the wrong number of nodes"
+        );
+      }
+      _ => {
+        let len = bytes.next().unwrap_or(0) % 8;
+        let input: LinkedList<u8> = bytes.by_ref().take(len as usize).collect();
+        let index = cursor.index();
+        let input_len = input.len();
+
+        cursor.splice_before(input);
+        assert_eq!(
+          index.map(|index| index + input_len),
+          cursor.index(),
+          "splicing moved the cursor"
+        );
+      }
+    }
+
+    cursor.check_invariants();
+  }
+
+  list.check_invariants();
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn empty_input() {
+    run_cursor_ops(&[]);
+  }
+
+  #[test]
+  fn corpus() {
+    let corpus: &[&[u8]] = &[
+      // Splice into the empty list, then walk all the way around.
+      &[3, 3, 1, 2, 3, 0, 0, 0, 0, 1, 1, 1, 1],
+      // Split at the front, the back and the ghost.
+      &[3, 4, 1, 2, 3, 4, 0, 2, 1, 2, 0, 2],
+      // Splice at the front, in the middle and at the ghost.
+      &[3, 2, 5, 6, 0, 3, 1, 9, 0, 3, 2, 7, 8, 0, 0, 3, 1, 4],
+      // Truncated splices.
+      &[3, 7, 1],
+      &[3],
+    ];
+
+    for data in corpus {
+      run_cursor_ops(data);
+    }
+  }
+
+  #[test]
+  fn every_short_input() {
+    // Enough values to hit each operation with a few splice lengths.
+    let alphabet: Vec<u8> = (0..12).collect();
+    let mut data = Vec::new();
+
+    fn each(alphabet: &[u8], data: &mut Vec<u8>, depth: usize) {
+      run_cursor_ops(data);
+
+      if depth == 0 {
+        return;
+      }
+
+      for &byte in alphabet {
+        data.push(byte);
+        each(alphabet, data, depth - 1);
+        data.pop();
+      }
+    }
+
+    each(&alphabet, &mut data, 4);
+  }
+}
@@ -0,0 +1,910 @@
+//! Dependency free JSON for dumping list contents.
+//!
+//! Both directions stream: [`ToJson`] writes straight into an `io::Write`
+//! and [`JsonReader`] pulls bytes from an `io::Read` through a small fixed
+//! buffer, so a list is never held in memory as text.
+//!
+//! ```
+//! use learn_rust_with_entirely_too_many_linked_lists::{json, production_linked_list::LinkedList};
+//!
+//! let list: LinkedList<String> = ["a", "b"].iter().map(|s| s.to_string()).collect();
+//!
+//! let text = json::to_string(&list).unwrap();
+//! assert_eq!(r#"["a","b"]"#, text);
+//!
+//! let parsed: LinkedList<String> = json::from_str(&text).unwrap();
+//! assert_eq!(list, parsed);
+//! ```
+
+use std::{
+  error, fmt,
+  io::{self, Read, Write},
+  marker::PhantomData,
+};
+
+use crate::production_linked_list::LinkedList;
+
+const BUF_LEN: usize = 4096;
+
+#[derive(Debug)]
+pub enum JsonErrorKind {
+  Io(io::Error),
+  UnexpectedEof,
+  UnexpectedChar(char),
+  /// Valid JSON, but not the type that was asked for.
+  UnexpectedToken {
+    expected: &'static str,
+  },
+  InvalidNumber,
+  NumberOutOfRange,
+  InvalidEscape,
+  InvalidUnicode,
+  InvalidUtf8,
+  TrailingCharacters,
+}
+
+/// An error along with the position (both 1-based) it was found at.
+#[derive(Debug)]
+pub struct JsonError {
+  pub kind: JsonErrorKind,
+  pub line: usize,
+  pub column: usize,
+}
+
+impl fmt::Display for JsonErrorKind {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      JsonErrorKind::Io(err) => write!(f, "i/o error: {}", err),
+      JsonErrorKind::UnexpectedEof => write!(f, "unexpected end of input"),
+      JsonErrorKind::UnexpectedChar(c) => write!(f, "unexpected character {:?}", c),
+      JsonErrorKind::UnexpectedToken { expected } => write!(f, "expected {}", expected),
+      JsonErrorKind::InvalidNumber => write!(f, "invalid number"),
+      JsonErrorKind::NumberOutOfRange => write!(f, "number out of range"),
+      JsonErrorKind::InvalidEscape => write!(f, "invalid escape sequence"),
+      JsonErrorKind::InvalidUnicode => write!(f, "invalid unicode escape"),
+      JsonErrorKind::InvalidUtf8 => write!(f, "invalid utf-8"),
+      JsonErrorKind::TrailingCharacters => write!(f, "trailing characters after value"),
+    }
+  }
+}
+
+impl fmt::Display for JsonError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(
+      f,
+      "{} at line {} column {}",
+      self.kind, self.line, self.column
+    )
+  }
+}
+
+impl error::Error for JsonError {
+  fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+    match &self.kind {
+      JsonErrorKind::Io(err) => Some(err),
+      _ => None,
+    }
+  }
+}
+
+pub trait ToJson {
+  fn write_json<W: Write + ?Sized>(&self, writer: &mut W) -> io::Result<()>;
+}
+
+pub trait FromJson: Sized {
+  fn read_json<R: Read>(reader: &mut JsonReader<R>) -> Result<Self, JsonError>;
+}
+
+pub fn to_writer<T, W>(writer: &mut W, value: &T) -> io::Result<()>
+where
+  T: ToJson + ?Sized,
+  W: Write + ?Sized,
+{
+  value.write_json(writer)
+}
+
+pub fn to_string<T: ToJson + ?Sized>(value: &T) -> io::Result<String> {
+  let mut bytes = Vec::new();
+  value.write_json(&mut bytes)?;
+  // We only ever write valid utf-8.
+  Ok(String::from_utf8(bytes).unwrap())
+}
+
+/// Parses a single value, only allowing whitespace after it.
+pub fn from_reader<T: FromJson, R: Read>(reader: R) -> Result<T, JsonError> {
+  let mut reader = JsonReader::new(reader);
+  let value = T::read_json(&mut reader)?;
+  reader.finish()?;
+  Ok(value)
+}
+
+pub fn from_str<T: FromJson>(s: &str) -> Result<T, JsonError> {
+  from_reader(s.as_bytes())
+}
+
+/// Writes `s` as a quoted JSON string.
+pub fn write_str<W: Write + ?Sized>(writer: &mut W, s: &str) -> io::Result<()> {
+  writer.write_all(b"\"")?;
+
+  let mut start = 0;
+
+  for (i, byte) in s.bytes().enumerate() {
+    let escape: &[u8] = match byte {
+      b'"' => b"\\\"",
+      b'\\' => b"\\\\",
+      b'\n' => b"\\n",
+      b'\r' => b"\\r",
+      b'\t' => b"\\t",
+      0x08 => b"\\b",
+      0x0c => b"\\f",
+      0x00..=0x1f => b"",
+      _ => continue,
+    };
+
+    writer.write_all(&s.as_bytes()[start..i])?;
+
+    if escape.is_empty() {
+      write!(writer, "\\u{:04x}", byte)?;
+    } else {
+      writer.write_all(escape)?;
+    }
+
+    start = i + 1;
+  }
+
+  writer.write_all(&s.as_bytes()[start..])?;
+  writer.write_all(b"\"")
+}
+
+/// Writes every item of `iter` as one JSON array without collecting it first.
+pub fn write_array<W, I>(writer: &mut W, iter: I) -> io::Result<()>
+where
+  W: Write + ?Sized,
+  I: IntoIterator,
+  I::Item: ToJson,
+{
+  writer.write_all(b"[")?;
+
+  for (i, item) in iter.into_iter().enumerate() {
+    if i > 0 {
+      writer.write_all(b",")?;
+    }
+    item.write_json(writer)?;
+  }
+
+  writer.write_all(b"]")
+}
+
+/// A pull parser over any `io::Read` that keeps track of where it is.
+pub struct JsonReader<R> {
+  reader: R,
+  buf: Box<[u8; BUF_LEN]>,
+  pos: usize,
+  filled: usize,
+  line: usize,
+  column: usize,
+}
+
+impl<R: Read> JsonReader<R> {
+  pub fn new(reader: R) -> Self {
+    Self {
+      reader,
+      buf: Box::new([0; BUF_LEN]),
+      pos: 0,
+      filled: 0,
+      line: 1,
+      column: 1,
+    }
+  }
+
+  pub fn error(&self, kind: JsonErrorKind) -> JsonError {
+    JsonError {
+      kind,
+      line: self.line,
+      column: self.column,
+    }
+  }
+
+  fn peek(&mut self) -> Result<Option<u8>, JsonError> {
+    if self.pos == self.filled {
+      self.pos = 0;
+      self.filled = loop {
+        match self.reader.read(&mut self.buf[..]) {
+          Ok(n) => break n,
+          Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+          Err(err) => return Err(self.error(JsonErrorKind::Io(err))),
+        }
+      };
+    }
+
+    Ok(self.buf[..self.filled].get(self.pos).copied())
+  }
+
+  fn bump(&mut self) -> Result<u8, JsonError> {
+    let byte = self
+      .peek()?
+      .ok_or_else(|| self.error(JsonErrorKind::UnexpectedEof))?;
+
+    self.pos += 1;
+
+    if byte == b'\n' {
+      self.line += 1;
+      self.column = 1;
+    } else if byte & 0xc0 != 0x80 {
+      // Continuation bytes belong to the character that was already counted.
+      self.column += 1;
+    }
+
+    Ok(byte)
+  }
+
+  /// Reports the next byte as unexpected. Anything outside of ascii shows up
+  /// as the replacement character, since only its first byte is at hand.
+  fn unexpected(&mut self) -> JsonError {
+    match self.peek() {
+      Ok(Some(byte)) => {
+        let c = if byte.is_ascii() {
+          byte as char
+        } else {
+          char::REPLACEMENT_CHARACTER
+        };
+        self.error(JsonErrorKind::UnexpectedChar(c))
+      }
+      Ok(None) => self.error(JsonErrorKind::UnexpectedEof),
+      Err(err) => err,
+    }
+  }
+
+  fn skip_whitespace(&mut self) -> Result<(), JsonError> {
+    while let Some(b' ' | b'\t' | b'\n' | b'\r') = self.peek()? {
+      self.bump()?;
+    }
+    Ok(())
+  }
+
+  fn expect(&mut self, expected: u8) -> Result<(), JsonError> {
+    if self.peek()? == Some(expected) {
+      self.bump()?;
+      Ok(())
+    } else {
+      Err(self.unexpected())
+    }
+  }
+
+  fn expect_literal(&mut self, literal: &[u8]) -> Result<(), JsonError> {
+    for &byte in literal {
+      self.expect(byte)?;
+    }
+    Ok(())
+  }
+
+  /// Makes sure only whitespace is left in the input.
+  pub fn finish(&mut self) -> Result<(), JsonError> {
+    self.skip_whitespace()?;
+
+    match self.peek()? {
+      None => Ok(()),
+      Some(_) => Err(self.error(JsonErrorKind::TrailingCharacters)),
+    }
+  }
+
+  /// Returns true and consumes `null` if it is the next value.
+  pub fn read_null(&mut self) -> Result<bool, JsonError> {
+    self.skip_whitespace()?;
+
+    if self.peek()? == Some(b'n') {
+      self.expect_literal(b"null")?;
+      Ok(true)
+    } else {
+      Ok(false)
+    }
+  }
+
+  pub fn read_bool(&mut self) -> Result<bool, JsonError> {
+    self.skip_whitespace()?;
+
+    match self.peek()? {
+      Some(b't') => self.expect_literal(b"true").map(|_| true),
+      Some(b'f') => self.expect_literal(b"false").map(|_| false),
+      Some(b'"' | b'[' | b'{' | b'n' | b'-' | b'0'..=b'9') => {
+        Err(self.error(JsonErrorKind::UnexpectedToken { expected: "a bool" }))
+      }
+      _ => Err(self.unexpected()),
+    }
+  }
+
+  /// Reads a number and returns its text, checked against the JSON grammar,
+  /// along with the position it started at.
+  fn read_number_text(&mut self) -> Result<(String, usize, usize), JsonError> {
+    self.skip_whitespace()?;
+
+    let (line, column) = (self.line, self.column);
+
+    match self.peek()? {
+      Some(b'-' | b'0'..=b'9') => {}
+      Some(b'"' | b'[' | b'{' | b't' | b'f' | b'n') => {
+        return Err(self.error(JsonErrorKind::UnexpectedToken {
+          expected: "a number",
+        }))
+      }
+      _ => return Err(self.unexpected()),
+    }
+
+    let mut text = String::new();
+
+    if self.peek()? == Some(b'-') {
+      text.push(self.bump()? as char);
+    }
+
+    match self.peek()? {
+      Some(b'0') => text.push(self.bump()? as char),
+      Some(b'1'..=b'9') => self.read_digits(&mut text)?,
+      _ => return Err(self.error(JsonErrorKind::InvalidNumber)),
+    }
+
+    if self.peek()? == Some(b'.') {
+      text.push(self.bump()? as char);
+
+      if !matches!(self.peek()?, Some(b'0'..=b'9')) {
+        return Err(self.error(JsonErrorKind::InvalidNumber));
+      }
+      self.read_digits(&mut text)?;
+    }
+
+    if let Some(b'e' | b'E') = self.peek()? {
+      text.push(self.bump()? as char);
+
+      if let Some(b'+' | b'-') = self.peek()? {
+        text.push(self.bump()? as char);
+      }
+
+      if !matches!(self.peek()?, Some(b'0'..=b'9')) {
+        return Err(self.error(JsonErrorKind::InvalidNumber));
+      }
+      self.read_digits(&mut text)?;
+    }
+
+    Ok((text, line, column))
+  }
+
+  fn read_digits(&mut self, text: &mut String) -> Result<(), JsonError> {
+    while let Some(b'0'..=b'9') = self.peek()? {
+      text.push(self.bump()? as char);
+    }
+    Ok(())
+  }
+
+  pub fn read_f64(&mut self) -> Result<f64, JsonError> {
+    let (text, line, column) = self.read_number_text()?;
+
+    text.parse().map_err(|_| JsonError {
+      kind: JsonErrorKind::InvalidNumber,
+      line,
+      column,
+    })
+  }
+
+  pub fn read_i128(&mut self) -> Result<i128, JsonError> {
+    let (text, line, column) = self.read_number_text()?;
+
+    let kind = if text.contains(['.', 'e', 'E']) {
+      JsonErrorKind::UnexpectedToken {
+        expected: "an integer",
+      }
+    } else {
+      match text.parse() {
+        Ok(value) => return Ok(value),
+        Err(_) => JsonErrorKind::NumberOutOfRange,
+      }
+    };
+
+    Err(JsonError { kind, line, column })
+  }
+
+  pub fn read_string(&mut self) -> Result<String, JsonError> {
+    self.skip_whitespace()?;
+
+    match self.peek()? {
+      Some(b'"') => {}
+      Some(b'[' | b'{' | b't' | b'f' | b'n' | b'-' | b'0'..=b'9') => {
+        return Err(self.error(JsonErrorKind::UnexpectedToken {
+          expected: "a string",
+        }))
+      }
+      _ => return Err(self.unexpected()),
+    }
+
+    let (line, column) = (self.line, self.column);
+    self.bump()?;
+
+    let mut bytes = Vec::new();
+
+    loop {
+      match self.peek()? {
+        None => return Err(self.error(JsonErrorKind::UnexpectedEof)),
+        Some(b'"') => {
+          self.bump()?;
+          break;
+        }
+        Some(b'\\') => {
+          self.bump()?;
+          self.read_escape(&mut bytes)?;
+        }
+        Some(0x00..=0x1f) => return Err(self.unexpected()),
+        Some(_) => bytes.push(self.bump()?),
+      }
+    }
+
+    String::from_utf8(bytes).map_err(|_| JsonError {
+      kind: JsonErrorKind::InvalidUtf8,
+      line,
+      column,
+    })
+  }
+
+  fn read_escape(&mut self, bytes: &mut Vec<u8>) -> Result<(), JsonError> {
+    let c = match self.peek()? {
+      Some(b'"') => '"',
+      Some(b'\\') => '\\',
+      Some(b'/') => '/',
+      Some(b'b') => '\u{08}',
+      Some(b'f') => '\u{0c}',
+      Some(b'n') => '\n',
+      Some(b'r') => '\r',
+      Some(b't') => '\t',
+      Some(b'u') => {
+        let (line, column) = (self.line, self.column);
+        self.bump()?;
+        let c = self.read_unicode_escape(line, column)?;
+        bytes.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+        return Ok(());
+      }
+      None => return Err(self.error(JsonErrorKind::UnexpectedEof)),
+      Some(_) => return Err(self.error(JsonErrorKind::InvalidEscape)),
+    };
+
+    self.bump()?;
+    bytes.push(c as u8);
+    Ok(())
+  }
+
+  /// Reads the hex digits after `\u`, pairing up surrogates.
+  fn read_unicode_escape(&mut self, line: usize, column: usize) -> Result<char, JsonError> {
+    let invalid = JsonError {
+      kind: JsonErrorKind::InvalidUnicode,
+      line,
+      column,
+    };
+
+    let high = self.read_hex4()?;
+
+    let code = match high {
+      0xd800..=0xdbff => {
+        self.expect_literal(b"\\u")?;
+        let low = self.read_hex4()?;
+
+        if !(0xdc00..=0xdfff).contains(&low) {
+          return Err(invalid);
+        }
+
+        0x10000 + ((high - 0xd800) << 10) + (low - 0xdc00)
+      }
+      0xdc00..=0xdfff => return Err(invalid),
+      code => code,
+    };
+
+    char::from_u32(code).ok_or(invalid)
+  }
+
+  fn read_hex4(&mut self) -> Result<u32, JsonError> {
+    let mut value = 0;
+
+    for _ in 0..4 {
+      let digit = match self.peek()? {
+        Some(byte) => (byte as char).to_digit(16),
+        None => return Err(self.error(JsonErrorKind::UnexpectedEof)),
+      };
+
+      match digit {
+        Some(digit) => {
+          self.bump()?;
+          value = value * 16 + digit;
+        }
+        None => return Err(self.error(JsonErrorKind::InvalidUnicode)),
+      }
+    }
+
+    Ok(value)
+  }
+
+  /// Consumes the opening bracket of an array and returns an iterator that
+  /// parses its elements one at a time.
+  pub fn array<T: FromJson>(&mut self) -> Result<Array<'_, R, T>, JsonError> {
+    self.skip_whitespace()?;
+
+    match self.peek()? {
+      Some(b'[') => {}
+      Some(b'"' | b'{' | b't' | b'f' | b'n' | b'-' | b'0'..=b'9') => {
+        return Err(self.error(JsonErrorKind::UnexpectedToken {
+          expected: "an array",
+        }))
+      }
+      _ => return Err(self.unexpected()),
+    }
+
+    self.bump()?;
+
+    Ok(Array {
+      reader: self,
+      state: ArrayState::First,
+      _p: PhantomData,
+    })
+  }
+}
+
+enum ArrayState {
+  First,
+  Rest,
+  Done,
+}
+
+pub struct Array<'a, R, T> {
+  reader: &'a mut JsonReader<R>,
+  state: ArrayState,
+  _p: PhantomData<T>,
+}
+
+impl<'a, R: Read, T: FromJson> Array<'a, R, T> {
+  fn next_element(&mut self) -> Result<Option<T>, JsonError> {
+    self.reader.skip_whitespace()?;
+
+    match self.state {
+      ArrayState::Done => return Ok(None),
+      ArrayState::First => {
+        if self.reader.peek()? == Some(b']') {
+          self.reader.bump()?;
+          return Ok(None);
+        }
+      }
+      ArrayState::Rest => match self.reader.peek()? {
+        Some(b']') => {
+          self.reader.bump()?;
+          return Ok(None);
+        }
+        Some(b',') => {
+          self.reader.bump()?;
+        }
+        _ => return Err(self.reader.unexpected()),
+      },
+    }
+
+    self.state = ArrayState::Rest;
+
+    T::read_json(self.reader).map(Some)
+  }
+}
+
+impl<'a, R: Read, T: FromJson> Iterator for Array<'a, R, T> {
+  type Item = Result<T, JsonError>;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    let result = self.next_element();
+
+    if !matches!(result, Ok(Some(_))) {
+      self.state = ArrayState::Done;
+    }
+
+    result.transpose()
+  }
+}
+
+impl ToJson for bool {
+  fn write_json<W: Write + ?Sized>(&self, writer: &mut W) -> io::Result<()> {
+    write!(writer, "{}", self)
+  }
+}
+
+impl FromJson for bool {
+  fn read_json<R: Read>(reader: &mut JsonReader<R>) -> Result<Self, JsonError> {
+    reader.read_bool()
+  }
+}
+
+macro_rules! impl_integer {
+  ($($t:ty),*) => {
+    $(
+      impl ToJson for $t {
+        fn write_json<W: Write + ?Sized>(&self, writer: &mut W) -> io::Result<()> {
+          write!(writer, "{}", self)
+        }
+      }
+
+      impl FromJson for $t {
+        fn read_json<R: Read>(reader: &mut JsonReader<R>) -> Result<Self, JsonError> {
+          reader.skip_whitespace()?;
+          let (line, column) = (reader.line, reader.column);
+          let value = reader.read_i128()?;
+
+          <$t>::try_from(value).map_err(|_| JsonError {
+            kind: JsonErrorKind::NumberOutOfRange,
+            line,
+            column,
+          })
+        }
+      }
+    )*
+  };
+}
+
+macro_rules! impl_float {
+  ($($t:ty),*) => {
+    $(
+      impl ToJson for $t {
+        fn write_json<W: Write + ?Sized>(&self, writer: &mut W) -> io::Result<()> {
+          if !self.is_finite() {
+            return Err(io::Error::new(
+              io::ErrorKind::InvalidInput,
+              "JSON can't represent NaN or infinity",
+            ));
+          }
+          write!(writer, "{}", self)
+        }
+      }
+
+      impl FromJson for $t {
+        fn read_json<R: Read>(reader: &mut JsonReader<R>) -> Result<Self, JsonError> {
+          reader.skip_whitespace()?;
+          let (line, column) = (reader.line, reader.column);
+          let value = reader.read_f64()?;
+
+          // `as` would quietly turn anything too big into infinity.
+          if (value as $t).is_infinite() {
+            return Err(JsonError {
+              kind: JsonErrorKind::NumberOutOfRange,
+              line,
+              column,
+            });
+          }
+          Ok(value as $t)
+        }
+      }
+    )*
+  };
+}
+
+impl_integer!(u8, u16, u32, u64, usize, i8, i16, i32, i64, isize);
+impl_float!(f32, f64);
+
+impl ToJson for str {
+  fn write_json<W: Write + ?Sized>(&self, writer: &mut W) -> io::Result<()> {
+    write_str(writer, self)
+  }
+}
+
+impl ToJson for String {
+  fn write_json<W: Write + ?Sized>(&self, writer: &mut W) -> io::Result<()> {
+    write_str(writer, self)
+  }
+}
+
+impl FromJson for String {
+  fn read_json<R: Read>(reader: &mut JsonReader<R>) -> Result<Self, JsonError> {
+    reader.read_string()
+  }
+}
+
+impl ToJson for char {
+  fn write_json<W: Write + ?Sized>(&self, writer: &mut W) -> io::Result<()> {
+    write_str(writer, self.encode_utf8(&mut [0; 4]))
+  }
+}
+
+impl FromJson for char {
+  fn read_json<R: Read>(reader: &mut JsonReader<R>) -> Result<Self, JsonError> {
+    reader.skip_whitespace()?;
+    let (line, column) = (reader.line, reader.column);
+    let s = reader.read_string()?;
+    let mut chars = s.chars();
+
+    match (chars.next(), chars.next()) {
+      (Some(c), None) => Ok(c),
+      _ => Err(JsonError {
+        kind: JsonErrorKind::UnexpectedToken {
+          expected: "a single character string",
+        },
+        line,
+        column,
+      }),
+    }
+  }
+}
+
+impl<T: ToJson> ToJson for Option<T> {
+  fn write_json<W: Write + ?Sized>(&self, writer: &mut W) -> io::Result<()> {
+    match self {
+      Some(value) => value.write_json(writer),
+      None => writer.write_all(b"null"),
+    }
+  }
+}
+
+impl<T: FromJson> FromJson for Option<T> {
+  fn read_json<R: Read>(reader: &mut JsonReader<R>) -> Result<Self, JsonError> {
+    if reader.read_null()? {
+      Ok(None)
+    } else {
+      T::read_json(reader).map(Some)
+    }
+  }
+}
+
+impl<T: ToJson + ?Sized> ToJson for &T {
+  fn write_json<W: Write + ?Sized>(&self, writer: &mut W) -> io::Result<()> {
+    (**self).write_json(writer)
+  }
+}
+
+impl<T: ToJson> ToJson for LinkedList<T> {
+  fn write_json<W: Write + ?Sized>(&self, writer: &mut W) -> io::Result<()> {
+    write_array(writer, self.iter())
+  }
+}
+
+impl<T: FromJson> FromJson for LinkedList<T> {
+  fn read_json<R: Read>(reader: &mut JsonReader<R>) -> Result<Self, JsonError> {
+    reader.array()?.collect()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::trickle::Trickle;
+
+  fn list_from<T: Clone>(v: &[T]) -> LinkedList<T> {
+    v.iter().cloned().collect()
+  }
+
+  #[test]
+  fn write_lists() {
+    assert_eq!("[]", to_string(&LinkedList::<i32>::new()).unwrap());
+    assert_eq!("[1,-2,3]", to_string(&list_from(&[1, -2, 3])).unwrap());
+    assert_eq!(
+      "[true,null,false]",
+      to_string(&list_from(&[Some(true), None, Some(false)])).unwrap()
+    );
+    assert_eq!("[0.5,1]", to_string(&list_from(&[0.5, 1.0])).unwrap());
+  }
+
+  #[test]
+  fn write_escapes() {
+    assert_eq!(
+      r#""a\"b\\c\nd\te\u0001ü""#,
+      to_string("a\"b\\c\nd\te\u{1}ü").unwrap()
+    );
+  }
+
+  #[test]
+  fn write_rejects_nan() {
+    assert!(to_string(&list_from(&[f64::NAN])).is_err());
+  }
+
+  #[test]
+  fn round_trip() {
+    let list = list_from(&[
+      "plain".to_string(),
+      "quote \" and \\".to_string(),
+      "control \u{7}\r\n".to_string(),
+      "emoji 🦀".to_string(),
+      String::new(),
+    ]);
+    assert_eq!(list, from_str(&to_string(&list).unwrap()).unwrap());
+
+    let nested: LinkedList<LinkedList<i64>> = (0..4).map(|i| (0..i).collect()).collect();
+    assert_eq!(nested, from_str(&to_string(&nested).unwrap()).unwrap());
+
+    let floats = list_from(&[0.1, -2.5e-10, 1e300, 0.0]);
+    assert_eq!(floats, from_str(&to_string(&floats).unwrap()).unwrap());
+  }
+
+  #[test]
+  fn read_with_whitespace_and_escapes() {
+    let input = " [ \"\\u00fc\\ud83e\\udd80\" ,\n\t\"\\/\\b\\f\" ] \n";
+    let list: LinkedList<String> = from_str(input).unwrap();
+
+    assert_eq!(
+      list_from(&["ü🦀".to_string(), "/\u{8}\u{c}".to_string()]),
+      list
+    );
+  }
+
+  #[test]
+  fn read_numbers() {
+    assert_eq!(list_from(&[0, -1, 255]), from_str("[0,-1,255]").unwrap());
+    assert_eq!(u64::MAX, from_str::<u64>("18446744073709551615").unwrap());
+    assert_eq!(1.5e3, from_str::<f64>("1.5E+3").unwrap());
+  }
+
+  #[test]
+  fn streams_elements() {
+    let input = b"[10, 20, 30]";
+    let mut reader = JsonReader::new(Trickle(input));
+    let mut array = reader.array::<u32>().unwrap();
+
+    assert_eq!(10, array.next().unwrap().unwrap());
+    assert_eq!(20, array.next().unwrap().unwrap());
+    assert_eq!(30, array.next().unwrap().unwrap());
+    assert!(array.next().is_none());
+    assert!(reader.finish().is_ok());
+  }
+
+  fn error_at<T: FromJson + fmt::Debug>(input: &str) -> (String, usize, usize) {
+    let err = from_str::<T>(input).unwrap_err();
+    (err.kind.to_string(), err.line, err.column)
+  }
+
+  #[test]
+  fn errors_report_position() {
+    assert_eq!(
+      ("unexpected character '}'".to_string(), 1, 5),
+      error_at::<LinkedList<i32>>("[1, }")
+    );
+    assert_eq!(
+      ("unexpected character ';'".to_string(), 3, 3),
+      error_at::<LinkedList<i32>>("[\n  1,\n 2;\n]")
+    );
+    assert_eq!(
+      ("unexpected end of input".to_string(), 1, 9),
+      error_at::<LinkedList<String>>(r#"["abc", "#)
+    );
+    assert_eq!(
+      ("expected a string".to_string(), 1, 7),
+      error_at::<LinkedList<String>>(r#"["ü", 1]"#)
+    );
+    assert_eq!(
+      ("number out of range".to_string(), 1, 2),
+      error_at::<LinkedList<u8>>("[256]")
+    );
+    assert_eq!(
+      ("number out of range".to_string(), 1, 5),
+      error_at::<LinkedList<u8>>("[1, 256]")
+    );
+    assert_eq!(
+      ("number out of range".to_string(), 2, 3),
+      error_at::<LinkedList<f32>>("[1.5,\n  1e39]")
+    );
+    assert_eq!(
+      ("expected an integer".to_string(), 1, 2),
+      error_at::<LinkedList<u8>>("[2.5]")
+    );
+    assert_eq!(
+      ("invalid number".to_string(), 1, 4),
+      error_at::<LinkedList<f64>>("[1.]")
+    );
+    assert_eq!(
+      ("invalid escape sequence".to_string(), 1, 4),
+      error_at::<String>(r#""a\x""#)
+    );
+    assert_eq!(
+      ("invalid unicode escape".to_string(), 1, 4),
+      error_at::<String>(r#""a\udc00""#)
+    );
+    assert_eq!(
+      ("trailing characters after value".to_string(), 1, 4),
+      error_at::<LinkedList<u8>>("[] []")
+    );
+    assert_eq!(
+      ("expected an array".to_string(), 1, 1),
+      error_at::<LinkedList<u8>>("{}")
+    );
+  }
+
+  #[test]
+  fn error_display() {
+    let err = from_str::<LinkedList<Option<bool>>>("[true,\n nul]").unwrap_err();
+    assert_eq!(
+      "unexpected character ']' at line 2 column 5",
+      err.to_string()
+    );
+  }
+}
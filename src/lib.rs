@@ -1,9 +1,35 @@
+pub mod codec;
+pub mod diff;
+pub mod disk_list;
+pub mod dot;
+#[cfg(test)]
+mod drop_count;
+mod drop_guard;
+pub mod ffi;
 pub mod fifth;
 pub mod first;
 pub mod fourth;
+pub mod free_list_alloc;
+pub mod fuzz;
+pub mod json;
+pub mod list_fmt;
+pub mod memory;
 pub mod miri;
+#[cfg(test)]
+mod model;
+pub mod pairing_heap;
+pub mod production_linked_list;
+mod rng;
+pub mod rope;
 pub mod second;
+pub mod self_organizing;
 pub mod seventh;
 pub mod sixth;
+pub mod skip_list;
 pub mod third;
-pub mod production_linked_list;
\ No newline at end of file
+pub mod trace;
+pub mod traits;
+#[cfg(test)]
+mod trickle;
+pub mod unrolled;
+pub mod xor_list;
@@ -0,0 +1,196 @@
+//! The textual form shared by the lists' `Display` and `FromStr` impls.
+//!
+//! `{}` prints `[1, 2, 3]` and `{:#}` prints the links between the nodes
+//! instead, `1 <-> 2 <-> 3`, for every list. Parsing accepts either form, and
+//! `1 -> 2 -> 3` as well. Elements are printed with their own `Display`, so
+//! only elements that don't contain the separators survive a round trip.
+
+use std::{error, fmt, str::FromStr};
+
+pub(crate) const SINGLY_LINKED: &str = " -> ";
+pub(crate) const DOUBLY_LINKED: &str = " <-> ";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseListError<E> {
+  /// The input was blank. Empty lists are written as `[]`.
+  Empty,
+  /// The input started with `[` but didn't end with `]`.
+  UnclosedBracket,
+  /// Nothing between two separators. `position` is a byte offset into the input.
+  EmptyElement { index: usize, position: usize },
+  /// The element itself failed to parse.
+  Element {
+    index: usize,
+    position: usize,
+    error: E,
+  },
+}
+
+impl<E: fmt::Display> fmt::Display for ParseListError<E> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      ParseListError::Empty => write!(f, "cannot parse a list from an empty string"),
+      ParseListError::UnclosedBracket => write!(f, "missing closing `]`"),
+      ParseListError::EmptyElement { index, position } => {
+        write!(f, "element {} at byte {} is empty", index, position)
+      }
+      ParseListError::Element {
+        index,
+        position,
+        error,
+      } => write!(f, "element {} at byte {}: {}", index, position, error),
+    }
+  }
+}
+
+impl<E: error::Error + 'static> error::Error for ParseListError<E> {
+  fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+    match self {
+      ParseListError::Element { error, .. } => Some(error),
+      _ => None,
+    }
+  }
+}
+
+/// Writes `iter` as `[a, b, c]`, or joined by `link` in the alternate form.
+pub(crate) fn write_list<I>(f: &mut fmt::Formatter<'_>, iter: I, link: &str) -> fmt::Result
+where
+  I: IntoIterator,
+  I::Item: fmt::Display,
+{
+  let mut iter = iter.into_iter().peekable();
+
+  // There are no links to show in an empty list.
+  if !f.alternate() || iter.peek().is_none() {
+    write!(f, "[")?;
+
+    for (i, item) in iter.enumerate() {
+      if i > 0 {
+        write!(f, ", ")?;
+      }
+      fmt::Display::fmt(&item, f)?;
+    }
+
+    return write!(f, "]");
+  }
+
+  for (i, item) in iter.enumerate() {
+    if i > 0 {
+      f.write_str(link)?;
+    }
+    fmt::Display::fmt(&item, f)?;
+  }
+
+  Ok(())
+}
+
+/// Parses either textual form, handing the elements to `push` front to back.
+pub(crate) fn parse_list<T, F>(s: &str, mut push: F) -> Result<(), ParseListError<T::Err>>
+where
+  T: FromStr,
+  F: FnMut(T),
+{
+  let trimmed = s.trim();
+
+  if trimmed.is_empty() {
+    return Err(ParseListError::Empty);
+  }
+
+  let pieces: Vec<&str> = if let Some(inner) = trimmed.strip_prefix('[') {
+    let inner = inner
+      .strip_suffix(']')
+      .ok_or(ParseListError::UnclosedBracket)?;
+
+    if inner.trim().is_empty() {
+      return Ok(());
+    }
+
+    inner.split(',').collect()
+  } else if trimmed.contains(DOUBLY_LINKED.trim()) {
+    trimmed.split(DOUBLY_LINKED.trim()).collect()
+  } else {
+    trimmed.split(SINGLY_LINKED.trim()).collect()
+  };
+
+  // Every piece is a subslice of `s`.
+  let position_of = |piece: &str| piece.as_ptr() as usize - s.as_ptr() as usize;
+
+  for (index, piece) in pieces.into_iter().enumerate() {
+    if piece.trim().is_empty() {
+      return Err(ParseListError::EmptyElement {
+        index,
+        position: position_of(piece),
+      });
+    }
+
+    let piece = piece.trim();
+    let position = position_of(piece);
+
+    let elem = piece.parse().map_err(|error| ParseListError::Element {
+      index,
+      position,
+      error,
+    })?;
+
+    push(elem);
+  }
+
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::num::ParseIntError;
+
+  fn parse(s: &str) -> Result<Vec<i32>, ParseListError<ParseIntError>> {
+    let mut v = Vec::new();
+    parse_list(s, |elem| v.push(elem))?;
+    Ok(v)
+  }
+
+  #[test]
+  fn parses_both_forms() {
+    assert_eq!(Ok(vec![]), parse("[]"));
+    assert_eq!(Ok(vec![]), parse(" [  ] "));
+    assert_eq!(Ok(vec![1, 2, 3]), parse("[1, 2, 3]"));
+    assert_eq!(Ok(vec![1, 2, 3]), parse("[1,2,3]"));
+    assert_eq!(Ok(vec![-1, -2]), parse("-1 <-> -2"));
+    assert_eq!(Ok(vec![-1, -2]), parse("-1 -> -2"));
+    assert_eq!(Ok(vec![7]), parse("7"));
+  }
+
+  #[test]
+  fn reports_errors() {
+    assert_eq!(Err(ParseListError::Empty), parse("  "));
+    assert_eq!(Err(ParseListError::UnclosedBracket), parse("[1, 2"));
+    assert_eq!(
+      Err(ParseListError::EmptyElement {
+        index: 1,
+        position: 3
+      }),
+      parse("[1, , 3]")
+    );
+    assert_eq!(
+      Err(ParseListError::EmptyElement {
+        index: 2,
+        position: 6
+      }),
+      parse("[1, 2,]")
+    );
+
+    match parse("[1, x2]") {
+      Err(ParseListError::Element {
+        index: 1,
+        position: 4,
+        ..
+      }) => {}
+      other => panic!("unexpected result {:?}", other),
+    }
+
+    assert_eq!(
+      "element 1 at byte 4: invalid digit found in string",
+      parse("[1, x2]").unwrap_err().to_string()
+    );
+  }
+}
@@ -0,0 +1,155 @@
+//! How much heap memory the lists' nodes take up.
+//!
+//! Only the nodes themselves are counted. Anything an element owns on its
+//! own (the buffer behind a `String`, say) is left out, since the list has
+//! no way of knowing about it.
+
+use std::alloc::Layout;
+
+pub trait MemoryUsage {
+  fn node_count(&self) -> usize;
+
+  /// Bytes allocated on the heap for the list's nodes.
+  fn heap_bytes(&self) -> usize;
+}
+
+/// Size of the allocation behind an `Rc<T>`: the strong and weak counts
+/// followed by the value itself.
+pub(crate) fn rc_allocation_size<T>() -> usize {
+  let (layout, _) = Layout::new::<[usize; 2]>()
+    .extend(Layout::new::<T>())
+    .unwrap();
+
+  layout.pad_to_align().size()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{
+    fifth, first, fourth, production_linked_list::LinkedList, second, seventh, sixth, third,
+  };
+  use std::mem::size_of;
+
+  #[test]
+  fn rc_allocation_size_includes_counts() {
+    let counts = 2 * size_of::<usize>();
+
+    assert_eq!(counts, rc_allocation_size::<()>());
+    assert_eq!(counts + size_of::<u64>(), rc_allocation_size::<u64>());
+    // Padded out to the alignment of the counts.
+    assert_eq!(counts + size_of::<usize>(), rc_allocation_size::<u8>());
+  }
+
+  #[test]
+  fn empty_lists_use_nothing() {
+    assert_eq!(0, first::List::new().heap_bytes());
+    assert_eq!(0, second::List::<u8>::new().heap_bytes());
+    assert_eq!(0, third::List::<u8>::new().heap_bytes());
+    assert_eq!(0, fourth::List::<u8>::new().heap_bytes());
+    assert_eq!(0, fifth::List::<u8>::new().heap_bytes());
+    assert_eq!(0, sixth::List::<u8>::new().heap_bytes());
+    assert_eq!(0, LinkedList::<u8>::new().heap_bytes());
+  }
+
+  #[test]
+  fn boxed_nodes() {
+    // The element plus the pointer to the next node.
+    let node = size_of::<u64>() + size_of::<usize>();
+
+    let mut list = second::List::new();
+    list.push(1u64);
+    list.push(2);
+    list.push(3);
+
+    assert_eq!(3, list.node_count());
+    assert_eq!(3 * node, list.heap_bytes());
+
+    let mut list = first::List::new();
+    list.push(1);
+    list.push(2);
+    assert_eq!(2, list.node_count());
+    // The `i32` is padded out to the pointer.
+    assert_eq!(2 * 2 * size_of::<usize>(), list.heap_bytes());
+
+    let mut list = fifth::List::new();
+    list.push(1u64);
+    list.push(2);
+    assert_eq!(2, list.node_count());
+    assert_eq!(2 * node, list.heap_bytes());
+
+    let mut list = sixth::List::new();
+    list.push(1u64);
+    assert_eq!(1, list.node_count());
+    assert_eq!(node, list.heap_bytes());
+  }
+
+  #[test]
+  fn production_linked_list() {
+    let list: LinkedList<u64> = (0..10).collect();
+
+    assert_eq!(10, list.node_count());
+    // The element plus the two links.
+    assert_eq!(
+      10 * (size_of::<u64>() + 2 * size_of::<usize>()),
+      list.heap_bytes()
+    );
+  }
+
+  #[test]
+  fn fourth_counts_the_refcell() {
+    let mut list = fourth::List::new();
+    list.push_back(1u64);
+    list.push_back(2);
+
+    assert_eq!(2, list.node_count());
+    let counts = 2 * size_of::<usize>();
+    let borrow_flag = size_of::<isize>();
+    let links = 2 * size_of::<usize>();
+    assert_eq!(
+      2 * (counts + borrow_flag + size_of::<u64>() + links),
+      list.heap_bytes()
+    );
+  }
+
+  #[test]
+  fn third_tells_unique_from_shared() {
+    let tail = third::List::new().cons(3u64).cons(2).cons(1);
+    // The counts, the element and the link.
+    let node = 2 * size_of::<usize>() + size_of::<u64>() + size_of::<usize>();
+
+    assert_eq!(3, tail.node_count());
+    assert_eq!(3 * node, tail.unique_bytes());
+    assert_eq!(0, tail.shared_bytes());
+
+    let a = tail.cons(10).cons(20);
+
+    assert_eq!(5, a.node_count());
+    assert_eq!(5 * node, a.heap_bytes());
+    assert_eq!(2 * node, a.unique_bytes());
+    assert_eq!(3 * node, a.shared_bytes());
+
+    // `tail` itself is now entirely shared with `a`.
+    assert_eq!(0, tail.unique_bytes());
+    assert_eq!(3 * node, tail.shared_bytes());
+
+    drop(a);
+    assert_eq!(3 * node, tail.unique_bytes());
+
+    // Sharing only the middle of a list makes everything after it shared.
+    let middle = tail.tail();
+    assert_eq!(node, tail.unique_bytes());
+    assert_eq!(2 * node, tail.shared_bytes());
+    drop(middle);
+  }
+
+  #[test]
+  fn seventh_lives_on_the_stack() {
+    seventh::List::push(None, 1, |list| {
+      seventh::List::push(Some(list), 2, |list| {
+        assert_eq!(2, list.node_count());
+        assert_eq!(0, list.heap_bytes());
+      })
+    })
+  }
+}
@@ -0,0 +1,438 @@
+//! Model-based tests: random sequences of operations are run against a list
+//! and a `VecDeque` at the same time, checking they agree after every step.
+//!
+//! A failing sequence is shrunk before it's reported, by dropping operations
+//! and simplifying the ones left until nothing more can go while it still
+//! fails. Set `MODEL_SEED` to rerun a single case, and `MODEL_CASES` to run
+//! more of them:
+//!
+//! ```terminal
+//! MODEL_CASES=100000 cargo test --release model
+//! ```
+
+use std::{
+  collections::VecDeque,
+  env,
+  fmt::{self, Write},
+  panic::{self, AssertUnwindSafe},
+};
+
+use crate::{fifth, fourth, memory::MemoryUsage, rng::Rng, sixth, trace};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+  PushFront,
+  PushBack,
+  PopFront,
+  PopBack,
+  CursorNext,
+  CursorPrev,
+  SplitBefore,
+  SpliceBefore,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Op {
+  PushFront(u8),
+  PushBack(u8),
+  PopFront,
+  PopBack,
+  CursorNext,
+  CursorPrev,
+  SplitBefore,
+  SpliceBefore(Vec<u8>),
+}
+
+impl Op {
+  fn generate(kinds: &[Kind], rng: &mut Rng) -> Op {
+    // Small values make shrunk cases easier to read.
+    let kind = kinds[rng.below(kinds.len())];
+    let value = rng.below(100) as u8;
+
+    match kind {
+      Kind::PushFront => Op::PushFront(value),
+      Kind::PushBack => Op::PushBack(value),
+      Kind::PopFront => Op::PopFront,
+      Kind::PopBack => Op::PopBack,
+      Kind::CursorNext => Op::CursorNext,
+      Kind::CursorPrev => Op::CursorPrev,
+      Kind::SplitBefore => Op::SplitBefore,
+      Kind::SpliceBefore => {
+        let len = rng.below(4);
+        Op::SpliceBefore((0..len).map(|_| rng.below(100) as u8).collect())
+      }
+    }
+  }
+
+  /// Simpler versions of this operation, for shrinking.
+  fn simplify(&self) -> Vec<Op> {
+    match self {
+      Op::PushFront(v) if *v != 0 => vec![Op::PushFront(0)],
+      Op::PushBack(v) if *v != 0 => vec![Op::PushBack(0)],
+      Op::SpliceBefore(elems) => {
+        let mut simpler: Vec<Op> = (0..elems.len())
+          .map(|i| {
+            let mut elems = elems.clone();
+            elems.remove(i);
+            Op::SpliceBefore(elems)
+          })
+          .collect();
+
+        if elems.iter().any(|&v| v != 0) {
+          simpler.push(Op::SpliceBefore(vec![0; elems.len()]));
+        }
+
+        simpler
+      }
+      _ => vec![],
+    }
+  }
+}
+
+/// Runs a sequence against one list, failing with a description of the
+/// first step where it and the model disagreed.
+type Check = fn(&[Op]) -> Result<(), String>;
+
+fn cases() -> u64 {
+  env::var("MODEL_CASES")
+    .ok()
+    .and_then(|cases| cases.parse().ok())
+    .unwrap_or(500)
+}
+
+fn generate(kinds: &[Kind], seed: u64) -> Vec<Op> {
+  let mut rng = Rng(seed);
+  let len = rng.below(64);
+  (0..len).map(|_| Op::generate(kinds, &mut rng)).collect()
+}
+
+/// Runs `check`, turning a panic into a failure like any other.
+fn run(check: Check, ops: &[Op]) -> Result<(), String> {
+  panic::catch_unwind(AssertUnwindSafe(|| check(ops))).unwrap_or_else(|payload| {
+    let message = payload
+      .downcast_ref::<&str>()
+      .map(|message| message.to_string())
+      .or_else(|| payload.downcast_ref::<String>().cloned())
+      .unwrap_or_else(|| "unknown panic".to_string());
+    Err(format!("panicked: {}", message))
+  })
+}
+
+/// Shrinks a failing sequence: first by dropping runs of operations, long
+/// ones first, then by simplifying single operations, repeating until
+/// neither makes progress.
+fn shrink(check: Check, mut ops: Vec<Op>, mut error: String) -> (Vec<Op>, String) {
+  loop {
+    let mut progress = false;
+
+    let mut chunk = ops.len().max(1);
+    while chunk > 0 {
+      let mut start = 0;
+
+      while start < ops.len() {
+        let mut candidate = ops.clone();
+        candidate.drain(start..(start + chunk).min(ops.len()));
+
+        match run(check, &candidate) {
+          Err(e) => {
+            ops = candidate;
+            error = e;
+            progress = true;
+          }
+          Ok(()) => start += chunk,
+        }
+      }
+
+      chunk /= 2;
+    }
+
+    for i in 0..ops.len() {
+      for simpler in ops[i].simplify() {
+        let mut candidate = ops.clone();
+        candidate[i] = simpler;
+
+        if let Err(e) = run(check, &candidate) {
+          ops = candidate;
+          error = e;
+          progress = true;
+          break;
+        }
+      }
+    }
+
+    if !progress {
+      return (ops, error);
+    }
+  }
+}
+
+/// Checks `cases()` random sequences made of `kinds`, panicking with the
+/// shrunk reproduction of the first one that fails.
+fn check_model(name: &str, kinds: &[Kind], check: Check) {
+  let seeds: Vec<u64> = match env::var("MODEL_SEED") {
+    Ok(seed) => vec![seed.parse().expect("MODEL_SEED should be a number")],
+    Err(_) => (0..cases()).collect(),
+  };
+
+  for seed in seeds {
+    let ops = generate(kinds, seed);
+
+    if let Err(error) = run(check, &ops) {
+      let (ops, error) = shrink(check, ops, error);
+      panic!(
+        "{} disagrees with the model (seed {}), shrunk to:\n{}{}",
+        name,
+        seed,
+        Script(&ops),
+        error
+      );
+    }
+  }
+}
+
+/// Prints operations one per line, for failure messages.
+struct Script<'a>(&'a [Op]);
+
+impl fmt::Display for Script<'_> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    for (i, op) in self.0.iter().enumerate() {
+      writeln!(f, "  {}: {:?}", i, op)?;
+    }
+    Ok(())
+  }
+}
+
+fn step_error(i: usize, op: &Op, expected: impl fmt::Debug, actual: impl fmt::Debug) -> String {
+  format!(
+    "step {} ({:?}): expected {:?}, got {:?}",
+    i, op, expected, actual
+  )
+}
+
+/// The production list goes through `trace::replay`, which already keeps
+/// a model of the list and its cursor and compares them after each line.
+fn check_production(ops: &[Op]) -> Result<(), String> {
+  let mut script = String::new();
+  let mut in_cursor = false;
+
+  for op in ops {
+    let cursor_op = matches!(
+      op,
+      Op::CursorNext | Op::CursorPrev | Op::SplitBefore | Op::SpliceBefore(_)
+    );
+
+    if cursor_op && !in_cursor {
+      script.push_str("cursor\n");
+    }
+    in_cursor = cursor_op;
+
+    match op {
+      Op::PushFront(v) => writeln!(script, "push_front {}", v),
+      Op::PushBack(v) => writeln!(script, "push_back {}", v),
+      Op::PopFront => writeln!(script, "pop_front"),
+      Op::PopBack => writeln!(script, "pop_back"),
+      Op::CursorNext => writeln!(script, "move_next"),
+      Op::CursorPrev => writeln!(script, "move_prev"),
+      Op::SplitBefore => writeln!(script, "split_before"),
+      Op::SpliceBefore(elems) => writeln!(script, "splice_before {:?}", elems),
+    }
+    .unwrap();
+  }
+
+  trace::replay::<u8>(&script)
+    .map(drop)
+    .map_err(|err| format!("{}\n{}", err, script))
+}
+
+fn check_fourth(ops: &[Op]) -> Result<(), String> {
+  let mut list = fourth::List::new();
+  let mut model = VecDeque::new();
+
+  for (i, op) in ops.iter().enumerate() {
+    let (expected, actual) = match *op {
+      Op::PushFront(v) => {
+        model.push_front(v);
+        list.push_front(v);
+        (None, None)
+      }
+      Op::PushBack(v) => {
+        model.push_back(v);
+        list.push_back(v);
+        (None, None)
+      }
+      Op::PopFront => (model.pop_front(), list.pop_front()),
+      Op::PopBack => (model.pop_back(), list.pop_back()),
+      _ => unreachable!(),
+    };
+
+    if expected != actual {
+      return Err(step_error(i, op, expected, actual));
+    }
+
+    let ends = (model.front(), model.back());
+    let list_ends = (list.peek_front().map(|v| *v), list.peek_back().map(|v| *v));
+    if (ends.0.copied(), ends.1.copied()) != list_ends {
+      return Err(step_error(i, op, ends, list_ends));
+    }
+
+    let contents = format!("{:?}", model);
+    if contents != list.to_string() {
+      return Err(step_error(i, op, contents, list.to_string()));
+    }
+  }
+
+  Ok(())
+}
+
+fn check_fifth(ops: &[Op]) -> Result<(), String> {
+  let mut list = fifth::List::new();
+  let mut model = VecDeque::new();
+
+  for (i, op) in ops.iter().enumerate() {
+    let (expected, actual) = match *op {
+      Op::PushBack(v) => {
+        model.push_back(v);
+        list.push(v);
+        (None, None)
+      }
+      Op::PopFront => (model.pop_front(), list.pop()),
+      _ => unreachable!(),
+    };
+
+    if expected != actual {
+      return Err(step_error(i, op, expected, actual));
+    }
+
+    // There's nothing else to look at the list with.
+    if model.len() != list.node_count() {
+      return Err(step_error(i, op, model.len(), list.node_count()));
+    }
+  }
+
+  let rest: Vec<u8> = std::iter::from_fn(|| list.pop()).collect();
+  if model != rest {
+    return Err(step_error(ops.len(), &Op::PopFront, model, rest));
+  }
+
+  Ok(())
+}
+
+fn check_sixth(ops: &[Op]) -> Result<(), String> {
+  let mut list = sixth::List::new();
+  let mut model = VecDeque::new();
+
+  for (i, op) in ops.iter().enumerate() {
+    let (expected, actual) = match *op {
+      Op::PushBack(v) => {
+        model.push_back(v);
+        list.push(v);
+        (None, None)
+      }
+      Op::PopFront => (model.pop_front(), list.pop()),
+      _ => unreachable!(),
+    };
+
+    if expected != actual {
+      return Err(step_error(i, op, expected, actual));
+    }
+
+    if model.front() != list.peek() {
+      return Err(step_error(i, op, model.front(), list.peek()));
+    }
+
+    let contents: Vec<&u8> = list.iter().collect();
+    if !model.iter().eq(contents.iter().copied()) || model.len() != list.node_count() {
+      return Err(step_error(i, op, &model, contents));
+    }
+  }
+
+  Ok(())
+}
+
+const DEQUE: &[Kind] = &[
+  Kind::PushFront,
+  Kind::PushBack,
+  Kind::PopFront,
+  Kind::PopBack,
+];
+const QUEUE: &[Kind] = &[Kind::PushBack, Kind::PopFront];
+
+#[test]
+fn production_linked_list() {
+  check_model(
+    "production_linked_list",
+    &[
+      Kind::PushFront,
+      Kind::PushBack,
+      Kind::PopFront,
+      Kind::PopBack,
+      Kind::CursorNext,
+      Kind::CursorNext,
+      Kind::CursorPrev,
+      Kind::SplitBefore,
+      Kind::SpliceBefore,
+    ],
+    check_production,
+  );
+}
+
+#[test]
+fn fourth() {
+  check_model("fourth", DEQUE, check_fourth);
+}
+
+#[test]
+fn fifth() {
+  check_model("fifth", QUEUE, check_fifth);
+}
+
+#[test]
+fn sixth() {
+  check_model("sixth", QUEUE, check_sixth);
+}
+
+/// A queue that loses every third push, to check the shrinker gets down
+/// to the shortest sequence that shows it.
+fn check_lossy(ops: &[Op]) -> Result<(), String> {
+  let mut list = Vec::new();
+  let mut model = VecDeque::new();
+  let mut pushes = 0;
+
+  for (i, op) in ops.iter().enumerate() {
+    match *op {
+      Op::PushBack(v) => {
+        pushes += 1;
+        model.push_back(v);
+        if pushes % 3 != 0 {
+          list.push(v);
+        }
+      }
+      Op::PopFront => {
+        model.pop_front();
+        if !list.is_empty() {
+          list.remove(0);
+        }
+      }
+      _ => unreachable!(),
+    }
+
+    if model.len() != list.len() {
+      return Err(step_error(i, op, model.len(), list.len()));
+    }
+  }
+
+  Ok(())
+}
+
+#[test]
+fn shrinks_to_a_minimal_sequence() {
+  let ops = generate(QUEUE, 3)
+    .into_iter()
+    .chain([Op::PushBack(5), Op::PushBack(6), Op::PushBack(7)])
+    .collect::<Vec<_>>();
+  let error = run(check_lossy, &ops).unwrap_err();
+
+  let (ops, _) = shrink(check_lossy, ops, error);
+
+  assert_eq!(vec![Op::PushBack(0), Op::PushBack(0), Op::PushBack(0)], ops);
+}
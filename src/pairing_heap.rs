@@ -0,0 +1,758 @@
+//! A pairing heap: a min-heap that merges in O(1) and can lower a key in
+//! place, which `BinaryHeap` can't.
+//!
+//! Every node keeps its children as an intrusive list threaded through the
+//! children's `next` and `prev` links, where the first child's `prev` is
+//! its parent. Pushing and merging just make one root the other's first
+//! child. Popping merges the root's children in two passes, pairs left to
+//! right then those right to left, which is what keeps it quick.
+//!
+//! `push` hands back a [`Handle`] to the element, for
+//! [`decrease_key`](PairingHeap::decrease_key). Handles notice when their
+//! element is popped, and which heap it ended up in after merges, so a stale
+//! one is an error rather than a dangling pointer.
+//!
+//! ```
+//! use learn_rust_with_entirely_too_many_linked_lists::pairing_heap::PairingHeap;
+//!
+//! let mut heap = PairingHeap::new();
+//! heap.push(5, "later");
+//! let urgent = heap.push(8, "urgent");
+//! heap.push(3, "soon");
+//!
+//! heap.decrease_key(&urgent, 1).unwrap();
+//!
+//! assert_eq!(Some((1, "urgent")), heap.pop());
+//! assert_eq!(Some((3, "soon")), heap.pop());
+//! assert!(heap.decrease_key(&urgent, 0).is_err());
+//! ```
+
+use std::{
+  cell::RefCell,
+  error,
+  fmt::{self, Debug},
+  marker::PhantomData,
+  mem,
+  ptr::NonNull,
+  rc::{Rc, Weak},
+};
+
+use crate::drop_guard;
+
+pub struct PairingHeap<K, V> {
+  root: Link<K, V>,
+  len: usize,
+  owner: Rc<Owner>,
+  _p: PhantomData<Box<Node<K, V>>>,
+}
+
+type Link<K, V> = Option<NonNull<Node<K, V>>>;
+
+struct Node<K, V> {
+  key: K,
+  value: V,
+  child: Link<K, V>,
+  next: Link<K, V>,
+  /// The sibling before, or the parent for a first child.
+  prev: Link<K, V>,
+  /// Only the node holds this, so handles can tell when it's gone.
+  _token: Rc<Token>,
+}
+
+struct Token {
+  /// The heap the node was pushed onto.
+  owner: Rc<Owner>,
+}
+
+/// Which heap a node is in. Merging a heap into another points its owner at
+/// the other's, so a node's heap is at the end of the chain from the owner
+/// it was pushed with. Chains get shortened whenever they're followed.
+#[derive(Default)]
+struct Owner {
+  merged_into: RefCell<Option<Rc<Owner>>>,
+}
+
+fn find_owner(owner: &Rc<Owner>) -> Rc<Owner> {
+  let mut root = owner.clone();
+  loop {
+    let next = root.merged_into.borrow().clone();
+    match next {
+      Some(next) => root = next,
+      None => break,
+    }
+  }
+
+  let mut current = owner.clone();
+  while !Rc::ptr_eq(&current, &root) {
+    let next = current.merged_into.replace(Some(root.clone())).unwrap();
+    current = next;
+  }
+
+  root
+}
+
+/// Points at one element of a heap, for as long as it's in one.
+pub struct Handle<K, V> {
+  node: NonNull<Node<K, V>>,
+  token: Weak<Token>,
+}
+
+impl<K, V> Clone for Handle<K, V> {
+  fn clone(&self) -> Self {
+    Self {
+      node: self.node,
+      token: self.token.clone(),
+    }
+  }
+}
+
+impl<K, V> Debug for Handle<K, V> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_tuple("Handle").field(&self.node).finish()
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecreaseKeyError {
+  /// The element has been popped, or its heap dropped.
+  Removed,
+  /// The element is in a different heap.
+  OtherHeap,
+  /// The new key is bigger than the old one.
+  KeyIncreased,
+}
+
+impl fmt::Display for DecreaseKeyError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      DecreaseKeyError::Removed => write!(f, "element is no longer in a heap"),
+      DecreaseKeyError::OtherHeap => write!(f, "element is in a different heap"),
+      DecreaseKeyError::KeyIncreased => write!(f, "new key is bigger than the old one"),
+    }
+  }
+}
+
+impl error::Error for DecreaseKeyError {}
+
+impl<K: Ord, V> PairingHeap<K, V> {
+  pub fn new() -> Self {
+    Self {
+      root: None,
+      len: 0,
+      owner: Rc::default(),
+      _p: PhantomData,
+    }
+  }
+
+  pub fn len(&self) -> usize {
+    self.len
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.len == 0
+  }
+
+  pub fn clear(&mut self) {
+    drop(mem::take(self));
+  }
+
+  pub fn push(&mut self, key: K, value: V) -> Handle<K, V> {
+    let token = Rc::new(Token {
+      owner: self.owner.clone(),
+    });
+    let handle_token = Rc::downgrade(&token);
+
+    unsafe {
+      let node = NonNull::new_unchecked(Box::into_raw(Box::new(Node {
+        key,
+        value,
+        child: None,
+        next: None,
+        prev: None,
+        _token: token,
+      })));
+
+      self.root = meld(self.root, Some(node));
+      self.len += 1;
+
+      Handle {
+        node,
+        token: handle_token,
+      }
+    }
+  }
+
+  /// The smallest key and its value.
+  pub fn peek(&self) -> Option<(&K, &V)> {
+    self
+      .root
+      .map(|root| unsafe { (&(*root.as_ptr()).key, &(*root.as_ptr()).value) })
+  }
+
+  pub fn pop(&mut self) -> Option<(K, V)> {
+    // Take everything out of the heap before comparing anything, so if a
+    // comparison in `combine` panics the heap is left empty rather than
+    // pointing at freed nodes.
+    let root = self.root.take()?;
+    let len = mem::take(&mut self.len);
+
+    unsafe {
+      let child = (*root.as_ptr()).child.take();
+      let root = Box::from_raw(root.as_ptr());
+      self.root = combine(child);
+      self.len = len - 1;
+
+      Some((root.key, root.value))
+    }
+  }
+
+  /// Moves everything in `other` into `self` without touching any nodes.
+  /// Handles into `other` work on `self` from now on.
+  pub fn merge(&mut self, mut other: Self) {
+    self.root = meld(self.root, other.root.take());
+    self.len += mem::take(&mut other.len);
+    other.merged_into(&self.owner);
+  }
+
+  /// Lowers the key of the element behind `handle` to `key`, which can be
+  /// equal to the old one but not bigger.
+  pub fn decrease_key(&mut self, handle: &Handle<K, V>, key: K) -> Result<(), DecreaseKeyError> {
+    let node = self.node(handle)?;
+
+    unsafe {
+      if key > (*node.as_ptr()).key {
+        return Err(DecreaseKeyError::KeyIncreased);
+      }
+
+      let root = self.root.unwrap();
+      if root == node {
+        (*node.as_ptr()).key = key;
+        return Ok(());
+      }
+
+      // Both comparisons happen before anything moves, so one that panics
+      // leaves the heap as it was.
+      let becomes_root = key < (*root.as_ptr()).key;
+      (*node.as_ptr()).key = key;
+
+      // Take it out of its parent's children, with its own children still
+      // under it, and put that back in at the top.
+      let prev = (*node.as_ptr()).prev.unwrap();
+      let next = (*node.as_ptr()).next;
+      if (*prev.as_ptr()).child == Some(node) {
+        (*prev.as_ptr()).child = next;
+      } else {
+        (*prev.as_ptr()).next = next;
+      }
+      if let Some(next) = next {
+        (*next.as_ptr()).prev = Some(prev);
+      }
+
+      self.root = Some(if becomes_root {
+        adopt(node, root)
+      } else {
+        adopt(root, node)
+      });
+    }
+
+    Ok(())
+  }
+
+  /// The key and value behind `handle`, if it's still in this heap.
+  pub fn get(&self, handle: &Handle<K, V>) -> Option<(&K, &V)> {
+    let node = self.node(handle).ok()?;
+    unsafe { Some((&(*node.as_ptr()).key, &(*node.as_ptr()).value)) }
+  }
+
+  pub fn contains(&self, handle: &Handle<K, V>) -> bool {
+    self.node(handle).is_ok()
+  }
+
+  fn node(&self, handle: &Handle<K, V>) -> Result<NonNull<Node<K, V>>, DecreaseKeyError> {
+    let token = handle.token.upgrade().ok_or(DecreaseKeyError::Removed)?;
+
+    if !Rc::ptr_eq(&find_owner(&token.owner), &self.owner) {
+      return Err(DecreaseKeyError::OtherHeap);
+    }
+
+    Ok(handle.node)
+  }
+
+  #[cfg(test)]
+  fn check_invariants(&self) {
+    unsafe fn check<K: Ord, V>(node: NonNull<Node<K, V>>, prev: Link<K, V>) -> usize {
+      let node = &*node.as_ptr();
+      assert!(node.prev == prev, "bad prev link");
+
+      let mut count = 1;
+      let mut prev = None;
+      let mut child = node.child;
+      while let Some(current) = child {
+        assert!((*current.as_ptr()).key >= node.key, "child is smaller");
+        let parent_or_sibling = prev.or(Some(NonNull::from(node)));
+        count += check(current, parent_or_sibling);
+        prev = Some(current);
+        child = (*current.as_ptr()).next;
+      }
+      count
+    }
+
+    let count = match self.root {
+      Some(root) => unsafe {
+        assert!((*root.as_ptr()).next.is_none(), "root has siblings");
+        check(root, None)
+      },
+      None => 0,
+    };
+    assert_eq!(self.len, count);
+  }
+}
+
+impl<K, V> PairingHeap<K, V> {
+  /// Marks this (now empty) heap's handles as belonging to `owner`.
+  fn merged_into(&mut self, owner: &Rc<Owner>) {
+    *self.owner.merged_into.borrow_mut() = Some(owner.clone());
+  }
+}
+
+/// Makes whichever root has the bigger key the first child of the other.
+/// Any siblings or parent they had are forgotten.
+fn meld<K: Ord, V>(a: Link<K, V>, b: Link<K, V>) -> Link<K, V> {
+  let (a, b) = match (a, b) {
+    (None, b) => return b,
+    (a, None) => return a,
+    (Some(a), Some(b)) => (a, b),
+  };
+
+  unsafe {
+    Some(if (*b.as_ptr()).key < (*a.as_ptr()).key {
+      adopt(b, a)
+    } else {
+      adopt(a, b)
+    })
+  }
+}
+
+/// Makes `child` the first child of `parent` and returns `parent`, like
+/// [`meld`] without the comparing.
+fn adopt<K, V>(parent: NonNull<Node<K, V>>, child: NonNull<Node<K, V>>) -> NonNull<Node<K, V>> {
+  unsafe {
+    let first = (*parent.as_ptr()).child;
+    (*child.as_ptr()).next = first;
+    (*child.as_ptr()).prev = Some(parent);
+    if let Some(first) = first {
+      (*first.as_ptr()).prev = Some(child);
+    }
+    (*parent.as_ptr()).child = Some(child);
+    (*parent.as_ptr()).next = None;
+    (*parent.as_ptr()).prev = None;
+
+    parent
+  }
+}
+
+/// Melds a list of siblings into one tree: pairs left to right, then the
+/// pairs right to left. The pairs are stacked up through their `next` links
+/// rather than in a `Vec`.
+///
+/// `meld` compares before it relinks anything, so if a comparison panics
+/// every node is still on one of the chains, and they all get freed.
+fn combine<K: Ord, V>(first: Link<K, V>) -> Link<K, V> {
+  struct Chains<K, V> {
+    current: Link<K, V>,
+    pairs: Link<K, V>,
+    root: Link<K, V>,
+  }
+
+  impl<K, V> Drop for Chains<K, V> {
+    fn drop(&mut self) {
+      free_all(
+        [self.current, self.pairs, self.root]
+          .into_iter()
+          .flatten()
+          .collect(),
+      );
+    }
+  }
+
+  let mut chains = Chains {
+    current: first,
+    pairs: None,
+    root: None,
+  };
+
+  unsafe {
+    while let Some(a) = chains.current {
+      let b = (*a.as_ptr()).next;
+      let rest = b.and_then(|b| (*b.as_ptr()).next);
+
+      let pair = meld(Some(a), b).unwrap();
+      (*pair.as_ptr()).next = chains.pairs;
+      (*pair.as_ptr()).prev = None;
+      chains.pairs = Some(pair);
+      chains.current = rest;
+    }
+
+    while let Some(pair) = chains.pairs {
+      let rest = (*pair.as_ptr()).next;
+
+      let root = meld(chains.root, Some(pair)).unwrap();
+      (*root.as_ptr()).next = None;
+      chains.root = Some(root);
+      chains.pairs = rest;
+    }
+
+    chains.root.take()
+  }
+}
+
+/// Frees the trees rooted at `nodes`, along with all their siblings.
+fn free_all<K, V>(mut nodes: Vec<NonNull<Node<K, V>>>) {
+  drop_guard::drop_all_with_guard(&mut nodes, |nodes| {
+    let node = unsafe { Box::from_raw(nodes.pop()?.as_ptr()) };
+    nodes.extend(node.child);
+    nodes.extend(node.next);
+    Some(node)
+  });
+}
+
+impl<K, V> Drop for PairingHeap<K, V> {
+  fn drop(&mut self) {
+    free_all(self.root.take().into_iter().collect());
+  }
+}
+
+impl<K: Ord, V> Default for PairingHeap<K, V> {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl<K: Ord, V> Extend<(K, V)> for PairingHeap<K, V> {
+  fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
+    for (key, value) in iter {
+      self.push(key, value);
+    }
+  }
+}
+
+impl<K: Ord, V> FromIterator<(K, V)> for PairingHeap<K, V> {
+  fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+    let mut heap = Self::new();
+    heap.extend(iter);
+    heap
+  }
+}
+
+impl<K: Ord + Debug, V: Debug> Debug for PairingHeap<K, V> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_struct("PairingHeap")
+      .field("len", &self.len)
+      .field("min", &self.peek())
+      .finish()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::drop_count::DropCounter;
+  use std::{
+    cell::Cell,
+    cmp::Ordering,
+    panic::{self, AssertUnwindSafe},
+  };
+
+  /// A fixed but scrambled sequence of keys, with repeats.
+  fn keys(n: u64) -> Vec<u64> {
+    (0..n).map(|i| (i * 7919 + 13) % (n / 2 + 1)).collect()
+  }
+
+  fn drain<K: Ord, V>(heap: &mut PairingHeap<K, V>) -> Vec<K> {
+    let mut keys = Vec::new();
+    while let Some((key, _)) = heap.pop() {
+      heap.check_invariants();
+      keys.push(key);
+    }
+    keys
+  }
+
+  #[test]
+  fn basics() {
+    let mut heap = PairingHeap::new();
+    assert_eq!(None, heap.peek());
+    assert_eq!(None, heap.pop());
+
+    heap.push(3, 'c');
+    heap.push(1, 'a');
+    heap.push(2, 'b');
+    heap.check_invariants();
+
+    assert_eq!(3, heap.len());
+    assert_eq!(Some((&1, &'a')), heap.peek());
+    assert_eq!(Some((1, 'a')), heap.pop());
+    assert_eq!(Some((2, 'b')), heap.pop());
+    assert_eq!(Some((3, 'c')), heap.pop());
+    assert_eq!(None, heap.pop());
+    assert!(heap.is_empty());
+  }
+
+  #[test]
+  fn pops_in_order() {
+    let keys = keys(500);
+    let mut heap: PairingHeap<u64, ()> = keys.iter().map(|&key| (key, ())).collect();
+    heap.check_invariants();
+
+    let mut sorted = keys.clone();
+    sorted.sort();
+    assert_eq!(sorted, drain(&mut heap));
+  }
+
+  #[test]
+  fn merge() {
+    let mut a: PairingHeap<u64, ()> = [(5, ()), (1, ()), (9, ())].into_iter().collect();
+    let mut b = PairingHeap::new();
+    let handle = b.push(7, ());
+    b.push(0, ());
+
+    a.merge(b);
+    a.check_invariants();
+    assert_eq!(5, a.len());
+
+    // The handle followed its element into `a`.
+    assert!(a.contains(&handle));
+    a.decrease_key(&handle, 2).unwrap();
+    assert_eq!(vec![0, 1, 2, 5, 9], drain(&mut a));
+
+    a.merge(PairingHeap::new());
+    PairingHeap::new().merge(a);
+  }
+
+  #[test]
+  fn decrease_key() {
+    let mut heap = PairingHeap::new();
+    let handles: Vec<_> = keys(200)
+      .into_iter()
+      .enumerate()
+      .map(|(i, key)| heap.push(key + 1000, i))
+      .collect();
+
+    // Get some structure going first.
+    heap.pop();
+    heap.pop();
+
+    let mut expected: Vec<(u64, usize)> = Vec::new();
+    for (i, handle) in handles.iter().enumerate() {
+      let Some((&key, &value)) = heap.get(handle) else {
+        continue;
+      };
+      let key = if i % 3 == 0 { key - 1000 } else { key };
+      heap.decrease_key(handle, key).unwrap();
+      heap.check_invariants();
+      expected.push((key, value));
+    }
+
+    expected.sort();
+    let mut popped = Vec::new();
+    while let Some(entry) = heap.pop() {
+      popped.push(entry);
+    }
+    let keys: Vec<u64> = popped.iter().map(|&(key, _)| key).collect();
+    assert_eq!(
+      expected.iter().map(|&(key, _)| key).collect::<Vec<_>>(),
+      keys
+    );
+  }
+
+  #[test]
+  fn decrease_key_errors() {
+    let mut heap = PairingHeap::new();
+    let mut other = PairingHeap::new();
+    let a = heap.push(5, "a");
+    let b = other.push(5, "b");
+
+    assert_eq!(
+      Err(DecreaseKeyError::KeyIncreased),
+      heap.decrease_key(&a, 6)
+    );
+    assert_eq!(Some((&5, &"a")), heap.get(&a));
+    assert_eq!(Err(DecreaseKeyError::OtherHeap), heap.decrease_key(&b, 1));
+    assert_eq!(None, heap.get(&b));
+
+    heap.pop();
+    assert_eq!(Err(DecreaseKeyError::Removed), heap.decrease_key(&a, 1));
+    assert!(!heap.contains(&a));
+
+    drop(other);
+    assert_eq!(Err(DecreaseKeyError::Removed), heap.decrease_key(&b, 1));
+  }
+
+  #[test]
+  fn handles_through_chains_of_merges() {
+    let mut heaps: Vec<PairingHeap<usize, ()>> = (0..50).map(|_| PairingHeap::new()).collect();
+    let handles: Vec<_> = heaps
+      .iter_mut()
+      .enumerate()
+      .map(|(i, heap)| heap.push(i + 100, ()))
+      .collect();
+
+    // Each heap into the next, so the first one's owner is at the end of a
+    // long chain.
+    let mut merged = heaps.remove(0);
+    for mut heap in heaps {
+      heap.merge(merged);
+      merged = heap;
+    }
+
+    for (i, handle) in handles.iter().enumerate() {
+      merged.decrease_key(handle, i).unwrap();
+    }
+    merged.check_invariants();
+    assert_eq!((0..50).collect::<Vec<_>>(), drain(&mut merged));
+  }
+
+  #[test]
+  fn dijkstra() {
+    // From 0 the direct edges look short, but the long way round is shorter.
+    let edges: &[(usize, usize, u32)] = &[
+      (0, 1, 7),
+      (0, 2, 9),
+      (0, 5, 14),
+      (1, 2, 10),
+      (1, 3, 15),
+      (2, 3, 11),
+      (2, 5, 2),
+      (3, 4, 6),
+      (4, 5, 9),
+    ];
+
+    let mut heap = PairingHeap::new();
+    let handles: Vec<_> = (0..6)
+      .map(|node| heap.push(if node == 0 { 0 } else { u32::MAX }, node))
+      .collect();
+    let mut dist = [u32::MAX; 6];
+
+    while let Some((d, node)) = heap.pop() {
+      dist[node] = d;
+
+      for &(a, b, weight) in edges {
+        let other = match node {
+          _ if a == node => b,
+          _ if b == node => a,
+          _ => continue,
+        };
+        if let Some((&current, _)) = heap.get(&handles[other]) {
+          if d + weight < current {
+            heap.decrease_key(&handles[other], d + weight).unwrap();
+          }
+        }
+      }
+    }
+
+    assert_eq!([0, 7, 9, 20, 20, 11], dist);
+  }
+
+  /// A key whose comparisons panic once `armed` is set.
+  struct Touchy {
+    key: u32,
+    armed: Rc<Cell<bool>>,
+  }
+
+  impl PartialEq for Touchy {
+    fn eq(&self, other: &Self) -> bool {
+      self.cmp(other).is_eq()
+    }
+  }
+
+  impl Eq for Touchy {}
+
+  impl PartialOrd for Touchy {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+      Some(self.cmp(other))
+    }
+  }
+
+  impl Ord for Touchy {
+    fn cmp(&self, other: &Self) -> Ordering {
+      assert!(!self.armed.get(), "comparison");
+      self.key.cmp(&other.key)
+    }
+  }
+
+  #[test]
+  fn survives_panicking_comparisons() {
+    let armed = Rc::new(Cell::new(false));
+    let touchy = |key| Touchy {
+      key,
+      armed: armed.clone(),
+    };
+    let counter = DropCounter::new();
+
+    let mut heap = PairingHeap::new();
+    let handles: Vec<_> = (0..10)
+      .map(|i| heap.push(touchy(i * 10), counter.make()))
+      .collect();
+    heap.pop();
+
+    // Lowering a key is all or nothing.
+    armed.set(true);
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+      heap.decrease_key(&handles[7], touchy(5)).unwrap();
+    }));
+    assert!(result.is_err());
+    armed.set(false);
+    heap.check_invariants();
+    assert_eq!(9, heap.len());
+    assert_eq!(70, heap.get(&handles[7]).unwrap().0.key);
+
+    heap.decrease_key(&handles[7], touchy(5)).unwrap();
+    heap.check_invariants();
+    assert_eq!(5, heap.peek().unwrap().0.key);
+    drop(heap);
+    counter.assert_all_dropped();
+
+    // A pop that panics throws away what's left, and leaves an empty heap
+    // behind rather than one pointing at freed nodes.
+    let mut heap = PairingHeap::new();
+    let handles: Vec<_> = (0..10)
+      .map(|i| heap.push(touchy(i), counter.make()))
+      .collect();
+    heap.pop();
+    armed.set(true);
+    let result = panic::catch_unwind(AssertUnwindSafe(|| heap.pop()));
+    assert!(result.is_err());
+    armed.set(false);
+    counter.assert_all_dropped();
+    assert!(!heap.contains(&handles[5]));
+    heap.check_invariants();
+    assert!(heap.is_empty());
+
+    heap.push(touchy(3), counter.make());
+    assert_eq!(3, heap.pop().unwrap().0.key);
+  }
+
+  #[test]
+  fn frees_everything() {
+    let counter = DropCounter::new();
+
+    let mut heap = PairingHeap::new();
+    for (i, value) in counter.make_n(100).into_iter().enumerate() {
+      heap.push((i * 37) % 100, value);
+    }
+    drop(heap.pop());
+    drop(heap.pop());
+    assert_eq!(2, counter.dropped());
+    drop(heap);
+    counter.assert_all_dropped();
+
+    let mut heap = PairingHeap::new();
+    for (i, value) in counter.make_n(20).into_iter().enumerate() {
+      heap.push(i, value);
+    }
+    heap.pop();
+    counter.panic_on_drop(counter.created() - 7);
+    let result = panic::catch_unwind(AssertUnwindSafe(|| drop(heap)));
+    assert!(result.is_err());
+    counter.assert_all_dropped();
+  }
+}
@@ -1,10 +1,23 @@
 use std::{
   boxed,
   cmp::Ordering,
-  fmt::{self, Debug},
+  fmt::{self, Debug, Display},
   hash::{Hash, Hasher},
   marker::PhantomData,
+  mem,
+  num::NonZeroUsize,
+  ops::{Bound, RangeBounds},
   ptr::NonNull,
+  str::FromStr,
+  thread,
+};
+
+use crate::{
+  dot::{Edge, Graph, ToDot},
+  drop_guard,
+  list_fmt::{self, ParseListError},
+  memory::MemoryUsage,
+  traits::{Deque, Queue, Stack},
 };
 
 pub struct LinkedList<T> {
@@ -193,19 +206,291 @@ impl<T> LinkedList<T> {
   }
 }
 
+/// Parallel iteration on top of `std::thread::scope`.
+///
+/// The list is cut into one contiguous run of nodes per available core in a
+/// single walk, and each run is handed to its own thread as an `Iter` or
+/// `IterMut` bounded by the run's first node, last node and length. If `f`
+/// panics on any thread, the panic is propagated once every thread is done.
+impl<T> LinkedList<T> {
+  pub fn par_for_each<F>(&self, f: F)
+  where
+    T: Sync,
+    F: Fn(&T) + Sync,
+  {
+    let f = &f;
+
+    thread::scope(|scope| {
+      for iter in self.par_iters() {
+        scope.spawn(move || iter.for_each(f));
+      }
+    });
+  }
+
+  pub fn par_for_each_mut<F>(&mut self, f: F)
+  where
+    T: Send,
+    F: Fn(&mut T) + Sync,
+  {
+    let f = &f;
+
+    thread::scope(|scope| {
+      for iter in self.par_iters_mut() {
+        scope.spawn(move || iter.for_each(f));
+      }
+    });
+  }
+
+  /// Maps every element on the thread that owns its run, then stitches the
+  /// resulting lists together in order without copying them.
+  pub fn par_map_collect<U, F>(&self, f: F) -> LinkedList<U>
+  where
+    T: Sync,
+    U: Send,
+    F: Fn(&T) -> U + Sync,
+  {
+    let f = &f;
+
+    let parts: Vec<LinkedList<U>> = thread::scope(|scope| {
+      let handles: Vec<_> = self
+        .par_iters()
+        .into_iter()
+        .map(|iter| scope.spawn(move || iter.map(f).collect::<LinkedList<U>>()))
+        .collect();
+
+      handles
+        .into_iter()
+        .map(|handle| {
+          handle
+            .join()
+            .unwrap_or_else(|err| std::panic::resume_unwind(err))
+        })
+        .collect()
+    });
+
+    let mut output = LinkedList::new();
+
+    for part in parts {
+      // The cursor starts at the ghost, so this appends to the back.
+      output.cursor_mut().splice_before(part);
+    }
+
+    output
+  }
+
+  /// Folds every run starting from its own `identity()`, then combines the
+  /// partial results front to back with `reduce`.
+  pub fn par_fold<A, I, F, R>(&self, identity: I, fold: F, reduce: R) -> A
+  where
+    T: Sync,
+    A: Send,
+    I: Fn() -> A + Sync,
+    F: Fn(A, &T) -> A + Sync,
+    R: Fn(A, A) -> A,
+  {
+    let (identity, fold) = (&identity, &fold);
+
+    let partials: Vec<A> = thread::scope(|scope| {
+      let handles: Vec<_> = self
+        .par_iters()
+        .into_iter()
+        .map(|iter| scope.spawn(move || iter.fold(identity(), fold)))
+        .collect();
+
+      handles
+        .into_iter()
+        .map(|handle| {
+          handle
+            .join()
+            .unwrap_or_else(|err| std::panic::resume_unwind(err))
+        })
+        .collect()
+    });
+
+    partials.into_iter().reduce(reduce).unwrap_or_else(identity)
+  }
+
+  fn par_iters(&self) -> Vec<Iter<'_, T>> {
+    self
+      .split_runs(par_threads(self.len))
+      .into_iter()
+      .map(|(front, back, len)| Iter {
+        front,
+        back,
+        len,
+        _p: PhantomData,
+      })
+      .collect()
+  }
+
+  fn par_iters_mut(&mut self) -> Vec<IterMut<'_, T>> {
+    self
+      .split_runs(par_threads(self.len))
+      .into_iter()
+      .map(|(front, back, len)| IterMut {
+        front,
+        back,
+        len,
+        _p: PhantomData,
+      })
+      .collect()
+  }
+
+  /// Walks the list once, returning the first node, last node and length of
+  /// `parts` runs whose lengths differ by at most one.
+  fn split_runs(&self, parts: usize) -> Vec<(Link<T>, Link<T>, usize)> {
+    let mut runs = Vec::with_capacity(parts);
+    let mut current = self.front;
+
+    for i in 0..parts {
+      let len = self.len / parts + usize::from(i < self.len % parts);
+      let front = current;
+      let mut back = current;
+
+      for _ in 0..len {
+        back = current;
+        // SAFETY: there are at least `len` nodes left after `current`.
+        current = unsafe { (*current.unwrap().as_ptr()).back };
+      }
+
+      runs.push((front, back, len));
+    }
+
+    runs
+  }
+}
+
+/// One thread per core, but never more threads than elements.
+fn par_threads(len: usize) -> usize {
+  thread::available_parallelism()
+    .map_or(1, NonZeroUsize::get)
+    .min(len)
+}
+
+/// Iterators over part of the list. Like slicing, a range that's backwards
+/// or runs past the end panics.
+impl<T> LinkedList<T> {
+  pub fn range<R: RangeBounds<usize>>(&self, range: R) -> Iter<'_, T> {
+    let (front, back, len) = self.run(range);
+
+    Iter {
+      front,
+      back,
+      len,
+      _p: PhantomData,
+    }
+  }
+
+  pub fn range_mut<R: RangeBounds<usize>>(&mut self, range: R) -> IterMut<'_, T> {
+    let (front, back, len) = self.run(range);
+
+    IterMut {
+      front,
+      back,
+      len,
+      _p: PhantomData,
+    }
+  }
+
+  /// Splits `iter_mut()` into `0..at` and `at..`, which never share a node,
+  /// so each can go to its own thread.
+  pub fn iter_mut_split_at(&mut self, at: usize) -> (IterMut<'_, T>, IterMut<'_, T>) {
+    assert!(
+      at <= self.len,
+      "split index {} out of range for length {}",
+      at,
+      self.len
+    );
+
+    let (front, back) = (self.run(..at), self.run(at..));
+
+    (
+      IterMut {
+        front: front.0,
+        back: front.1,
+        len: front.2,
+        _p: PhantomData,
+      },
+      IterMut {
+        front: back.0,
+        back: back.1,
+        len: back.2,
+        _p: PhantomData,
+      },
+    )
+  }
+
+  /// The first node, last node and length of `range`, in the same shape
+  /// `split_runs` hands out.
+  fn run<R: RangeBounds<usize>>(&self, range: R) -> (Link<T>, Link<T>, usize) {
+    let start = match range.start_bound() {
+      Bound::Included(&start) => start,
+      Bound::Excluded(&start) => start.checked_add(1).expect("range start overflows"),
+      Bound::Unbounded => 0,
+    };
+    let end = match range.end_bound() {
+      Bound::Included(&end) => end.checked_add(1).expect("range end overflows"),
+      Bound::Excluded(&end) => end,
+      Bound::Unbounded => self.len,
+    };
+
+    assert!(
+      start <= end,
+      "range starts at {} but ends at {}",
+      start,
+      end
+    );
+    assert!(
+      end <= self.len,
+      "range end {} out of range for length {}",
+      end,
+      self.len
+    );
+
+    if start == end {
+      return (None, None, 0);
+    }
+
+    (self.node_at(start), self.node_at(end - 1), end - start)
+  }
+
+  /// Walks to the node at `index`, from whichever end is closer.
+  fn node_at(&self, index: usize) -> Link<T> {
+    debug_assert!(index < self.len);
+
+    // SAFETY: `index` is in bounds, so every step lands on a node.
+    unsafe {
+      if index < self.len / 2 {
+        let mut current = self.front;
+        for _ in 0..index {
+          current = (*current.unwrap().as_ptr()).back;
+        }
+        current
+      } else {
+        let mut current = self.back;
+        for _ in index + 1..self.len {
+          current = (*current.unwrap().as_ptr()).front;
+        }
+        current
+      }
+    }
+  }
+}
+
 unsafe impl<T: Send> Send for LinkedList<T> {}
 unsafe impl<T: Sync> Sync for LinkedList<T> {}
 
-unsafe impl<'a, T: Send> Send for Iter<'a, T> {}
-unsafe impl<'a, T: Send> Sync for Iter<'a, T> {}
+// Iter only ever hands out shared references, so sending it to another
+// thread is the same as sending a &T.
+unsafe impl<'a, T: Sync> Send for Iter<'a, T> {}
+unsafe impl<'a, T: Sync> Sync for Iter<'a, T> {}
 
 unsafe impl<'a, T: Send> Send for IterMut<'a, T> {}
-unsafe impl<'a, T: Send> Sync for IterMut<'a, T> {}
+unsafe impl<'a, T: Sync> Sync for IterMut<'a, T> {}
 
 impl<T> Drop for LinkedList<T> {
   fn drop(&mut self) {
-    // Pop nodes until the list is empty.
-    while self.pop_front().is_some() {}
+    drop_guard::drop_all_with_guard(self, Self::pop_front);
   }
 }
 
@@ -255,6 +540,106 @@ impl<T: Debug> Debug for LinkedList<T> {
   }
 }
 
+impl<T: Display> Display for LinkedList<T> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    list_fmt::write_list(f, self, list_fmt::DOUBLY_LINKED)
+  }
+}
+
+impl<T> MemoryUsage for LinkedList<T> {
+  fn node_count(&self) -> usize {
+    self.len
+  }
+
+  fn heap_bytes(&self) -> usize {
+    self.len * mem::size_of::<Node<T>>()
+  }
+}
+
+impl<T> Stack<T> for LinkedList<T> {
+  fn push(&mut self, elem: T) {
+    LinkedList::push_front(self, elem);
+  }
+
+  fn pop(&mut self) -> Option<T> {
+    LinkedList::pop_front(self)
+  }
+}
+
+impl<T> Queue<T> for LinkedList<T> {
+  fn enqueue(&mut self, elem: T) {
+    LinkedList::push_back(self, elem);
+  }
+
+  fn dequeue(&mut self) -> Option<T> {
+    LinkedList::pop_front(self)
+  }
+}
+
+impl<T> Deque<T> for LinkedList<T> {
+  fn push_front(&mut self, elem: T) {
+    LinkedList::push_front(self, elem);
+  }
+
+  fn push_back(&mut self, elem: T) {
+    LinkedList::push_back(self, elem);
+  }
+
+  fn pop_front(&mut self) -> Option<T> {
+    LinkedList::pop_front(self)
+  }
+
+  fn pop_back(&mut self) -> Option<T> {
+    LinkedList::pop_back(self)
+  }
+}
+
+impl<T: Display> ToDot for LinkedList<T> {
+  fn add_to_graph(&self, graph: &mut Graph, name: &str) {
+    graph.marker(
+      name,
+      "head",
+      self.front.map(|node| node.as_ptr() as *const Node<T>),
+    );
+    graph.marker(
+      name,
+      "tail",
+      self.back.map(|node| node.as_ptr() as *const Node<T>),
+    );
+
+    let mut current = self.front;
+
+    while let Some(node) = current {
+      unsafe {
+        let node = node.as_ptr() as *const Node<T>;
+
+        graph.node(node, &(*node).elem);
+
+        // Remember that `front` points towards the front of the list,
+        // which makes it the previous node.
+        if let Some(prev) = (*node).front {
+          graph.edge(node, prev.as_ptr(), Edge::Prev);
+        }
+        if let Some(next) = (*node).back {
+          graph.edge(node, next.as_ptr(), Edge::Next);
+        }
+
+        current = (*node).back;
+      }
+    }
+  }
+}
+
+impl<T: FromStr> FromStr for LinkedList<T> {
+  type Err = ParseListError<T::Err>;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    let mut list = Self::new();
+    list_fmt::parse_list(s, |elem| list.push_back(elem))?;
+    Ok(list)
+  }
+}
+
 impl<T: PartialEq> PartialEq for LinkedList<T> {
   fn eq(&self, other: &Self) -> bool {
     self.len() == other.len() && self.iter().eq(other)
@@ -468,7 +853,7 @@ impl<'a, T> CursorMut<'a, T> {
         self.current = (*current.as_ptr()).front;
 
         if self.current.is_some() {
-          *self.index.as_mut().unwrap() += 1;
+          *self.index.as_mut().unwrap() -= 1;
         } else {
           self.index = None;
         }
@@ -483,21 +868,33 @@ impl<'a, T> CursorMut<'a, T> {
     unsafe { self.current.map(|node| &mut (*node.as_ptr()).elem) }
   }
 
+  /// Like `current`, but gives up the cursor to borrow the element for as
+  /// long as the list.
+  pub fn into_current(self) -> Option<&'a mut T> {
+    unsafe { self.current.map(|node| &mut (*node.as_ptr()).elem) }
+  }
+
   pub fn peek_next(&mut self) -> Option<&mut T> {
     unsafe {
-      self
-        .current
-        .and_then(|node| (*node.as_ptr()).back)
-        .map(|node| &mut (*node.as_ptr()).elem)
+      let next = match self.current {
+        Some(node) => (*node.as_ptr()).back,
+        // The ghost sits between the back and the front.
+        None => self.list.front,
+      };
+
+      next.map(|node| &mut (*node.as_ptr()).elem)
     }
   }
 
   pub fn peek_prev(&mut self) -> Option<&mut T> {
     unsafe {
-      self
-        .current
-        .and_then(|node| (*node.as_ptr()).front)
-        .map(|node| &mut (*node.as_ptr()).elem)
+      let prev = match self.current {
+        Some(node) => (*node.as_ptr()).front,
+        // The ghost sits between the back and the front.
+        None => self.list.back,
+      };
+
+      prev.map(|node| &mut (*node.as_ptr()).elem)
     }
   }
 
@@ -519,7 +916,8 @@ impl<'a, T> CursorMut<'a, T> {
 
           // What the output will become
           let output_len = old_len - new_len;
-          let output_front = self.list.front;
+          // Nothing comes before the front, so the output is empty there.
+          let output_front = previous.and(self.list.front);
           let output_back = previous;
 
           // Break the links between current and previous
@@ -558,8 +956,9 @@ impl<'a, T> CursorMut<'a, T> {
       if let Some(current) = self.current {
         if let Some(0) = self.index {
           // We're appending to the front
-          (*current.as_ptr()).front = input.back.take();
-          (*input.back.unwrap().as_ptr()).back = Some(current);
+          let in_back = input.back.take().unwrap();
+          (*current.as_ptr()).front = Some(in_back);
+          (*in_back.as_ptr()).back = Some(current);
           self.list.front = input.front.take();
         } else {
           let previous = (*current.as_ptr()).front.unwrap();
@@ -576,8 +975,9 @@ impl<'a, T> CursorMut<'a, T> {
         self.list.len += input.len;
         input.len = 0;
       } else if let Some(back) = self.list.back {
-        (*back.as_ptr()).back = input.front.take();
-        (*input.front.unwrap().as_ptr()).front = Some(back);
+        let in_front = input.front.take().unwrap();
+        (*back.as_ptr()).back = Some(in_front);
+        (*in_front.as_ptr()).front = Some(back);
         self.list.back = input.back.take();
         self.list.len += input.len;
         input.len = 0;
@@ -588,6 +988,394 @@ impl<'a, T> CursorMut<'a, T> {
   }
 }
 
+impl<T> LinkedList<T> {
+  /// Runs `f` as one all-or-nothing edit. If it returns `Err` or panics,
+  /// every change it made is undone by relinking the original nodes, so the
+  /// list ends up exactly as it was without a single element being cloned.
+  ///
+  /// Elements removed inside the transaction stay alive until it ends, in
+  /// case they have to be put back, and are dropped once it commits.
+  pub fn transaction<R, E, F>(&mut self, f: F) -> Result<R, E>
+  where
+    F: FnOnce(&mut Transaction<'_, T>) -> Result<R, E>,
+  {
+    let mut tx = Transaction {
+      cursor: self.cursor_mut(),
+      log: Vec::new(),
+    };
+
+    let result = f(&mut tx);
+
+    if result.is_ok() {
+      tx.commit();
+    }
+
+    // Otherwise dropping `tx` rolls it back, and the same happens while
+    // unwinding if `f` panicked.
+    result
+  }
+
+  /// Links `node` in between `prev` and `next`, which must be adjacent.
+  unsafe fn link_between(&mut self, node: NonNull<Node<T>>, prev: Link<T>, next: Link<T>) {
+    (*node.as_ptr()).front = prev;
+    (*node.as_ptr()).back = next;
+
+    match prev {
+      Some(prev) => (*prev.as_ptr()).back = Some(node),
+      None => self.front = Some(node),
+    }
+
+    match next {
+      Some(next) => (*next.as_ptr()).front = Some(node),
+      None => self.back = Some(node),
+    }
+
+    self.len += 1;
+  }
+
+  /// Takes `node` out of the list, leaving its own links as they were.
+  unsafe fn unlink(&mut self, node: NonNull<Node<T>>) {
+    let prev = (*node.as_ptr()).front;
+    let next = (*node.as_ptr()).back;
+
+    match prev {
+      Some(prev) => (*prev.as_ptr()).back = next,
+      None => self.front = next,
+    }
+
+    match next {
+      Some(next) => (*next.as_ptr()).front = prev,
+      None => self.back = prev,
+    }
+
+    self.len -= 1;
+  }
+}
+
+/// What a [`Transaction`] did, and so what undoing it takes.
+enum Undo<T> {
+  /// A new node, freed on rollback.
+  Inserted(NonNull<Node<T>>),
+  /// A node taken out from between `prev` and `next`, put back there on
+  /// rollback and freed on commit.
+  Removed {
+    node: NonNull<Node<T>>,
+    prev: Link<T>,
+    next: Link<T>,
+  },
+  /// A run of `len` new nodes from `first` to `last`, freed on rollback.
+  Spliced {
+    first: NonNull<Node<T>>,
+    last: NonNull<Node<T>>,
+    len: usize,
+  },
+}
+
+/// The list as seen from inside [`LinkedList::transaction`], with a cursor
+/// for editing in the middle. Elements that were in the list before are only
+/// handed out as shared references, since writes to them couldn't be undone.
+pub struct Transaction<'a, T> {
+  cursor: CursorMut<'a, T>,
+  log: Vec<Undo<T>>,
+}
+
+impl<'a, T> Transaction<'a, T> {
+  pub fn len(&self) -> usize {
+    self.cursor.list.len
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.len() == 0
+  }
+
+  pub fn front(&self) -> Option<&T> {
+    self.cursor.list.front()
+  }
+
+  pub fn back(&self) -> Option<&T> {
+    self.cursor.list.back()
+  }
+
+  pub fn iter(&self) -> Iter<'_, T> {
+    self.cursor.list.iter()
+  }
+
+  pub fn index(&self) -> Option<usize> {
+    self.cursor.index
+  }
+
+  pub fn current(&self) -> Option<&T> {
+    unsafe { self.cursor.current.map(|node| &(*node.as_ptr()).elem) }
+  }
+
+  pub fn move_next(&mut self) {
+    self.cursor.move_next();
+  }
+
+  pub fn move_prev(&mut self) {
+    self.cursor.move_prev();
+  }
+
+  pub fn push_front(&mut self, elem: T) {
+    unsafe {
+      let front = self.cursor.list.front;
+      self.insert(elem, None, front);
+    }
+  }
+
+  pub fn push_back(&mut self, elem: T) {
+    unsafe {
+      let back = self.cursor.list.back;
+      self.insert(elem, back, None);
+    }
+  }
+
+  pub fn pop_front(&mut self) -> Option<&T> {
+    let front = self.cursor.list.front?;
+    unsafe { Some(self.remove(front)) }
+  }
+
+  pub fn pop_back(&mut self) -> Option<&T> {
+    let back = self.cursor.list.back?;
+    unsafe { Some(self.remove(back)) }
+  }
+
+  /// Inserts before the cursor, or at the back when it's on the ghost.
+  pub fn insert_before(&mut self, elem: T) {
+    unsafe {
+      let (prev, next) = self.around_cursor();
+      self.insert(elem, prev, next);
+    }
+  }
+
+  /// Inserts after the cursor, or at the front when it's on the ghost.
+  pub fn insert_after(&mut self, elem: T) {
+    unsafe {
+      let (prev, next) = match self.cursor.current {
+        Some(current) => (Some(current), (*current.as_ptr()).back),
+        None => (None, self.cursor.list.front),
+      };
+      self.insert(elem, prev, next);
+    }
+  }
+
+  /// Removes the element under the cursor, moving the cursor on to the next
+  /// one.
+  pub fn remove_current(&mut self) -> Option<&T> {
+    let current = self.cursor.current?;
+    unsafe { Some(self.remove(current)) }
+  }
+
+  /// Moves all of `input` in before the cursor, or onto the back when it's
+  /// on the ghost.
+  pub fn splice_before(&mut self, mut input: LinkedList<T>) {
+    let (first, last, len) = match (input.front.take(), input.back.take()) {
+      (Some(first), Some(last)) => (first, last, mem::take(&mut input.len)),
+      _ => return,
+    };
+
+    unsafe {
+      let (prev, next) = self.around_cursor();
+      let list = &mut *self.cursor.list;
+
+      (*first.as_ptr()).front = prev;
+      (*last.as_ptr()).back = next;
+
+      match prev {
+        Some(prev) => (*prev.as_ptr()).back = Some(first),
+        None => list.front = Some(first),
+      }
+
+      match next {
+        Some(next) => (*next.as_ptr()).front = Some(last),
+        None => list.back = Some(last),
+      }
+
+      list.len += len;
+    }
+
+    if let Some(index) = self.cursor.index.as_mut() {
+      *index += len;
+    }
+
+    self.log.push(Undo::Spliced { first, last, len });
+  }
+
+  /// The nodes on either side of the gap before the cursor.
+  unsafe fn around_cursor(&self) -> (Link<T>, Link<T>) {
+    match self.cursor.current {
+      Some(current) => ((*current.as_ptr()).front, Some(current)),
+      None => (self.cursor.list.back, None),
+    }
+  }
+
+  unsafe fn insert(&mut self, elem: T, prev: Link<T>, next: Link<T>) {
+    let node = NonNull::new_unchecked(Box::into_raw(Box::new(Node {
+      front: None,
+      back: None,
+      elem,
+    })));
+
+    self.cursor.list.link_between(node, prev, next);
+    self.log.push(Undo::Inserted(node));
+
+    // Only something going in before the cursor moves its index, which is
+    // either right before it or at the front.
+    if let (Some(index), Some(current)) = (self.cursor.index.as_mut(), self.cursor.current) {
+      if next == Some(current) || prev.is_none() {
+        *index += 1;
+      }
+    }
+  }
+
+  unsafe fn remove(&mut self, node: NonNull<Node<T>>) -> &T {
+    let prev = (*node.as_ptr()).front;
+    let next = (*node.as_ptr()).back;
+
+    if self.cursor.current == Some(node) {
+      self.cursor.current = next;
+      if next.is_none() {
+        self.cursor.index = None;
+      }
+    } else if let Some(index) = self.cursor.index.as_mut() {
+      // Other than the current node, only the ends get removed, and of
+      // those only the front is before the cursor.
+      if self.cursor.list.front == Some(node) {
+        *index -= 1;
+      }
+    }
+
+    self.cursor.list.unlink(node);
+    self.log.push(Undo::Removed { node, prev, next });
+
+    &(*node.as_ptr()).elem
+  }
+
+  /// Keeps the changes, freeing whatever was removed.
+  fn commit(mut self) {
+    let mut log = mem::take(&mut self.log);
+
+    drop_guard::drop_all_with_guard(&mut log, |log| loop {
+      if let Undo::Removed { node, .. } = log.pop()? {
+        return Some(unsafe { Box::from_raw(node.as_ptr()) });
+      }
+    });
+  }
+
+  fn rollback(&mut self) {
+    let list = &mut *self.cursor.list;
+
+    while let Some(undo) = self.log.pop() {
+      unsafe {
+        match undo {
+          Undo::Inserted(node) => {
+            list.unlink(node);
+            drop(Box::from_raw(node.as_ptr()));
+          }
+          Undo::Removed { node, prev, next } => list.link_between(node, prev, next),
+          Undo::Spliced { first, last, len } => {
+            let prev = (*first.as_ptr()).front;
+            let next = (*last.as_ptr()).back;
+
+            match prev {
+              Some(prev) => (*prev.as_ptr()).back = next,
+              None => list.front = next,
+            }
+
+            match next {
+              Some(next) => (*next.as_ptr()).front = prev,
+              None => list.back = prev,
+            }
+
+            list.len -= len;
+
+            // Hand the run back to a list of its own to free it.
+            (*first.as_ptr()).front = None;
+            (*last.as_ptr()).back = None;
+            drop(LinkedList {
+              front: Some(first),
+              back: Some(last),
+              len,
+              _p: PhantomData,
+            });
+          }
+        }
+      }
+    }
+  }
+}
+
+impl<T> Drop for Transaction<'_, T> {
+  fn drop(&mut self) {
+    self.rollback();
+  }
+}
+
+impl<T> LinkedList<T> {
+  /// Walks the list checking that every link agrees with the node it points
+  /// at, and with `front`, `back` and `len`. Panics at the first that doesn't.
+  pub(crate) fn check_invariants(&self) {
+    let (front, back) = match (self.front, self.back) {
+      (None, None) => {
+        assert_eq!(self.len, 0, "empty list with a non-zero len");
+        return;
+      }
+      (Some(front), Some(back)) => (front, back),
+      _ => panic!("only one of front and back is set"),
+    };
+
+    unsafe {
+      assert!(
+        (*front.as_ptr()).front.is_none(),
+        "front node has a previous node"
+      );
+      assert!((*back.as_ptr()).back.is_none(), "back node has a next node");
+
+      let mut count = 1;
+      let mut node = front;
+
+      while let Some(next) = (*node.as_ptr()).back {
+        assert_eq!(
+          (*next.as_ptr()).front,
+          Some(node),
+          "node {} doesn't point back at node {}",
+          count,
+          count - 1
+        );
+        assert!(count < self.len, "more nodes than len {}", self.len);
+
+        node = next;
+        count += 1;
+      }
+
+      assert_eq!(node, back, "walking forwards doesn't end at back");
+      assert_eq!(count, self.len, "len doesn't match the number of nodes");
+    }
+  }
+}
+
+impl<'a, T> CursorMut<'a, T> {
+  /// The list's invariants, plus the cursor being on the node its index says.
+  pub(crate) fn check_invariants(&self) {
+    self.list.check_invariants();
+
+    match (self.current, self.index) {
+      (None, None) => {}
+      (Some(current), Some(index)) => {
+        assert!(index < self.list.len, "index {} out of bounds", index);
+
+        let mut node = self.list.front.unwrap();
+        for _ in 0..index {
+          node = unsafe { (*node.as_ptr()).back.unwrap() };
+        }
+
+        assert_eq!(node, current, "cursor isn't at index {}", index);
+      }
+      _ => panic!("cursor index and current disagree about the ghost"),
+    }
+  }
+}
+
 #[cfg(test)]
 mod test {
   use super::*;
@@ -840,6 +1628,35 @@ mod test {
     assert_eq!(format!("{:?}", list), r#"["just", "one", "test", "more"]"#);
   }
 
+  #[test]
+  fn test_display() {
+    let list: LinkedList<i32> = (0..4).collect();
+    assert_eq!(list.to_string(), "[0, 1, 2, 3]");
+    assert_eq!(format!("{:#}", list), "0 <-> 1 <-> 2 <-> 3");
+    assert_eq!(LinkedList::<i32>::new().to_string(), "[]");
+
+    let list: LinkedList<f64> = list_from(&[0.5, 1.25]);
+    assert_eq!(format!("{:.1}", list), "[0.5, 1.2]");
+  }
+
+  #[test]
+  fn test_from_str() {
+    let list: LinkedList<i32> = (0..4).collect();
+    assert_eq!(list.to_string().parse(), Ok(list.clone()));
+    assert_eq!(format!("{:#}", list).parse(), Ok(list));
+    assert_eq!("[]".parse(), Ok(LinkedList::<i32>::new()));
+
+    let err = "[1, 2, three]".parse::<LinkedList<i32>>().unwrap_err();
+    assert!(matches!(
+      err,
+      ParseListError::Element {
+        index: 2,
+        position: 7,
+        ..
+      }
+    ));
+  }
+
   #[test]
   fn test_hashmap() {
     // Check that HashMap works with this as a key
@@ -862,6 +1679,427 @@ mod test {
     assert!(map.is_empty());
   }
 
+  #[test]
+  fn test_cursor_move_prev() {
+    let mut list = generate_test();
+    let mut cursor = list.cursor_mut();
+
+    cursor.move_prev();
+    assert_eq!(cursor.index(), Some(6));
+    assert_eq!(cursor.current(), Some(&mut 6));
+
+    cursor.move_prev();
+    assert_eq!(cursor.index(), Some(5));
+    assert_eq!(cursor.current(), Some(&mut 5));
+  }
+
+  #[test]
+  fn test_cursor_edits_at_the_ends() {
+    let mut list = generate_test();
+    let mut cursor = list.cursor_mut();
+
+    // Splitting before the front leaves nothing behind.
+    cursor.move_next();
+    assert_eq!(cursor.split_before(), LinkedList::new());
+
+    cursor.splice_before(list_from(&[-2, -1]));
+    assert_eq!(cursor.index(), Some(2));
+
+    // At the ghost the input goes on the end.
+    cursor.move_prev();
+    cursor.move_prev();
+    cursor.move_prev();
+    cursor.splice_before(list_from(&[7, 8]));
+    assert_eq!(cursor.index(), None);
+
+    assert_eq!(list, list_from(&[-2, -1, 0, 1, 2, 3, 4, 5, 6, 7, 8]));
+    assert_eq!(
+      list.iter().rev().copied().collect::<Vec<_>>(),
+      vec![8, 7, 6, 5, 4, 3, 2, 1, 0, -1, -2]
+    );
+  }
+
+  #[test]
+  fn test_cursor_peek_from_the_ghost() {
+    let mut list = generate_test();
+    let mut cursor = list.cursor_mut();
+
+    assert_eq!(cursor.peek_next(), Some(&mut 0));
+    assert_eq!(cursor.peek_prev(), Some(&mut 6));
+
+    let mut empty = LinkedList::<i32>::new();
+    let mut cursor = empty.cursor_mut();
+    assert_eq!(cursor.peek_next(), None);
+    assert_eq!(cursor.peek_prev(), None);
+  }
+
+  /// A mix of every kind of edit a transaction can make.
+  fn edit(tx: &mut Transaction<'_, i32>) {
+    tx.push_front(-1);
+    tx.push_back(7);
+    assert_eq!(tx.pop_front(), Some(&-1));
+    assert_eq!(tx.pop_front(), Some(&0));
+    assert_eq!(tx.pop_back(), Some(&7));
+
+    tx.move_next();
+    tx.move_next();
+    assert_eq!(tx.current(), Some(&2));
+    tx.insert_before(10);
+    tx.insert_after(11);
+    assert_eq!(tx.remove_current(), Some(&2));
+    assert_eq!(tx.current(), Some(&11));
+    tx.splice_before(list_from(&[20, 21]));
+    assert_eq!(tx.index(), Some(4));
+
+    // Removing the front shifts the cursor's index, the back doesn't.
+    tx.pop_front();
+    tx.pop_back();
+    assert_eq!(tx.index(), Some(3));
+    assert_eq!(tx.iter().nth(3), tx.current());
+  }
+
+  #[test]
+  fn test_transaction_commit() {
+    let mut list = generate_test();
+
+    let result: Result<_, ()> = list.transaction(|tx| {
+      edit(tx);
+      Ok(tx.len())
+    });
+
+    assert_eq!(result, Ok(7));
+    assert_eq!(list, list_from(&[10, 20, 21, 11, 3, 4, 5]));
+    list.check_invariants();
+  }
+
+  #[test]
+  fn test_transaction_rollback() {
+    let mut list = generate_test();
+    let nodes: Vec<*const i32> = list.iter().map(|elem| elem as *const i32).collect();
+
+    let result = list.transaction(|tx| {
+      edit(tx);
+      tx.splice_before(list_from(&[30]));
+      Err::<(), _>("nope")
+    });
+
+    assert_eq!(result, Err("nope"));
+    assert_eq!(list, generate_test());
+    list.check_invariants();
+
+    // The very same nodes, not copies of them.
+    let after: Vec<*const i32> = list.iter().map(|elem| elem as *const i32).collect();
+    assert_eq!(nodes, after);
+  }
+
+  #[test]
+  fn test_transaction_rollback_on_panic() {
+    let mut list = generate_test();
+
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+      list.transaction(|tx| -> Result<(), ()> {
+        edit(tx);
+        panic!("halfway through");
+      })
+    }));
+
+    assert!(result.is_err());
+    assert_eq!(list, generate_test());
+    list.check_invariants();
+  }
+
+  #[test]
+  fn test_transaction_on_empty_list() {
+    let mut list = LinkedList::new();
+
+    let result = list.transaction(|tx| {
+      assert_eq!(tx.pop_front(), None);
+      assert_eq!(tx.remove_current(), None);
+
+      // On the ghost, before means the back and after the front.
+      tx.insert_before(2);
+      tx.insert_after(1);
+      tx.splice_before(LinkedList::new());
+      tx.splice_before(list_from(&[3]));
+      Ok::<_, ()>(tx.iter().copied().collect::<Vec<_>>())
+    });
+
+    assert_eq!(result, Ok(vec![1, 2, 3]));
+    list.check_invariants();
+
+    let _ = list.transaction(|tx| {
+      while tx.pop_back().is_some() {}
+      assert!(tx.is_empty());
+      Err::<(), _>(())
+    });
+    assert_eq!(list, list_from(&[1, 2, 3]));
+  }
+
+  #[test]
+  fn test_transaction_never_clones() {
+    use crate::drop_count::DropCounter;
+
+    let counter = DropCounter::new();
+    let mut list: LinkedList<_> = counter.make_n(4).into_iter().collect();
+
+    let _ = list.transaction(|tx| {
+      tx.pop_front();
+      tx.push_back(counter.make());
+      Err::<(), _>(())
+    });
+    // The pushed element is gone, the popped one is back.
+    assert_eq!(5, counter.created());
+    assert_eq!(1, counter.dropped());
+    assert_eq!(4, list.len());
+
+    let _ = list.transaction(|tx| {
+      tx.pop_front();
+      tx.pop_front();
+      // Still alive until the transaction is over.
+      assert_eq!(1, counter.dropped());
+      Ok::<_, ()>(())
+    });
+    assert_eq!(3, counter.dropped());
+    assert_eq!(5, counter.created());
+
+    drop(list);
+    counter.assert_all_dropped();
+  }
+
+  #[test]
+  fn test_transaction_commit_survives_a_panic() {
+    use crate::drop_count::DropCounter;
+
+    let counter = DropCounter::new();
+    let mut list: LinkedList<_> = counter.make_n(5).into_iter().collect();
+    counter.panic_on_drop(counter.created() - 4);
+
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+      list.transaction(|tx| {
+        tx.pop_front();
+        tx.pop_front();
+        tx.pop_front();
+        Ok::<_, ()>(())
+      })
+    }));
+    assert!(result.is_err());
+    assert_eq!(3, counter.dropped());
+    list.check_invariants();
+    assert_eq!(2, list.len());
+
+    drop(list);
+    counter.assert_all_dropped();
+  }
+
+  #[test]
+  #[should_panic(expected = "len doesn't match the number of nodes")]
+  fn test_check_invariants() {
+    let mut list = generate_test();
+    list.check_invariants();
+
+    list.len += 1;
+    list.check_invariants();
+  }
+
+  #[test]
+  fn test_split_runs() {
+    let list: LinkedList<i32> = (0..10).collect();
+
+    let runs: Vec<Vec<i32>> = list
+      .split_runs(3)
+      .into_iter()
+      .map(|(front, back, len)| {
+        Iter {
+          front,
+          back,
+          len,
+          _p: PhantomData,
+        }
+        .copied()
+        .collect()
+      })
+      .collect();
+
+    assert_eq!(runs, vec![vec![0, 1, 2, 3], vec![4, 5, 6], vec![7, 8, 9]]);
+
+    // The runs also work from the back.
+    let (front, back, len) = list.split_runs(2)[0];
+    let iter = Iter {
+      front,
+      back,
+      len,
+      _p: PhantomData,
+    };
+    assert_eq!(iter.rev().copied().collect::<Vec<_>>(), vec![4, 3, 2, 1, 0]);
+
+    assert!(LinkedList::<i32>::new().split_runs(0).is_empty());
+    assert_eq!(par_threads(0), 0);
+    assert_eq!(par_threads(1), 1);
+  }
+
+  #[test]
+  fn test_par_for_each() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    let list: LinkedList<usize> = (0..1000).collect();
+    let sum = AtomicUsize::new(0);
+
+    list.par_for_each(|x| {
+      sum.fetch_add(*x, Ordering::Relaxed);
+    });
+
+    assert_eq!(sum.into_inner(), 999 * 1000 / 2);
+
+    LinkedList::<usize>::new().par_for_each(|_| unreachable!());
+  }
+
+  #[test]
+  fn test_par_for_each_mut() {
+    let mut list: LinkedList<i32> = (0..1000).collect();
+
+    list.par_for_each_mut(|x| *x *= 2);
+
+    assert!(list.iter().copied().eq((0..1000).map(|x| x * 2)));
+    assert_eq!(list.len(), 1000);
+    assert_eq!(list.back(), Some(&1998));
+  }
+
+  #[test]
+  fn test_par_map_collect() {
+    let list: LinkedList<i32> = (0..1000).collect();
+
+    let strings = list.par_map_collect(|x| x.to_string());
+
+    assert_eq!(strings.len(), 1000);
+    assert!(strings.iter().cloned().eq((0..1000).map(|x| x.to_string())));
+    assert!(strings
+      .iter()
+      .rev()
+      .cloned()
+      .eq((0..1000).rev().map(|x| x.to_string())));
+
+    let empty: LinkedList<i32> = LinkedList::new();
+    assert!(empty.par_map_collect(|x| *x).is_empty());
+  }
+
+  #[test]
+  fn test_par_fold() {
+    let list: LinkedList<u64> = (1..=1000).collect();
+
+    let sum = list.par_fold(|| 0, |acc, x| acc + x, |a, b| a + b);
+    assert_eq!(sum, 500500);
+
+    // The partial results are combined in order.
+    let list: LinkedList<char> = "parallel".chars().collect();
+    let text = list.par_fold(
+      String::new,
+      |mut acc, c| {
+        acc.push(*c);
+        acc
+      },
+      |a, b| a + &b,
+    );
+    assert_eq!(text, "parallel");
+
+    let empty: LinkedList<u64> = LinkedList::new();
+    assert_eq!(empty.par_fold(|| 7, |acc, x| acc + x, |a, b| a + b), 7);
+  }
+
+  #[test]
+  fn test_range() {
+    let list: LinkedList<i32> = (0..10).collect();
+
+    assert!(list.range(2..5).copied().eq(2..5));
+    assert!(list.range(2..5).rev().copied().eq((2..5).rev()));
+    assert!(list.range(..).copied().eq(0..10));
+    assert!(list.range(7..=9).copied().eq(7..10));
+    assert!(list.range(..3).copied().eq(0..3));
+    assert_eq!(list.range(4..4).len(), 0);
+    assert_eq!(list.range(10..).next(), None);
+
+    // Both ends meet in the middle without going past each other.
+    let mut range = list.range(3..6);
+    assert_eq!(range.len(), 3);
+    assert_eq!(range.next(), Some(&3));
+    assert_eq!(range.next_back(), Some(&5));
+    assert_eq!(range.next(), Some(&4));
+    assert_eq!(range.next_back(), None);
+    assert_eq!(range.next(), None);
+
+    // From each end of an odd and an even length list.
+    for len in [1, 2, 7, 8] {
+      let list: LinkedList<usize> = (0..len).collect();
+      for start in 0..=len {
+        for end in start..=len {
+          assert!(list.range(start..end).copied().eq(start..end));
+          assert!(list.range(start..end).rev().copied().eq((start..end).rev()));
+        }
+      }
+    }
+
+    assert_eq!(LinkedList::<i32>::new().range(..).next(), None);
+  }
+
+  #[test]
+  #[should_panic(expected = "out of range")]
+  fn test_range_past_the_end() {
+    let list: LinkedList<i32> = (0..3).collect();
+    list.range(1..4);
+  }
+
+  #[test]
+  #[should_panic(expected = "starts at 2 but ends at 1")]
+  fn test_range_backwards() {
+    let list: LinkedList<i32> = (0..3).collect();
+    #[allow(clippy::reversed_empty_ranges)]
+    list.range(2..1);
+  }
+
+  #[test]
+  fn test_range_mut() {
+    let mut list: LinkedList<i32> = (0..10).collect();
+
+    list.range_mut(3..7).for_each(|x| *x *= 10);
+    list.range_mut(..2).rev().for_each(|x| *x = -*x);
+
+    assert!(list.iter().copied().eq([0, -1, 2, 30, 40, 50, 60, 7, 8, 9]));
+    list.check_invariants();
+  }
+
+  #[test]
+  fn test_iter_mut_split_at() {
+    let mut list: LinkedList<i32> = (0..10).collect();
+
+    {
+      let (left, right) = list.iter_mut_split_at(4);
+      assert_eq!((left.len(), right.len()), (4, 6));
+
+      thread::scope(|scope| {
+        scope.spawn(|| left.for_each(|x| *x += 100));
+        scope.spawn(|| right.rev().for_each(|x| *x -= 100));
+      });
+    }
+
+    assert!(list.iter().copied().eq((100..104).chain(-96..-90)));
+
+    let (left, right) = list.iter_mut_split_at(0);
+    assert_eq!((left.len(), right.len()), (0, 10));
+    let (left, right) = list.iter_mut_split_at(10);
+    assert_eq!((left.len(), right.len()), (10, 0));
+    assert_eq!(right.count(), 0);
+
+    let mut empty: LinkedList<i32> = LinkedList::new();
+    let (mut left, mut right) = empty.iter_mut_split_at(0);
+    assert_eq!((left.next(), right.next_back()), (None, None));
+  }
+
+  #[test]
+  #[should_panic(expected = "split index 4 out of range")]
+  fn test_iter_mut_split_at_past_the_end() {
+    let mut list: LinkedList<i32> = (0..3).collect();
+    list.iter_mut_split_at(4);
+  }
+
   #[allow(dead_code)]
   fn assert_properties() {
     fn is_send<T: Send>() {}
@@ -901,4 +2139,10 @@ mod test {
   /// }
   /// ```
   fn iter_mut_covariant() {}
+
+  crate::traits::conformance::stack_conformance!(LinkedList<i32>);
+
+  crate::traits::conformance::queue_conformance!(LinkedList<i32>);
+
+  crate::traits::conformance::deque_conformance!(LinkedList<i32>);
 }
@@ -0,0 +1,41 @@
+//! SplitMix64, the one small PRNG the crate shares. Plenty for flipping
+//! coins and picking test operations, and the same seed always gives the
+//! same numbers.
+
+#[derive(Clone)]
+pub(crate) struct Rng(pub(crate) u64);
+
+impl Rng {
+  pub(crate) fn next_u64(&mut self) -> u64 {
+    self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    let mut z = self.0;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+  }
+
+  /// A number in `0..n`.
+  #[cfg(test)]
+  pub(crate) fn below(&mut self, n: usize) -> usize {
+    (self.next_u64() % n as u64) as usize
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn rng_is_deterministic() {
+    let mut a = Rng(7);
+    let mut b = Rng(7);
+
+    for _ in 0..100 {
+      let n = a.below(10);
+      assert!(n < 10);
+      assert_eq!(n, b.below(10));
+    }
+
+    assert_ne!(Rng(1).next_u64(), Rng(2).next_u64());
+  }
+}
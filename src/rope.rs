@@ -0,0 +1,843 @@
+//! A rope: text kept as a `LinkedList` of short `String` chunks, so an edit
+//! in the middle of a big document only touches a string or two.
+//!
+//! Positions are char indices, like `str::chars` would count them. Chunks
+//! are never empty and hold at most [`MAX_CHUNK_LEN`] bytes. Edits split a
+//! chunk that grows past that, and merge one that shrinks below a quarter of
+//! it into a neighbour.
+//!
+//! ```
+//! use learn_rust_with_entirely_too_many_linked_lists::rope::Rope;
+//!
+//! let mut rope = Rope::from("hello world");
+//! rope.insert(5, ",");
+//! rope.delete(7..12);
+//! rope.insert(7, "rope");
+//! assert_eq!("hello, rope", rope.to_string());
+//!
+//! let mut cursor = rope.cursor_mut();
+//! cursor.seek(5);
+//! assert_eq!(",", cursor.delete_next(1));
+//! cursor.insert_str("\nthere");
+//!
+//! assert_eq!(vec!["hello", "there rope"], rope.lines().collect::<Vec<_>>());
+//! ```
+
+use std::{
+  fmt,
+  ops::{Bound, RangeBounds},
+  str,
+};
+
+use crate::production_linked_list::{self, LinkedList};
+
+/// Most bytes a chunk holds.
+pub const MAX_CHUNK_LEN: usize = 256;
+
+/// A chunk shorter than this gets merged into a neighbour when it fits.
+const MIN_CHUNK_LEN: usize = MAX_CHUNK_LEN / 4;
+
+#[derive(Clone, Default)]
+pub struct Rope {
+  chunks: LinkedList<String>,
+  /// In chars.
+  len: usize,
+}
+
+impl Rope {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Joins `lines` back into text, with a `'\n'` after each one like in a
+  /// file, so [`into_lines`](Self::into_lines) gives the same lines back.
+  pub fn from_lines(lines: LinkedList<String>) -> Self {
+    let mut rope = Rope::new();
+    let mut cursor = rope.cursor_mut();
+
+    for line in lines {
+      cursor.insert_str(&line);
+      cursor.insert_str("\n");
+    }
+
+    rope
+  }
+
+  pub fn into_lines(self) -> LinkedList<String> {
+    self.lines().collect()
+  }
+
+  /// Length in chars.
+  pub fn len(&self) -> usize {
+    self.len
+  }
+
+  pub fn len_bytes(&self) -> usize {
+    self.chunks.iter().map(String::len).sum()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.len == 0
+  }
+
+  pub fn chunks(&self) -> production_linked_list::Iter<'_, String> {
+    self.chunks.iter()
+  }
+
+  pub fn chars(&self) -> Chars<'_> {
+    Chars {
+      chunks: self.chunks.iter(),
+      current: "".chars(),
+    }
+  }
+
+  /// Splits on `'\n'` and drops a `'\r'` before it, like `str::lines`.
+  pub fn lines(&self) -> Lines<'_> {
+    Lines {
+      chars: self.chars(),
+    }
+  }
+
+  /// Inserts `text` so it starts at char `at`. Panics if `at` is past the
+  /// end.
+  pub fn insert(&mut self, at: usize, text: &str) {
+    let mut cursor = self.cursor_mut();
+    cursor.seek(at);
+    cursor.insert_str(text);
+  }
+
+  /// Removes the chars in `range`. Panics if it's out of bounds, like
+  /// slicing.
+  pub fn delete<R: RangeBounds<usize>>(&mut self, range: R) {
+    let (start, end) = bounds(range, self.len);
+
+    let mut cursor = self.cursor_mut();
+    cursor.seek(start);
+    cursor.delete_next(end - start);
+  }
+
+  /// Copies out the chars in `range`, skipping whole chunks on the way.
+  pub fn slice<R: RangeBounds<usize>>(&self, range: R) -> String {
+    let (start, end) = bounds(range, self.len);
+    let mut output = String::new();
+    let mut position = 0;
+
+    for chunk in self.chunks.iter() {
+      if position >= end {
+        break;
+      }
+
+      let len = chunk.chars().count();
+      if position + len > start {
+        let skip = start.saturating_sub(position);
+        let take = end.min(position + len) - start.max(position);
+        output.extend(chunk.chars().skip(skip).take(take));
+      }
+      position += len;
+    }
+
+    output
+  }
+
+  /// A cursor at the start of the text.
+  pub fn cursor_mut(&mut self) -> CursorMut<'_> {
+    let mut chunks = self.chunks.cursor_mut();
+    // Onto the front chunk, or stays on the ghost if there isn't one.
+    chunks.move_next();
+
+    CursorMut {
+      chunks,
+      len: &mut self.len,
+      position: 0,
+      offset: 0,
+    }
+  }
+
+  #[cfg(test)]
+  fn check_invariants(&self) {
+    let mut chars = 0;
+    let mut prev: Option<&String> = None;
+
+    for chunk in self.chunks.iter() {
+      assert!(!chunk.is_empty(), "empty chunk");
+      assert!(
+        chunk.len() <= MAX_CHUNK_LEN,
+        "chunk of {} bytes",
+        chunk.len()
+      );
+
+      if let Some(prev) = prev {
+        let small = prev.len() < MIN_CHUNK_LEN || chunk.len() < MIN_CHUNK_LEN;
+        assert!(
+          !small || prev.len() + chunk.len() > MAX_CHUNK_LEN,
+          "chunks of {} and {} bytes should have been merged",
+          prev.len(),
+          chunk.len()
+        );
+      }
+
+      chars += chunk.chars().count();
+      prev = Some(chunk);
+    }
+
+    assert_eq!(self.len, chars);
+  }
+}
+
+impl From<&str> for Rope {
+  fn from(text: &str) -> Self {
+    Self {
+      chunks: chunks_of(text),
+      len: text.chars().count(),
+    }
+  }
+}
+
+impl From<String> for Rope {
+  fn from(text: String) -> Self {
+    Self::from(text.as_str())
+  }
+}
+
+impl fmt::Display for Rope {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    self.chunks.iter().try_for_each(|chunk| f.write_str(chunk))
+  }
+}
+
+impl fmt::Debug for Rope {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "Rope({:?})", self.to_string())
+  }
+}
+
+/// Equal if the text is, however it's chunked.
+impl PartialEq for Rope {
+  fn eq(&self, other: &Self) -> bool {
+    self.len == other.len && self.chars().eq(other.chars())
+  }
+}
+
+impl Eq for Rope {}
+
+/// Start and end of `range` as chars into a text of `len` chars.
+fn bounds<R: RangeBounds<usize>>(range: R, len: usize) -> (usize, usize) {
+  let start = match range.start_bound() {
+    Bound::Included(&start) => start,
+    Bound::Excluded(&start) => start + 1,
+    Bound::Unbounded => 0,
+  };
+  let end = match range.end_bound() {
+    Bound::Included(&end) => end + 1,
+    Bound::Excluded(&end) => end,
+    Bound::Unbounded => len,
+  };
+
+  assert!(
+    start <= end,
+    "range starts at {} but ends at {}",
+    start,
+    end
+  );
+  assert!(
+    end <= len,
+    "range end {} out of range for length {}",
+    end,
+    len
+  );
+
+  (start, end)
+}
+
+/// Cuts `text` into as few chunks as fit, all about the same size so none
+/// of them start out small.
+fn chunks_of(text: &str) -> LinkedList<String> {
+  let mut chunks = LinkedList::new();
+  let mut rest = text;
+
+  while rest.len() > MAX_CHUNK_LEN {
+    let pieces = rest.len().div_ceil(MAX_CHUNK_LEN);
+    let mut cut = rest.len().div_ceil(pieces);
+    while !rest.is_char_boundary(cut) {
+      cut -= 1;
+    }
+
+    let (chunk, tail) = rest.split_at(cut);
+    chunks.push_back(chunk.to_string());
+    rest = tail;
+  }
+
+  if !rest.is_empty() {
+    chunks.push_back(rest.to_string());
+  }
+
+  chunks
+}
+
+/// Byte index of the char `chars` chars into `chunk`, or its length.
+fn byte_at(chunk: &str, chars: usize) -> usize {
+  chunk
+    .char_indices()
+    .nth(chars)
+    .map_or(chunk.len(), |(index, _)| index)
+}
+
+pub struct Chars<'a> {
+  chunks: production_linked_list::Iter<'a, String>,
+  current: str::Chars<'a>,
+}
+
+impl Iterator for Chars<'_> {
+  type Item = char;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    loop {
+      if let Some(c) = self.current.next() {
+        return Some(c);
+      }
+      self.current = self.chunks.next()?.chars();
+    }
+  }
+}
+
+pub struct Lines<'a> {
+  chars: Chars<'a>,
+}
+
+impl Iterator for Lines<'_> {
+  type Item = String;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    let mut line = String::new();
+    let mut any = false;
+
+    for c in self.chars.by_ref() {
+      any = true;
+
+      if c == '\n' {
+        if line.ends_with('\r') {
+          line.pop();
+        }
+        return Some(line);
+      }
+
+      line.push(c);
+    }
+
+    any.then_some(line)
+  }
+}
+
+/// An editing position between two chars, from 0 to `len()`, which edits
+/// happen at the way they would at a text cursor: inserting leaves it after
+/// the new text, and deleting takes text from in front of or behind it.
+///
+/// It sits on a chunk and a char offset into it, which is only ever on the
+/// ghost when the rope is empty.
+pub struct CursorMut<'a> {
+  chunks: production_linked_list::CursorMut<'a, String>,
+  len: &'a mut usize,
+  position: usize,
+  /// Chars into the current chunk, from 0 to its length.
+  offset: usize,
+}
+
+impl CursorMut<'_> {
+  pub fn position(&self) -> usize {
+    self.position
+  }
+
+  /// Length of the whole rope, in chars.
+  pub fn len(&self) -> usize {
+    *self.len
+  }
+
+  pub fn is_empty(&self) -> bool {
+    *self.len == 0
+  }
+
+  /// Moves to char `position`, from wherever the cursor is now. Panics if
+  /// it's past the end.
+  pub fn seek(&mut self, position: usize) {
+    assert!(
+      position <= *self.len,
+      "position {} out of range for length {}",
+      position,
+      self.len
+    );
+
+    if position > self.position {
+      self.move_right(position - self.position);
+    } else {
+      self.move_left(self.position - position);
+    }
+  }
+
+  /// Steps over the next char, returning it. Does nothing at the end.
+  pub fn move_next_char(&mut self) -> Option<char> {
+    let c = self.peek_next_char()?;
+    self.move_right(1);
+    Some(c)
+  }
+
+  /// Steps back over the previous char, returning it. Does nothing at the
+  /// start.
+  pub fn move_prev_char(&mut self) -> Option<char> {
+    let c = self.peek_prev_char()?;
+    self.move_left(1);
+    Some(c)
+  }
+
+  pub fn peek_next_char(&mut self) -> Option<char> {
+    let offset = self.offset;
+    let chunk = self.chunks.current()?;
+
+    match chunk.chars().nth(offset) {
+      Some(c) => Some(c),
+      None => self.chunks.peek_next()?.chars().next(),
+    }
+  }
+
+  pub fn peek_prev_char(&mut self) -> Option<char> {
+    match self.offset {
+      0 => self.chunks.peek_prev()?.chars().next_back(),
+      offset => self.chunks.current()?.chars().nth(offset - 1),
+    }
+  }
+
+  /// Inserts `text` at the cursor and moves past it.
+  pub fn insert_str(&mut self, text: &str) {
+    if text.is_empty() {
+      return;
+    }
+
+    let added = text.chars().count();
+    *self.len += added;
+    self.position += added;
+
+    let offset = self.offset;
+    let Some(chunk) = self.chunks.current() else {
+      // The rope was empty, and on the ghost splicing appends.
+      self.chunks.splice_before(chunks_of(text));
+      self.chunks.move_prev();
+      self.offset = self.chunk_len();
+      return;
+    };
+
+    let at = byte_at(chunk, offset);
+
+    if chunk.len() + text.len() <= MAX_CHUNK_LEN {
+      chunk.insert_str(at, text);
+      self.offset += added;
+      return;
+    }
+
+    let mut joined = String::with_capacity(chunk.len() + text.len());
+    joined.push_str(&chunk[..at]);
+    joined.push_str(text);
+    joined.push_str(&chunk[at..]);
+    let mut offset = offset + added;
+
+    // A small neighbour only stayed unmerged because this chunk was too big
+    // for it, which the pieces it's cut into might not be, so take it along.
+    if self
+      .chunks
+      .peek_prev()
+      .is_some_and(|prev| prev.len() < MIN_CHUNK_LEN)
+    {
+      self.chunks.move_prev();
+      let prev = self.remove_current();
+      offset += prev.chars().count();
+      joined.insert_str(0, &prev);
+    }
+    if self
+      .chunks
+      .peek_next()
+      .is_some_and(|next| next.len() < MIN_CHUNK_LEN)
+    {
+      self.chunks.move_next();
+      joined.push_str(&self.remove_current());
+      self.chunks.move_prev();
+    }
+
+    self.replace_current(&joined, offset);
+  }
+
+  pub fn insert_char(&mut self, c: char) {
+    self.insert_str(c.encode_utf8(&mut [0; 4]));
+  }
+
+  /// Deletes up to `n` chars after the cursor, like the delete key, and
+  /// returns them.
+  pub fn delete_next(&mut self, n: usize) -> String {
+    let mut n = n.min(*self.len - self.position);
+    let mut removed = String::new();
+
+    while n > 0 {
+      let offset = self.offset;
+      // There's text after the cursor, so it's on a chunk.
+      let chunk = self.chunks.current().unwrap();
+      let len = chunk.chars().count();
+
+      if offset == len {
+        // Done with this chunk, which may have got small on the way.
+        self.rebalance();
+        if self.offset == self.chunk_len() {
+          self.chunks.move_next();
+          self.offset = 0;
+        }
+        continue;
+      }
+
+      let step = (len - offset).min(n);
+      let from = byte_at(chunk, offset);
+      let to = from + byte_at(&chunk[from..], step);
+      removed.extend(chunk.drain(from..to));
+      n -= step;
+      *self.len -= step;
+
+      if chunk.is_empty() {
+        self.remove_current();
+
+        if self.chunks.current().is_none() {
+          // That was the back, so end up at the end of the one before.
+          self.chunks.move_prev();
+          self.offset = self.chunk_len();
+        }
+      }
+    }
+
+    self.rebalance();
+    removed
+  }
+
+  /// Deletes up to `n` chars before the cursor, like backspace, and returns
+  /// them.
+  pub fn delete_prev(&mut self, n: usize) -> String {
+    let n = n.min(self.position);
+    self.move_left(n);
+    self.delete_next(n)
+  }
+
+  fn move_right(&mut self, mut n: usize) {
+    while n > 0 {
+      let len = self.chunk_len();
+
+      if self.offset == len {
+        if self.chunks.peek_next().is_none() {
+          break;
+        }
+        self.chunks.move_next();
+        self.offset = 0;
+        continue;
+      }
+
+      let step = (len - self.offset).min(n);
+      self.offset += step;
+      self.position += step;
+      n -= step;
+    }
+  }
+
+  fn move_left(&mut self, mut n: usize) {
+    while n > 0 {
+      if self.offset == 0 {
+        if self.chunks.peek_prev().is_none() {
+          break;
+        }
+        self.chunks.move_prev();
+        self.offset = self.chunk_len();
+        continue;
+      }
+
+      let step = self.offset.min(n);
+      self.offset -= step;
+      self.position -= step;
+      n -= step;
+    }
+  }
+
+  /// Chars in the current chunk.
+  fn chunk_len(&mut self) -> usize {
+    self
+      .chunks
+      .current()
+      .map_or(0, |chunk| chunk.chars().count())
+  }
+
+  /// Takes the current chunk out of the list, leaving the cursor on the
+  /// one after it, or the ghost.
+  fn remove_current(&mut self) -> String {
+    // Cut off everything before, so the chunk is at the front and cutting
+    // in front of the next one leaves a list of just it.
+    let before = self.chunks.split_before();
+    self.chunks.move_next();
+    let mut removed = self.chunks.split_before();
+    self.chunks.splice_before(before);
+
+    removed.pop_front().unwrap()
+  }
+
+  /// Swaps the current chunk for `text` cut into chunks, and puts the
+  /// cursor `offset` chars into it.
+  fn replace_current(&mut self, text: &str, offset: usize) {
+    self.remove_current();
+
+    let pieces = chunks_of(text);
+    let count = pieces.len();
+    let (mut index, mut before) = (0, 0);
+    for piece in pieces.iter() {
+      let len = piece.chars().count();
+      if offset <= before + len {
+        break;
+      }
+      index += 1;
+      before += len;
+    }
+
+    self.chunks.splice_before(pieces);
+    for _ in index..count {
+      self.chunks.move_prev();
+    }
+    self.offset = offset - before;
+  }
+
+  /// Merges the current chunk with its neighbours if it or they have got
+  /// small and they'd fit together.
+  fn rebalance(&mut self) {
+    let Some(len) = self.chunks.current().map(|chunk| chunk.len()) else {
+      return;
+    };
+
+    if let Some(next) = self.chunks.peek_next() {
+      if (len < MIN_CHUNK_LEN || next.len() < MIN_CHUNK_LEN) && len + next.len() <= MAX_CHUNK_LEN {
+        self.chunks.move_next();
+        let next = self.remove_current();
+        self.chunks.move_prev();
+        self.chunks.current().unwrap().push_str(&next);
+      }
+    }
+
+    let len = self.chunks.current().unwrap().len();
+
+    if let Some(prev) = self.chunks.peek_prev() {
+      if (len < MIN_CHUNK_LEN || prev.len() < MIN_CHUNK_LEN) && len + prev.len() <= MAX_CHUNK_LEN {
+        let prev_len = prev.chars().count();
+        let current = self.remove_current();
+        self.chunks.move_prev();
+        self.chunks.current().unwrap().push_str(&current);
+        self.offset += prev_len;
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::rng::Rng;
+
+  /// Text long enough to need a few chunks, with some multi-byte chars.
+  fn long_text() -> String {
+    (0..600)
+      .map(|i| match i % 7 {
+        0 => "é".to_string(),
+        1 => "\n".to_string(),
+        2 => "🦀".to_string(),
+        _ => i.to_string(),
+      })
+      .collect()
+  }
+
+  #[test]
+  fn basics() {
+    let mut rope = Rope::new();
+    assert!(rope.is_empty());
+    assert_eq!("", rope.to_string());
+
+    rope.insert(0, "world");
+    rope.insert(0, "hello ");
+    rope.insert(11, "!");
+    assert_eq!("hello world!", rope.to_string());
+    assert_eq!(12, rope.len());
+
+    rope.delete(5..11);
+    assert_eq!("hello!", rope.to_string());
+    assert_eq!("ell", rope.slice(1..4));
+    assert_eq!("hello!", rope.slice(..));
+
+    rope.delete(..);
+    assert!(rope.is_empty());
+    assert_eq!(0, rope.chunks().len());
+    rope.check_invariants();
+  }
+
+  #[test]
+  fn counts_chars_not_bytes() {
+    let mut rope = Rope::from("añb🦀c");
+
+    assert_eq!(5, rope.len());
+    assert_eq!(9, rope.len_bytes());
+    assert_eq!("ñb🦀", rope.slice(1..4));
+
+    rope.insert(4, "é");
+    rope.delete(1..2);
+    assert_eq!("ab🦀éc", rope.to_string());
+  }
+
+  #[test]
+  #[should_panic(expected = "out of range")]
+  fn insert_past_the_end() {
+    Rope::from("abc").insert(4, "d");
+  }
+
+  #[test]
+  fn splits_and_merges_chunks() {
+    let text = long_text();
+    let mut rope = Rope::from(text.as_str());
+    assert!(rope.chunks().len() > 2);
+    rope.check_invariants();
+
+    // Grow one chunk past the limit.
+    let middle = rope.len() / 2;
+    rope.insert(middle, &"x".repeat(MAX_CHUNK_LEN));
+    rope.check_invariants();
+
+    // Then delete most of it again, across chunk boundaries.
+    rope.delete(middle - 10..middle + MAX_CHUNK_LEN + 10);
+    rope.check_invariants();
+
+    let mut expected: Vec<char> = text.chars().collect();
+    expected.drain(middle - 10..middle + 10);
+    assert_eq!(expected.into_iter().collect::<String>(), rope.to_string());
+
+    let chunks = rope.chunks().len();
+    rope.delete(..rope.len() - 1);
+    rope.check_invariants();
+    assert!(rope.chunks().len() < chunks);
+    assert_eq!(1, rope.len());
+  }
+
+  #[test]
+  fn lines() {
+    let rope = Rope::from("one\r\ntwo\n\nfour");
+    assert_eq!(
+      vec!["one", "two", "", "four"],
+      rope.lines().collect::<Vec<_>>()
+    );
+
+    assert_eq!(0, Rope::new().lines().count());
+    assert_eq!(vec![""], Rope::from("\n").lines().collect::<Vec<_>>());
+
+    let lines: LinkedList<String> = ["fn main() {", "", "}", ""]
+      .into_iter()
+      .map(String::from)
+      .collect();
+    let rope = Rope::from_lines(lines.clone());
+    assert_eq!("fn main() {\n\n}\n\n", rope.to_string());
+    assert_eq!(lines, rope.into_lines());
+  }
+
+  #[test]
+  fn cursor_editing() {
+    let mut rope = Rope::new();
+    let mut cursor = rope.cursor_mut();
+
+    for c in "helo".chars() {
+      cursor.insert_char(c);
+    }
+    assert_eq!(4, cursor.position());
+
+    assert_eq!(Some('o'), cursor.move_prev_char());
+    cursor.insert_char('l');
+    assert_eq!(Some('o'), cursor.peek_next_char());
+    assert_eq!(Some('l'), cursor.peek_prev_char());
+
+    cursor.seek(0);
+    assert_eq!(None, cursor.move_prev_char());
+    assert_eq!("", cursor.delete_prev(3));
+    assert_eq!("he", cursor.delete_next(2));
+    cursor.insert_str("je");
+
+    cursor.seek(cursor.len());
+    assert_eq!(None, cursor.peek_next_char());
+    assert_eq!("llo", cursor.delete_prev(3));
+    assert_eq!("", cursor.delete_next(1));
+    assert_eq!(2, cursor.position());
+
+    assert_eq!("je", rope.to_string());
+  }
+
+  #[test]
+  fn cursor_walks_across_chunks() {
+    let text = long_text();
+    let mut rope = Rope::from(text.as_str());
+    let mut cursor = rope.cursor_mut();
+
+    let mut forward = String::new();
+    while let Some(c) = cursor.move_next_char() {
+      forward.push(c);
+    }
+    assert_eq!(text, forward);
+
+    let mut backward = String::new();
+    while let Some(c) = cursor.move_prev_char() {
+      backward.push(c);
+    }
+    assert_eq!(text.chars().rev().collect::<String>(), backward);
+  }
+
+  #[test]
+  fn matches_a_string() {
+    let mut rng = Rng(0x2545f4914f6cdd1d);
+    let mut next = |bound: usize| rng.below(bound);
+
+    let pieces = [
+      "a",
+      "bc",
+      "é",
+      "🦀🦀",
+      "\n",
+      "long text to push chunks over",
+    ];
+    let mut rope = Rope::new();
+    let mut model: Vec<char> = Vec::new();
+
+    for _ in 0..2_000 {
+      let mut cursor = rope.cursor_mut();
+      cursor.seek(next(model.len() + 1));
+      let at = cursor.position();
+
+      match next(4) {
+        0 | 1 => {
+          let piece = pieces[next(pieces.len())].repeat(next(20) + 1);
+          cursor.insert_str(&piece);
+          model.splice(at..at, piece.chars());
+          assert_eq!(at + piece.chars().count(), cursor.position());
+        }
+        2 => {
+          let n = next(300);
+          let removed = cursor.delete_next(n);
+          let end = (at + n).min(model.len());
+          assert_eq!(model.drain(at..end).collect::<String>(), removed);
+          assert_eq!(at, cursor.position());
+        }
+        _ => {
+          let n = next(300);
+          let removed = cursor.delete_prev(n);
+          let start = at.saturating_sub(n);
+          assert_eq!(model.drain(start..at).collect::<String>(), removed);
+          assert_eq!(start, cursor.position());
+        }
+      }
+
+      assert_eq!(
+        model.get(cursor.position()).copied(),
+        cursor.peek_next_char()
+      );
+
+      rope.check_invariants();
+      assert_eq!(model.iter().collect::<String>(), rope.to_string());
+    }
+  }
+}
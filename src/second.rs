@@ -1,3 +1,12 @@
+use std::{fmt, mem, str::FromStr};
+
+use crate::{
+  dot::{Edge, Graph, ToDot},
+  list_fmt::{self, ParseListError},
+  memory::MemoryUsage,
+  traits::Stack,
+};
+
 /// Because List if a struct with a single field, its size is the same as the field.
 pub struct List<T> {
   head: Link<T>,
@@ -10,6 +19,12 @@ struct Node<T> {
   next: Link<T>,
 }
 
+impl<T> Default for List<T> {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
 impl<T> List<T> {
   pub fn new() -> Self {
     Self { head: None }
@@ -75,6 +90,66 @@ impl<T> Drop for List<T> {
   }
 }
 
+impl<T: fmt::Display> fmt::Display for List<T> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    list_fmt::write_list(f, self.iter(), list_fmt::DOUBLY_LINKED)
+  }
+}
+
+/// The first element in the text ends up on top of the stack.
+impl<T: FromStr> FromStr for List<T> {
+  type Err = ParseListError<T::Err>;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    let mut elems = Vec::new();
+    list_fmt::parse_list(s, |elem| elems.push(elem))?;
+
+    let mut list = List::new();
+    for elem in elems.into_iter().rev() {
+      list.push(elem);
+    }
+    Ok(list)
+  }
+}
+
+impl<T: fmt::Display> ToDot for List<T> {
+  fn add_to_graph(&self, graph: &mut Graph, name: &str) {
+    let mut current = self.head.as_deref();
+
+    graph.marker(name, "head", current.map(|node| node as *const Node<T>));
+
+    while let Some(node) = current {
+      graph.node(node, &node.elem);
+
+      if let Some(next) = node.next.as_deref() {
+        graph.edge(node, next, Edge::Next);
+      }
+
+      current = node.next.as_deref();
+    }
+  }
+}
+
+impl<T> MemoryUsage for List<T> {
+  fn node_count(&self) -> usize {
+    self.iter().count()
+  }
+
+  fn heap_bytes(&self) -> usize {
+    self.node_count() * mem::size_of::<Node<T>>()
+  }
+}
+
+impl<T> Stack<T> for List<T> {
+  fn push(&mut self, elem: T) {
+    List::push(self, elem);
+  }
+
+  fn pop(&mut self) -> Option<T> {
+    List::pop(self)
+  }
+}
+
 pub struct IntoIter<T>(List<T>);
 
 impl<T> Iterator for IntoIter<T> {
@@ -189,6 +264,32 @@ mod tests {
     assert_eq!(None, list.pop());
   }
 
+  #[test]
+  fn display_and_from_str() {
+    let mut list = List::new();
+
+    assert_eq!("[]", list.to_string());
+    assert_eq!("[]", format!("{:#}", list));
+
+    list.push(3);
+    list.push(2);
+    list.push(1);
+
+    assert_eq!("[1, 2, 3]", list.to_string());
+    assert_eq!("1 <-> 2 <-> 3", format!("{:#}", list));
+
+    let parsed: List<i32> = "[1, 2, 3]".parse().unwrap();
+    assert_eq!(vec![&1, &2, &3], parsed.iter().collect::<Vec<_>>());
+
+    let parsed: List<i32> = "1 -> 2 -> 3".parse().unwrap();
+    assert_eq!(Some(&1), parsed.peek());
+
+    assert!(matches!(
+      "[1, a]".parse::<List<i32>>(),
+      Err(ParseListError::Element { index: 1, .. })
+    ));
+  }
+
   #[test]
   fn peek() {
     let mut list = List::new();
@@ -224,4 +325,6 @@ mod tests {
 
     assert_eq!(Some(&mut 10), list.peek_mut());
   }
+
+  crate::traits::conformance::stack_conformance!(List<i32>);
 }
@@ -0,0 +1,464 @@
+//! A list that moves what gets looked up towards the front, so the things
+//! looked up most are found after only a step or two.
+//!
+//! For small tables where a few keys get most of the lookups, that's often
+//! quicker than hashing. How far an element moves is up to a [`Policy`], and
+//! moving it is just relinking its node, nothing gets copied.
+//!
+//! ```
+//! use learn_rust_with_entirely_too_many_linked_lists::self_organizing::{
+//!   SelfOrganizingList, Transpose,
+//! };
+//!
+//! let mut symbols = SelfOrganizingList::with_policy(Transpose);
+//! symbols.extend(["fn", "let", "if", "match"]);
+//!
+//! assert_eq!(Some(&mut "match"), symbols.access(&"match"));
+//! assert_eq!(
+//!   vec!["fn", "let", "match", "if"],
+//!   symbols.iter().copied().collect::<Vec<_>>()
+//! );
+//! assert_eq!(Some(4.0), symbols.stats().mean_hit_position());
+//! ```
+
+use std::fmt::{self, Debug};
+
+use crate::production_linked_list::LinkedList;
+
+/// Decides where an element that was just looked up goes.
+pub trait Policy {
+  /// `index` is where the element was found and `hits` how many times it's
+  /// been found, this time included. `before` gives the hit counts of the
+  /// elements in front of it, nearest first. Anything past `index` is
+  /// treated as `index`, leaving it where it is.
+  fn new_index<I>(&mut self, index: usize, hits: u64, before: I) -> usize
+  where
+    I: Iterator<Item = u64>;
+}
+
+/// Straight to the front. Adapts quickly when what's popular changes.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MoveToFront;
+
+impl Policy for MoveToFront {
+  fn new_index<I>(&mut self, _index: usize, _hits: u64, _before: I) -> usize
+  where
+    I: Iterator<Item = u64>,
+  {
+    0
+  }
+}
+
+/// One step forward, swapping with the element in front. Slow to adapt,
+/// but one unlucky lookup can't push everything else back.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Transpose;
+
+impl Policy for Transpose {
+  fn new_index<I>(&mut self, index: usize, _hits: u64, _before: I) -> usize
+  where
+    I: Iterator<Item = u64>,
+  {
+    index.saturating_sub(1)
+  }
+}
+
+/// In front of every element found fewer times, which keeps the list sorted
+/// by hit count.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FrequencyCount;
+
+impl Policy for FrequencyCount {
+  fn new_index<I>(&mut self, index: usize, hits: u64, before: I) -> usize
+  where
+    I: Iterator<Item = u64>,
+  {
+    index - before.take_while(|&other| other < hits).count()
+  }
+}
+
+/// Where lookups found what they were looking for.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Stats {
+  misses: u64,
+  /// How many hits there were at each index.
+  hit_positions: Vec<u64>,
+}
+
+impl Stats {
+  pub fn hits(&self) -> u64 {
+    self.hit_positions.iter().sum()
+  }
+
+  pub fn misses(&self) -> u64 {
+    self.misses
+  }
+
+  pub fn lookups(&self) -> u64 {
+    self.hits() + self.misses
+  }
+
+  /// Number of hits at each index, before the element got moved.
+  pub fn hit_positions(&self) -> &[u64] {
+    &self.hit_positions
+  }
+
+  /// Average index hits were at, counting from 1 so it's how many elements
+  /// each hit looked at.
+  pub fn mean_hit_position(&self) -> Option<f64> {
+    let hits = self.hits();
+    let total: u64 = (1..)
+      .zip(self.hit_positions.iter())
+      .map(|(position, count)| position * count)
+      .sum();
+
+    (hits > 0).then(|| total as f64 / hits as f64)
+  }
+
+  fn record_hit(&mut self, index: usize) {
+    if self.hit_positions.len() <= index {
+      self.hit_positions.resize(index + 1, 0);
+    }
+    self.hit_positions[index] += 1;
+  }
+}
+
+struct Entry<T> {
+  elem: T,
+  hits: u64,
+}
+
+pub struct SelfOrganizingList<T, P = MoveToFront> {
+  entries: LinkedList<Entry<T>>,
+  policy: P,
+  stats: Stats,
+}
+
+impl<T> SelfOrganizingList<T> {
+  pub fn new() -> Self {
+    Self::with_policy(MoveToFront)
+  }
+}
+
+impl<T, P: Policy> SelfOrganizingList<T, P> {
+  pub fn with_policy(policy: P) -> Self {
+    Self {
+      entries: LinkedList::new(),
+      policy,
+      stats: Stats::default(),
+    }
+  }
+
+  pub fn policy(&self) -> &P {
+    &self.policy
+  }
+
+  pub fn stats(&self) -> &Stats {
+    &self.stats
+  }
+
+  pub fn reset_stats(&mut self) {
+    self.stats = Stats::default();
+  }
+
+  pub fn len(&self) -> usize {
+    self.entries.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.entries.is_empty()
+  }
+
+  /// Adds `elem` at the back, where new elements start out.
+  pub fn push(&mut self, elem: T) {
+    self.entries.push_back(Entry { elem, hits: 0 });
+  }
+
+  /// Finds the first element matching `pred` and moves it as the policy
+  /// says. Every call counts towards the stats.
+  pub fn find<F>(&mut self, mut pred: F) -> Option<&mut T>
+  where
+    F: FnMut(&T) -> bool,
+  {
+    // One walk, remembering the hit counts on the way for the policy.
+    let mut cursor = self.entries.cursor_mut();
+    let mut before = Vec::new();
+    let hits = loop {
+      cursor.move_next();
+      let Some(entry) = cursor.current() else {
+        self.stats.misses += 1;
+        return None;
+      };
+      if pred(&entry.elem) {
+        entry.hits += 1;
+        break entry.hits;
+      }
+      before.push(entry.hits);
+    };
+
+    let index = before.len();
+    self.stats.record_hit(index);
+    let to = self
+      .policy
+      .new_index(index, hits, before.iter().rev().copied())
+      .min(index);
+
+    if to < index {
+      // Cut off everything in front of it, and from that the elements it
+      // jumps over, which then go back in after it.
+      let mut skipped = cursor.split_before();
+      let mut skipped_cursor = skipped.cursor_mut();
+      for _ in to..index {
+        skipped_cursor.move_prev();
+      }
+      let kept = skipped_cursor.split_before();
+
+      cursor.splice_before(kept);
+      cursor.move_next();
+      cursor.splice_before(skipped);
+      for _ in to..=index {
+        cursor.move_prev();
+      }
+    }
+
+    cursor.into_current().map(|entry| &mut entry.elem)
+  }
+
+  /// Finds an element equal to `value`.
+  pub fn access(&mut self, value: &T) -> Option<&mut T>
+  where
+    T: PartialEq,
+  {
+    self.find(|elem| elem == value)
+  }
+
+  /// Removes the first element matching `pred`, without counting it as a
+  /// lookup.
+  pub fn remove<F>(&mut self, mut pred: F) -> Option<T>
+  where
+    F: FnMut(&T) -> bool,
+  {
+    let index = self.entries.iter().position(|entry| pred(&entry.elem))?;
+    self.take(index).pop_front().map(|entry| entry.elem)
+  }
+
+  /// In the current order, without moving anything.
+  pub fn iter(&self) -> impl DoubleEndedIterator<Item = &T> + ExactSizeIterator {
+    self.entries.iter().map(|entry| &entry.elem)
+  }
+
+  /// Unlinks the node at `index`, handing it back as a list of one.
+  fn take(&mut self, index: usize) -> LinkedList<Entry<T>> {
+    let mut cursor = self.entries.cursor_mut();
+    for _ in 0..=index {
+      cursor.move_next();
+    }
+
+    // With everything before it cut off it's at the front, so cutting in
+    // front of the next node leaves just it.
+    let before = cursor.split_before();
+    cursor.move_next();
+    let node = cursor.split_before();
+    cursor.splice_before(before);
+
+    node
+  }
+}
+
+impl<T> Default for SelfOrganizingList<T> {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl<T, P: Policy> Extend<T> for SelfOrganizingList<T, P> {
+  fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+    iter.into_iter().for_each(|elem| self.push(elem));
+  }
+}
+
+impl<T> FromIterator<T> for SelfOrganizingList<T> {
+  fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+    let mut list = Self::new();
+    list.extend(iter);
+    list
+  }
+}
+
+impl<T: Debug, P> Debug for SelfOrganizingList<T, P> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_list()
+      .entries(self.entries.iter().map(|entry| &entry.elem))
+      .finish()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn order<P: Policy>(list: &SelfOrganizingList<char, P>) -> String {
+    list.iter().collect()
+  }
+
+  #[test]
+  fn move_to_front() {
+    let mut list: SelfOrganizingList<char> = "abcde".chars().collect();
+
+    assert_eq!(Some(&mut 'd'), list.access(&'d'));
+    assert_eq!("dabce", order(&list));
+    assert_eq!(Some(&mut 'e'), list.access(&'e'));
+    assert_eq!(Some(&mut 'e'), list.access(&'e'));
+    assert_eq!("edabc", order(&list));
+
+    assert_eq!(None, list.access(&'z'));
+    assert_eq!(5, list.len());
+  }
+
+  #[test]
+  fn transpose() {
+    let mut list = SelfOrganizingList::with_policy(Transpose);
+    list.extend("abcde".chars());
+
+    list.access(&'d');
+    assert_eq!("abdce", order(&list));
+    list.access(&'d');
+    list.access(&'d');
+    list.access(&'d');
+    assert_eq!("dabce", order(&list));
+    list.access(&'e');
+    assert_eq!("dabec", order(&list));
+  }
+
+  #[test]
+  fn frequency_count() {
+    let mut list = SelfOrganizingList::with_policy(FrequencyCount);
+    list.extend("abcd".chars());
+
+    list.access(&'c');
+    assert_eq!("cabd", order(&list));
+    list.access(&'d');
+    // Only ahead of the ones found fewer times.
+    assert_eq!("cdab", order(&list));
+    list.access(&'d');
+    assert_eq!("dcab", order(&list));
+    list.access(&'b');
+    list.access(&'b');
+    list.access(&'b');
+    assert_eq!("bdca", order(&list));
+  }
+
+  #[test]
+  fn find_can_edit_in_place() {
+    let mut list: SelfOrganizingList<(&str, u32)> =
+      [("x", 1), ("y", 2), ("z", 3)].into_iter().collect();
+
+    *list
+      .find(|&(name, _)| name == "z")
+      .map(|(_, value)| value)
+      .unwrap() += 10;
+
+    assert_eq!(
+      vec![("z", 13), ("x", 1), ("y", 2)],
+      list.iter().copied().collect::<Vec<_>>()
+    );
+  }
+
+  #[test]
+  fn moves_from_every_position() {
+    /// Always sends the element to the same index.
+    struct To(usize);
+
+    impl Policy for To {
+      fn new_index<I>(&mut self, _index: usize, _hits: u64, _before: I) -> usize
+      where
+        I: Iterator<Item = u64>,
+      {
+        self.0
+      }
+    }
+
+    for index in 0..5usize {
+      for to in [0, index.saturating_sub(1)] {
+        let mut list = SelfOrganizingList::with_policy(To(to));
+        list.extend("abcde".chars());
+
+        let found = "abcde".chars().nth(index).unwrap();
+        let mut expected: Vec<char> = "abcde".chars().collect();
+        expected.remove(index);
+        expected.insert(to, found);
+
+        assert_eq!(Some(found), list.access(&found).copied());
+        assert_eq!(expected.iter().collect::<String>(), order(&list));
+        list.entries.check_invariants();
+      }
+    }
+  }
+
+  #[test]
+  fn remove() {
+    let mut list: SelfOrganizingList<char> = "abc".chars().collect();
+
+    assert_eq!(Some('b'), list.remove(|&c| c == 'b'));
+    assert_eq!(None, list.remove(|&c| c == 'b'));
+    assert_eq!(Some('c'), list.remove(|&c| c == 'c'));
+    assert_eq!(Some('a'), list.remove(|&c| c == 'a'));
+    assert!(list.is_empty());
+    assert_eq!(0, list.stats().lookups());
+  }
+
+  #[test]
+  fn stats() {
+    let mut list: SelfOrganizingList<char> = "abc".chars().collect();
+    assert_eq!(None, list.stats().mean_hit_position());
+
+    list.access(&'c');
+    list.access(&'c');
+    list.access(&'b');
+    list.access(&'x');
+
+    let stats = list.stats();
+    assert_eq!((3, 1, 4), (stats.hits(), stats.misses(), stats.lookups()));
+    assert_eq!(&[1, 0, 2], stats.hit_positions());
+    assert_eq!(Some(7.0 / 3.0), stats.mean_hit_position());
+
+    list.reset_stats();
+    assert_eq!(&Stats::default(), list.stats());
+  }
+
+  #[test]
+  fn skewed_lookups_end_up_near_the_front() {
+    // Key `k` of 100 is looked up about 1/k as often as key 1, cycling.
+    let lookups: Vec<u32> = (1..=100)
+      .flat_map(|key| std::iter::repeat_n(key, 100 / key as usize))
+      .collect();
+
+    fn mean<P: Policy>(policy: P, lookups: &[u32]) -> f64 {
+      let mut list = SelfOrganizingList::with_policy(policy);
+      // Rarest first, so a list that never moved anything does badly.
+      list.extend((1..=100).rev());
+
+      for _ in 0..5 {
+        for key in lookups {
+          list.access(key);
+        }
+      }
+
+      list.stats().mean_hit_position().unwrap()
+    }
+
+    let unsorted =
+      101.0 - lookups.iter().map(|&key| key as f64).sum::<f64>() / lookups.len() as f64;
+
+    let (move_to_front, transpose, frequency_count) = (
+      mean(MoveToFront, &lookups),
+      mean(Transpose, &lookups),
+      mean(FrequencyCount, &lookups),
+    );
+
+    // Transpose creeps forward a step at a time, so it gains the least.
+    assert!(move_to_front < unsorted / 3.0, "{}", move_to_front);
+    assert!(frequency_count < unsorted / 3.0, "{}", frequency_count);
+    assert!(transpose < unsorted / 1.5, "{}", transpose);
+  }
+}
@@ -1,3 +1,10 @@
+use std::fmt;
+
+use crate::{
+  dot::{Edge, Graph, ToDot},
+  memory::MemoryUsage,
+};
+
 pub struct List<'a, T> {
   pub data: T,
   pub prev: Option<&'a List<'a, T>>,
@@ -28,6 +35,36 @@ impl<'a, T> Iterator for Iter<'a, T> {
   }
 }
 
+/// Each list is a single frame, so the head is the list itself.
+impl<'a, T: fmt::Display> ToDot for List<'a, T> {
+  fn add_to_graph(&self, graph: &mut Graph, name: &str) {
+    let mut current = Some(self);
+
+    graph.marker(name, "head", Some(self as *const List<'a, T>));
+
+    while let Some(node) = current {
+      graph.node(node, &node.data);
+
+      if let Some(prev) = node.prev {
+        graph.edge(node, prev, Edge::Prev);
+      }
+
+      current = node.prev;
+    }
+  }
+}
+
+/// Every frame lives on the stack of the callback that pushed it.
+impl<'a, T> MemoryUsage for List<'a, T> {
+  fn node_count(&self) -> usize {
+    self.iter().count()
+  }
+
+  fn heap_bytes(&self) -> usize {
+    0
+  }
+}
+
 #[cfg(test)]
 mod tests {
   use super::*;
@@ -1,3 +1,13 @@
+use std::{fmt, mem, str::FromStr};
+
+use crate::{
+  dot::{Edge, Graph, ToDot},
+  drop_guard,
+  list_fmt::{self, ParseListError},
+  memory::MemoryUsage,
+  traits::Queue,
+};
+
 pub struct List<T> {
   head: *mut Node<T>,
   tail: *mut Node<T>,
@@ -97,7 +107,66 @@ impl<T> List<T> {
 
 impl<T> Drop for List<T> {
   fn drop(&mut self) {
-    while self.pop().is_some() {}
+    drop_guard::drop_all_with_guard(self, Self::pop);
+  }
+}
+
+impl<T: fmt::Display> fmt::Display for List<T> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    list_fmt::write_list(f, self.iter(), list_fmt::DOUBLY_LINKED)
+  }
+}
+
+impl<T: FromStr> FromStr for List<T> {
+  type Err = ParseListError<T::Err>;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    let mut list = List::new();
+    list_fmt::parse_list(s, |elem| list.push(elem))?;
+    Ok(list)
+  }
+}
+
+impl<T: fmt::Display> ToDot for List<T> {
+  fn add_to_graph(&self, graph: &mut Graph, name: &str) {
+    let non_null = |node: *mut Node<T>| Some(node as *const Node<T>).filter(|node| !node.is_null());
+
+    graph.marker(name, "head", non_null(self.head));
+    graph.marker(name, "tail", non_null(self.tail));
+
+    let mut current = self.head;
+
+    while !current.is_null() {
+      unsafe {
+        graph.node(current, &(*current).elem);
+
+        if !(*current).next.is_null() {
+          graph.edge(current, (*current).next, Edge::Next);
+        }
+
+        current = (*current).next;
+      }
+    }
+  }
+}
+
+impl<T> MemoryUsage for List<T> {
+  fn node_count(&self) -> usize {
+    self.iter().count()
+  }
+
+  fn heap_bytes(&self) -> usize {
+    self.node_count() * mem::size_of::<Node<T>>()
+  }
+}
+
+impl<T> Queue<T> for List<T> {
+  fn enqueue(&mut self, elem: T) {
+    List::push(self, elem);
+  }
+
+  fn dequeue(&mut self) -> Option<T> {
+    List::pop(self)
   }
 }
 
@@ -180,6 +249,23 @@ mod tests {
     assert_eq!(None, list.pop());
   }
 
+  #[test]
+  fn sixth_display_and_from_str() {
+    let mut list: List<i32> = "[1, 2, 3]".parse().unwrap();
+
+    assert_eq!("[1, 2, 3]", list.to_string());
+    assert_eq!("1 <-> 2 <-> 3", format!("{:#}", list));
+
+    assert_eq!(Some(1), list.pop());
+    list.push(4);
+    assert_eq!("[2, 3, 4]", list.to_string());
+
+    assert_eq!(
+      Err(ParseListError::Empty),
+      "".parse::<List<i32>>().map(|_| ())
+    );
+  }
+
   #[test]
   fn sixth_miri_food() {
     let mut list = List::new();
@@ -217,4 +303,6 @@ mod tests {
 
     // Drop it on the ground and let the dtor exercise itself
   }
+
+  crate::traits::conformance::queue_conformance!(List<i32>);
 }
@@ -0,0 +1,1251 @@
+//! An ordered map built from a stack of linked lists. The bottom one holds
+//! every entry in key order, doubly linked just like
+//! `production_linked_list`, and each list above it holds a random half of
+//! the entries of the one below, so a search can skip most of the way along
+//! on the upper lists before dropping down.
+//!
+//! Node heights come from a small built-in PRNG, seeded by
+//! [`SkipListMap::with_seed`], so a given seed and sequence of operations
+//! always builds the same shape.
+//!
+//! Because it's all links, cutting the map at a key ([`SkipListMap::split_off`])
+//! or gluing on a map whose keys all come after this one's
+//! ([`SkipListMap::append`]) only touches the nodes either side of the cut.
+
+use std::{
+  borrow::Borrow,
+  fmt::{self, Debug},
+  iter::FromIterator,
+  marker::PhantomData,
+  mem,
+  ops::{Bound, RangeBounds},
+  ptr::NonNull,
+};
+
+use crate::{drop_guard, rng::Rng};
+
+/// Nothing is ever taller than this, which is plenty for 2^32 entries.
+const MAX_HEIGHT: usize = 32;
+
+const DEFAULT_SEED: u64 = 0x5eed_5eed_5eed_5eed;
+
+pub struct SkipListMap<K, V> {
+  front: Link<K, V>,
+  back: Link<K, V>,
+  /// The head's links on every level above the bottom one.
+  express: Vec<Link<K, V>>,
+  len: usize,
+  rng: Rng,
+  _p: PhantomData<Box<Node<K, V>>>,
+}
+
+type Link<K, V> = Option<NonNull<Node<K, V>>>;
+
+/// The first and last node of a run of them.
+type Span<K, V> = (NonNull<Node<K, V>>, NonNull<Node<K, V>>);
+
+struct Node<K, V> {
+  /// The bottom level, linked both ways.
+  front: Link<K, V>,
+  back: Link<K, V>,
+  /// The next node on each level above the bottom one this node is on.
+  express: Box<[Link<K, V>]>,
+  key: K,
+  value: V,
+}
+
+unsafe fn key<'a, K, V>(node: NonNull<Node<K, V>>) -> &'a K {
+  &(*node.as_ptr()).key
+}
+
+impl<K, V> SkipListMap<K, V> {
+  pub fn new() -> Self {
+    Self::with_seed(DEFAULT_SEED)
+  }
+
+  pub fn with_seed(seed: u64) -> Self {
+    Self {
+      front: None,
+      back: None,
+      express: Vec::new(),
+      len: 0,
+      rng: Rng(seed),
+      _p: PhantomData,
+    }
+  }
+
+  pub fn len(&self) -> usize {
+    self.len
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.len == 0
+  }
+
+  /// How many levels the map has right now, bottom one included.
+  pub fn height(&self) -> usize {
+    self.express.len() + 1
+  }
+
+  pub fn clear(&mut self) {
+    while self.pop_first().is_some() {}
+  }
+
+  pub fn first(&self) -> Option<(&K, &V)> {
+    self
+      .front
+      .map(|node| unsafe { (&(*node.as_ptr()).key, &(*node.as_ptr()).value) })
+  }
+
+  pub fn last(&self) -> Option<(&K, &V)> {
+    self
+      .back
+      .map(|node| unsafe { (&(*node.as_ptr()).key, &(*node.as_ptr()).value) })
+  }
+
+  pub fn pop_first(&mut self) -> Option<(K, V)> {
+    let first = self.front?;
+    // Everything that points at the first node is the head.
+    let preds = [None; MAX_HEIGHT];
+    Some(unsafe { self.unlink(&preds[..self.height()], first) })
+  }
+
+  pub fn pop_last(&mut self) -> Option<(K, V)> {
+    let last = self.back?;
+    // The last node is the end of every level it's on, so the search just
+    // has to stop short of it rather than compare keys.
+    let preds = self.preds(|node| node != last);
+    Some(unsafe { self.unlink(&preds, last) })
+  }
+
+  pub fn iter(&self) -> Iter<'_, K, V> {
+    Iter {
+      front: self.front,
+      back: self.back,
+      len: self.len,
+      _p: PhantomData,
+    }
+  }
+
+  pub fn iter_mut(&mut self) -> IterMut<'_, K, V> {
+    IterMut {
+      front: self.front,
+      back: self.back,
+      len: self.len,
+      _p: PhantomData,
+    }
+  }
+
+  pub fn keys(&self) -> impl DoubleEndedIterator<Item = &K> + ExactSizeIterator + '_ {
+    self.iter().map(|(key, _)| key)
+  }
+
+  pub fn values(&self) -> impl DoubleEndedIterator<Item = &V> + ExactSizeIterator + '_ {
+    self.iter().map(|(_, value)| value)
+  }
+
+  /// The link leaving `at` on `level`, where `None` is the head.
+  fn link(&self, at: Link<K, V>, level: usize) -> Link<K, V> {
+    unsafe {
+      match at {
+        None if level == 0 => self.front,
+        None => self.express[level - 1],
+        Some(node) if level == 0 => (*node.as_ptr()).back,
+        Some(node) => (*node.as_ptr()).express[level - 1],
+      }
+    }
+  }
+
+  unsafe fn link_mut(&mut self, at: Link<K, V>, level: usize) -> &mut Link<K, V> {
+    match at {
+      None if level == 0 => &mut self.front,
+      None => &mut self.express[level - 1],
+      Some(node) if level == 0 => &mut (*node.as_ptr()).back,
+      Some(node) => &mut (*node.as_ptr()).express[level - 1],
+    }
+  }
+
+  /// Walks down from the top level, going right while `before` says the
+  /// next node comes before whatever's being looked for, and returns where
+  /// it stopped on each level. `before` has to hold for some prefix of the
+  /// map and then never again.
+  fn preds(&self, mut before: impl FnMut(NonNull<Node<K, V>>) -> bool) -> Vec<Link<K, V>> {
+    let mut preds = vec![None; self.height()];
+    let mut at = None;
+
+    for level in (0..self.height()).rev() {
+      while let Some(next) = self.link(at, level) {
+        if !before(next) {
+          break;
+        }
+        at = Some(next);
+      }
+      preds[level] = at;
+    }
+
+    preds
+  }
+
+  /// Just the bottom level of `preds`, without the `Vec`.
+  fn pred(&self, mut before: impl FnMut(NonNull<Node<K, V>>) -> bool) -> Link<K, V> {
+    let mut at = None;
+
+    for level in (0..self.height()).rev() {
+      while let Some(next) = self.link(at, level) {
+        if !before(next) {
+          break;
+        }
+        at = Some(next);
+      }
+    }
+
+    at
+  }
+
+  fn random_height(&mut self) -> usize {
+    // A coin flip per level, and never more than one level taller than the
+    // map already is, so a lucky streak can't add a pile of empty levels.
+    let height = 1 + self.rng.next_u64().trailing_ones() as usize;
+    height.min(self.height() + 1).min(MAX_HEIGHT)
+  }
+
+  /// Puts a new node straight after `preds`, which have to be where a
+  /// search for `key` stopped.
+  unsafe fn link_in(
+    &mut self,
+    mut preds: Vec<Link<K, V>>,
+    key: K,
+    value: V,
+  ) -> NonNull<Node<K, V>> {
+    let height = self.random_height();
+    while self.height() < height {
+      self.express.push(None);
+      preds.push(None);
+    }
+
+    let node = NonNull::new_unchecked(Box::into_raw(Box::new(Node {
+      front: preds[0],
+      back: None,
+      express: vec![None; height - 1].into_boxed_slice(),
+      key,
+      value,
+    })));
+
+    for (level, &pred) in preds.iter().enumerate().take(height) {
+      let next = self.link_mut(pred, level).replace(node);
+      *self.link_mut(Some(node), level) = next;
+    }
+
+    match (*node.as_ptr()).back {
+      Some(next) => (*next.as_ptr()).front = Some(node),
+      None => self.back = Some(node),
+    }
+
+    self.len += 1;
+    node
+  }
+
+  /// Takes `node` out, where `preds` are the nodes before it on each level.
+  unsafe fn unlink(&mut self, preds: &[Link<K, V>], node: NonNull<Node<K, V>>) -> (K, V) {
+    let boxed = Box::from_raw(node.as_ptr());
+
+    for (level, &pred) in preds.iter().enumerate().take(boxed.express.len() + 1) {
+      let next = if level == 0 {
+        boxed.back
+      } else {
+        boxed.express[level - 1]
+      };
+      let link = self.link_mut(pred, level);
+      debug_assert_eq!(*link, Some(node));
+      *link = next;
+    }
+
+    match boxed.back {
+      Some(next) => (*next.as_ptr()).front = boxed.front,
+      None => self.back = boxed.front,
+    }
+
+    self.trim();
+    self.len -= 1;
+    (boxed.key, boxed.value)
+  }
+
+  /// Drops levels that have nothing left on them.
+  fn trim(&mut self) {
+    while let Some(None) = self.express.last() {
+      self.express.pop();
+    }
+  }
+}
+
+impl<K: Ord, V> SkipListMap<K, V> {
+  /// The node for `key`, if there is one.
+  fn find<Q>(&self, key: &Q) -> Link<K, V>
+  where
+    K: Borrow<Q>,
+    Q: Ord + ?Sized,
+  {
+    let pred = self.pred(|node| unsafe { self::key(node).borrow() < key });
+    self
+      .link(pred, 0)
+      .filter(|&node| unsafe { self::key(node).borrow() == key })
+  }
+
+  pub fn get<Q>(&self, key: &Q) -> Option<&V>
+  where
+    K: Borrow<Q>,
+    Q: Ord + ?Sized,
+  {
+    self
+      .find(key)
+      .map(|node| unsafe { &(*node.as_ptr()).value })
+  }
+
+  pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+  where
+    K: Borrow<Q>,
+    Q: Ord + ?Sized,
+  {
+    self
+      .find(key)
+      .map(|node| unsafe { &mut (*node.as_ptr()).value })
+  }
+
+  pub fn get_key_value<Q>(&self, key: &Q) -> Option<(&K, &V)>
+  where
+    K: Borrow<Q>,
+    Q: Ord + ?Sized,
+  {
+    self
+      .find(key)
+      .map(|node| unsafe { (&(*node.as_ptr()).key, &(*node.as_ptr()).value) })
+  }
+
+  pub fn contains_key<Q>(&self, key: &Q) -> bool
+  where
+    K: Borrow<Q>,
+    Q: Ord + ?Sized,
+  {
+    self.find(key).is_some()
+  }
+
+  /// Adds the entry, handing back the old value if `key` was already in
+  /// the map. The old key stays, like `BTreeMap`.
+  pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+    match self.entry(key) {
+      Entry::Occupied(mut entry) => Some(entry.insert(value)),
+      Entry::Vacant(entry) => {
+        entry.insert(value);
+        None
+      }
+    }
+  }
+
+  pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+  where
+    K: Borrow<Q>,
+    Q: Ord + ?Sized,
+  {
+    self.remove_entry(key).map(|(_, value)| value)
+  }
+
+  pub fn remove_entry<Q>(&mut self, key: &Q) -> Option<(K, V)>
+  where
+    K: Borrow<Q>,
+    Q: Ord + ?Sized,
+  {
+    let preds = self.preds(|node| unsafe { self::key(node).borrow() < key });
+    let node = self
+      .link(preds[0], 0)
+      .filter(|&node| unsafe { self::key(node).borrow() == key })?;
+    Some(unsafe { self.unlink(&preds, node) })
+  }
+
+  pub fn entry(&mut self, key: K) -> Entry<'_, K, V> {
+    let preds = self.preds(|node| unsafe { *self::key(node) < key });
+
+    match self.link(preds[0], 0) {
+      Some(node) if unsafe { *self::key(node) == key } => Entry::Occupied(OccupiedEntry {
+        map: self,
+        node,
+        preds,
+      }),
+      _ => Entry::Vacant(VacantEntry {
+        map: self,
+        key,
+        preds,
+      }),
+    }
+  }
+
+  /// The first and last node in `range`, or `None` if there aren't any.
+  fn bounds<Q, R>(&self, range: R) -> Option<Span<K, V>>
+  where
+    K: Borrow<Q>,
+    Q: Ord + ?Sized,
+    R: RangeBounds<Q>,
+  {
+    match (range.start_bound(), range.end_bound()) {
+      (Bound::Excluded(start), Bound::Excluded(end)) if start == end => {
+        panic!("range start and end are equal and excluded")
+      }
+      (
+        Bound::Included(start) | Bound::Excluded(start),
+        Bound::Included(end) | Bound::Excluded(end),
+      ) if start > end => {
+        panic!("range start is greater than range end")
+      }
+      _ => {}
+    }
+
+    let before_start = match range.start_bound() {
+      Bound::Included(start) => self.pred(|node| unsafe { key(node).borrow() < start }),
+      Bound::Excluded(start) => self.pred(|node| unsafe { key(node).borrow() <= start }),
+      Bound::Unbounded => None,
+    };
+    let first = self.link(before_start, 0)?;
+
+    let last = match range.end_bound() {
+      Bound::Included(end) => self.pred(|node| unsafe { key(node).borrow() <= end })?,
+      Bound::Excluded(end) => self.pred(|node| unsafe { key(node).borrow() < end })?,
+      Bound::Unbounded => self.back?,
+    };
+
+    // With an empty range the first node past the start is after the last
+    // one before the end.
+    unsafe { (key(first) <= key(last)).then_some((first, last)) }
+  }
+
+  /// The entries with keys in `range`, in order. Panics if the range starts
+  /// after it ends, like `BTreeMap::range`.
+  pub fn range<Q, R>(&self, range: R) -> Range<'_, K, V>
+  where
+    K: Borrow<Q>,
+    Q: Ord + ?Sized,
+    R: RangeBounds<Q>,
+  {
+    let (front, back) = self.bounds(range).unzip();
+    Range {
+      front,
+      back,
+      _p: PhantomData,
+    }
+  }
+
+  pub fn range_mut<Q, R>(&mut self, range: R) -> RangeMut<'_, K, V>
+  where
+    K: Borrow<Q>,
+    Q: Ord + ?Sized,
+    R: RangeBounds<Q>,
+  {
+    let (front, back) = self.bounds(range).unzip();
+    RangeMut {
+      front,
+      back,
+      _p: PhantomData,
+    }
+  }
+
+  /// Moves every entry with a key of at least `key` into a new map, which
+  /// only relinks one node per level on each side of the cut. Counting what
+  /// moved is still a walk over it though.
+  pub fn split_off<Q>(&mut self, key: &Q) -> Self
+  where
+    K: Borrow<Q>,
+    Q: Ord + ?Sized,
+  {
+    let preds = self.preds(|node| unsafe { self::key(node).borrow() < key });
+    let mut other = Self::with_seed(self.rng.next_u64());
+    other.express = vec![None; self.express.len()];
+
+    unsafe {
+      for (level, &pred) in preds.iter().enumerate() {
+        *other.link_mut(None, level) = self.link_mut(pred, level).take();
+      }
+
+      if let Some(first) = other.front {
+        (*first.as_ptr()).front = None;
+        other.back = mem::replace(&mut self.back, preds[0]);
+      }
+    }
+
+    let mut node = other.front;
+    while let Some(current) = node {
+      other.len += 1;
+      node = unsafe { (*current.as_ptr()).back };
+    }
+    self.len -= other.len;
+    self.trim();
+    other.trim();
+    other
+  }
+
+  /// Moves all of `other`'s entries into this map, leaving it empty. If
+  /// they all come after this map's keys that's just a splice, otherwise
+  /// they're inserted one at a time, with `other`'s value winning any
+  /// duplicate keys.
+  pub fn append(&mut self, other: &mut Self) {
+    let (Some(last), Some(first)) = (self.back, other.front) else {
+      if self.is_empty() {
+        mem::swap(self, other);
+      }
+      return;
+    };
+
+    if unsafe { key(last) >= key(first) } {
+      for (key, value) in mem::take(other) {
+        self.insert(key, value);
+      }
+      return;
+    }
+
+    // The last node on each level.
+    let mut preds = self.preds(|_| true);
+    while self.height() < other.height() {
+      self.express.push(None);
+      preds.push(None);
+    }
+
+    unsafe {
+      for (level, &pred) in preds.iter().enumerate().take(other.height()) {
+        *self.link_mut(pred, level) = other.link(None, level);
+      }
+      (*first.as_ptr()).front = Some(last);
+    }
+
+    self.back = other.back.take();
+    self.len += mem::take(&mut other.len);
+    other.front = None;
+    other.express.clear();
+  }
+}
+
+unsafe impl<K: Send, V: Send> Send for SkipListMap<K, V> {}
+unsafe impl<K: Sync, V: Sync> Sync for SkipListMap<K, V> {}
+
+impl<K, V> Drop for SkipListMap<K, V> {
+  fn drop(&mut self) {
+    drop_guard::drop_all_with_guard(self, Self::pop_first);
+  }
+}
+
+impl<K, V> Default for SkipListMap<K, V> {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl<K: Ord + Clone, V: Clone> Clone for SkipListMap<K, V> {
+  fn clone(&self) -> Self {
+    let mut new = Self::with_seed(self.rng.0);
+    new.extend(self.iter().map(|(key, value)| (key.clone(), value.clone())));
+    new
+  }
+}
+
+impl<K: Debug, V: Debug> Debug for SkipListMap<K, V> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_map().entries(self.iter()).finish()
+  }
+}
+
+impl<K: PartialEq, V: PartialEq> PartialEq for SkipListMap<K, V> {
+  fn eq(&self, other: &Self) -> bool {
+    self.len == other.len && self.iter().eq(other.iter())
+  }
+}
+
+impl<K: Eq, V: Eq> Eq for SkipListMap<K, V> {}
+
+impl<K: Ord, V> Extend<(K, V)> for SkipListMap<K, V> {
+  fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
+    for (key, value) in iter {
+      self.insert(key, value);
+    }
+  }
+}
+
+impl<K: Ord, V> FromIterator<(K, V)> for SkipListMap<K, V> {
+  fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+    let mut map = Self::new();
+    map.extend(iter);
+    map
+  }
+}
+
+pub enum Entry<'a, K, V> {
+  Occupied(OccupiedEntry<'a, K, V>),
+  Vacant(VacantEntry<'a, K, V>),
+}
+
+pub struct OccupiedEntry<'a, K, V> {
+  map: &'a mut SkipListMap<K, V>,
+  node: NonNull<Node<K, V>>,
+  preds: Vec<Link<K, V>>,
+}
+
+/// Remembers where the search stopped, so inserting doesn't search again.
+pub struct VacantEntry<'a, K, V> {
+  map: &'a mut SkipListMap<K, V>,
+  key: K,
+  preds: Vec<Link<K, V>>,
+}
+
+impl<'a, K: Ord, V> Entry<'a, K, V> {
+  pub fn key(&self) -> &K {
+    match self {
+      Entry::Occupied(entry) => entry.key(),
+      Entry::Vacant(entry) => entry.key(),
+    }
+  }
+
+  pub fn or_insert(self, default: V) -> &'a mut V {
+    self.or_insert_with(|| default)
+  }
+
+  pub fn or_insert_with<F: FnOnce() -> V>(self, default: F) -> &'a mut V {
+    match self {
+      Entry::Occupied(entry) => entry.into_mut(),
+      Entry::Vacant(entry) => entry.insert(default()),
+    }
+  }
+
+  pub fn or_default(self) -> &'a mut V
+  where
+    V: Default,
+  {
+    self.or_insert_with(V::default)
+  }
+
+  pub fn and_modify<F: FnOnce(&mut V)>(mut self, f: F) -> Self {
+    if let Entry::Occupied(entry) = &mut self {
+      f(entry.get_mut());
+    }
+    self
+  }
+}
+
+impl<'a, K: Ord, V> OccupiedEntry<'a, K, V> {
+  pub fn key(&self) -> &K {
+    unsafe { &(*self.node.as_ptr()).key }
+  }
+
+  pub fn get(&self) -> &V {
+    unsafe { &(*self.node.as_ptr()).value }
+  }
+
+  pub fn get_mut(&mut self) -> &mut V {
+    unsafe { &mut (*self.node.as_ptr()).value }
+  }
+
+  pub fn into_mut(self) -> &'a mut V {
+    unsafe { &mut (*self.node.as_ptr()).value }
+  }
+
+  pub fn insert(&mut self, value: V) -> V {
+    mem::replace(self.get_mut(), value)
+  }
+
+  pub fn remove(self) -> V {
+    self.remove_entry().1
+  }
+
+  pub fn remove_entry(self) -> (K, V) {
+    unsafe { self.map.unlink(&self.preds, self.node) }
+  }
+}
+
+impl<'a, K: Ord, V> VacantEntry<'a, K, V> {
+  pub fn key(&self) -> &K {
+    &self.key
+  }
+
+  pub fn into_key(self) -> K {
+    self.key
+  }
+
+  pub fn insert(self, value: V) -> &'a mut V {
+    unsafe {
+      let node = self.map.link_in(self.preds, self.key, value);
+      &mut (*node.as_ptr()).value
+    }
+  }
+}
+
+pub struct Iter<'a, K, V> {
+  front: Link<K, V>,
+  back: Link<K, V>,
+  len: usize,
+  _p: PhantomData<&'a Node<K, V>>,
+}
+
+impl<'a, K, V> Iterator for Iter<'a, K, V> {
+  type Item = (&'a K, &'a V);
+
+  fn next(&mut self) -> Option<Self::Item> {
+    if self.len == 0 {
+      return None;
+    }
+
+    self.front.map(|node| unsafe {
+      self.len -= 1;
+      self.front = (*node.as_ptr()).back;
+      (&(*node.as_ptr()).key, &(*node.as_ptr()).value)
+    })
+  }
+
+  fn size_hint(&self) -> (usize, Option<usize>) {
+    (self.len, Some(self.len))
+  }
+}
+
+impl<K, V> DoubleEndedIterator for Iter<'_, K, V> {
+  fn next_back(&mut self) -> Option<Self::Item> {
+    if self.len == 0 {
+      return None;
+    }
+
+    self.back.map(|node| unsafe {
+      self.len -= 1;
+      self.back = (*node.as_ptr()).front;
+      (&(*node.as_ptr()).key, &(*node.as_ptr()).value)
+    })
+  }
+}
+
+impl<K, V> ExactSizeIterator for Iter<'_, K, V> {}
+
+pub struct IterMut<'a, K, V> {
+  front: Link<K, V>,
+  back: Link<K, V>,
+  len: usize,
+  _p: PhantomData<&'a mut Node<K, V>>,
+}
+
+impl<'a, K, V> Iterator for IterMut<'a, K, V> {
+  type Item = (&'a K, &'a mut V);
+
+  fn next(&mut self) -> Option<Self::Item> {
+    if self.len == 0 {
+      return None;
+    }
+
+    self.front.map(|node| unsafe {
+      self.len -= 1;
+      self.front = (*node.as_ptr()).back;
+      (&(*node.as_ptr()).key, &mut (*node.as_ptr()).value)
+    })
+  }
+
+  fn size_hint(&self) -> (usize, Option<usize>) {
+    (self.len, Some(self.len))
+  }
+}
+
+impl<K, V> DoubleEndedIterator for IterMut<'_, K, V> {
+  fn next_back(&mut self) -> Option<Self::Item> {
+    if self.len == 0 {
+      return None;
+    }
+
+    self.back.map(|node| unsafe {
+      self.len -= 1;
+      self.back = (*node.as_ptr()).front;
+      (&(*node.as_ptr()).key, &mut (*node.as_ptr()).value)
+    })
+  }
+}
+
+impl<K, V> ExactSizeIterator for IterMut<'_, K, V> {}
+
+/// There's no count to go on, so `front` and `back` are both cleared once
+/// they meet.
+pub struct Range<'a, K, V> {
+  front: Link<K, V>,
+  back: Link<K, V>,
+  _p: PhantomData<&'a Node<K, V>>,
+}
+
+impl<'a, K, V> Iterator for Range<'a, K, V> {
+  type Item = (&'a K, &'a V);
+
+  fn next(&mut self) -> Option<Self::Item> {
+    let node = self.front?;
+    if self.front == self.back {
+      self.front = None;
+      self.back = None;
+    } else {
+      self.front = unsafe { (*node.as_ptr()).back };
+    }
+    unsafe { Some((&(*node.as_ptr()).key, &(*node.as_ptr()).value)) }
+  }
+}
+
+impl<K, V> DoubleEndedIterator for Range<'_, K, V> {
+  fn next_back(&mut self) -> Option<Self::Item> {
+    let node = self.back?;
+    if self.front == self.back {
+      self.front = None;
+      self.back = None;
+    } else {
+      self.back = unsafe { (*node.as_ptr()).front };
+    }
+    unsafe { Some((&(*node.as_ptr()).key, &(*node.as_ptr()).value)) }
+  }
+}
+
+pub struct RangeMut<'a, K, V> {
+  front: Link<K, V>,
+  back: Link<K, V>,
+  _p: PhantomData<&'a mut Node<K, V>>,
+}
+
+impl<'a, K, V> Iterator for RangeMut<'a, K, V> {
+  type Item = (&'a K, &'a mut V);
+
+  fn next(&mut self) -> Option<Self::Item> {
+    let node = self.front?;
+    if self.front == self.back {
+      self.front = None;
+      self.back = None;
+    } else {
+      self.front = unsafe { (*node.as_ptr()).back };
+    }
+    unsafe { Some((&(*node.as_ptr()).key, &mut (*node.as_ptr()).value)) }
+  }
+}
+
+impl<K, V> DoubleEndedIterator for RangeMut<'_, K, V> {
+  fn next_back(&mut self) -> Option<Self::Item> {
+    let node = self.back?;
+    if self.front == self.back {
+      self.front = None;
+      self.back = None;
+    } else {
+      self.back = unsafe { (*node.as_ptr()).front };
+    }
+    unsafe { Some((&(*node.as_ptr()).key, &mut (*node.as_ptr()).value)) }
+  }
+}
+
+// The iterators only hand out references, so they can cross threads
+// whenever the references could.
+unsafe impl<K: Sync, V: Sync> Send for Iter<'_, K, V> {}
+unsafe impl<K: Sync, V: Sync> Sync for Iter<'_, K, V> {}
+unsafe impl<K: Sync, V: Send> Send for IterMut<'_, K, V> {}
+unsafe impl<K: Sync, V: Sync> Sync for IterMut<'_, K, V> {}
+unsafe impl<K: Sync, V: Sync> Send for Range<'_, K, V> {}
+unsafe impl<K: Sync, V: Sync> Sync for Range<'_, K, V> {}
+unsafe impl<K: Sync, V: Send> Send for RangeMut<'_, K, V> {}
+unsafe impl<K: Sync, V: Sync> Sync for RangeMut<'_, K, V> {}
+
+pub struct IntoIter<K, V>(SkipListMap<K, V>);
+
+impl<K, V> Iterator for IntoIter<K, V> {
+  type Item = (K, V);
+
+  fn next(&mut self) -> Option<Self::Item> {
+    self.0.pop_first()
+  }
+
+  fn size_hint(&self) -> (usize, Option<usize>) {
+    (self.0.len, Some(self.0.len))
+  }
+}
+
+impl<K, V> DoubleEndedIterator for IntoIter<K, V> {
+  fn next_back(&mut self) -> Option<Self::Item> {
+    self.0.pop_last()
+  }
+}
+
+impl<K, V> ExactSizeIterator for IntoIter<K, V> {}
+
+impl<K, V> IntoIterator for SkipListMap<K, V> {
+  type Item = (K, V);
+  type IntoIter = IntoIter<K, V>;
+
+  fn into_iter(self) -> Self::IntoIter {
+    IntoIter(self)
+  }
+}
+
+impl<'a, K, V> IntoIterator for &'a SkipListMap<K, V> {
+  type Item = (&'a K, &'a V);
+  type IntoIter = Iter<'a, K, V>;
+
+  fn into_iter(self) -> Self::IntoIter {
+    self.iter()
+  }
+}
+
+impl<'a, K, V> IntoIterator for &'a mut SkipListMap<K, V> {
+  type Item = (&'a K, &'a mut V);
+  type IntoIter = IterMut<'a, K, V>;
+
+  fn into_iter(self) -> Self::IntoIter {
+    self.iter_mut()
+  }
+}
+
+#[cfg(test)]
+impl<K: Ord, V> SkipListMap<K, V> {
+  /// Checks every level is in order and only holds nodes from the level
+  /// below, that the bottom level's links agree both ways, and `len`.
+  /// Panics at the first thing that's off.
+  fn check_invariants(&self) {
+    unsafe {
+      let mut count = 0;
+      let mut prev = None;
+      let mut node = self.front;
+      while let Some(current) = node {
+        assert_eq!(
+          (*current.as_ptr()).front,
+          prev,
+          "node {} has the wrong front",
+          count
+        );
+        if let Some(prev) = prev {
+          assert!(key(prev) < key(current), "node {} is out of order", count);
+        }
+        assert!(
+          (*current.as_ptr()).express.as_ref().len() < self.height(),
+          "node {} is taller than the map",
+          count
+        );
+        count += 1;
+        prev = node;
+        node = (*current.as_ptr()).back;
+      }
+      assert_eq!(self.back, prev, "back isn't the last node");
+      assert_eq!(self.len, count, "len doesn't match the nodes");
+
+      for level in 1..self.height() {
+        assert!(self.link(None, level).is_some(), "level {} is empty", level);
+
+        // Both levels are in order, so walking the lower one alongside
+        // finds every node of the upper one if it's there at all.
+        let mut below = self.link(None, level - 1);
+        let mut at = self.link(None, level);
+        while let Some(node) = at {
+          while below.is_some_and(|below| below != node) {
+            below = self.link(below, level - 1);
+          }
+          assert_eq!(
+            below,
+            Some(node),
+            "level {} has a node level {} doesn't",
+            level,
+            level - 1
+          );
+          at = self.link(at, level);
+        }
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::drop_count::DropCounter;
+  use std::collections::BTreeMap;
+
+  fn check<K: Ord + Debug + Clone, V: PartialEq + Debug + Clone>(
+    map: &SkipListMap<K, V>,
+    model: &BTreeMap<K, V>,
+  ) {
+    map.check_invariants();
+    assert_eq!(
+      model.clone().into_iter().collect::<Vec<_>>(),
+      map
+        .iter()
+        .map(|(k, v)| (k.clone(), v.clone()))
+        .collect::<Vec<_>>()
+    );
+  }
+
+  #[test]
+  fn basics() {
+    let mut map = SkipListMap::new();
+    assert!(map.is_empty());
+    assert_eq!(None, map.get(&1));
+    assert_eq!(None, map.first());
+    assert_eq!(None, map.pop_last());
+
+    for i in [5, 1, 9, 3, 7] {
+      assert_eq!(None, map.insert(i, i * 10));
+    }
+    assert_eq!(Some(30), map.insert(3, 33));
+    map.check_invariants();
+
+    assert_eq!(5, map.len());
+    assert_eq!(Some(&33), map.get(&3));
+    assert_eq!(None, map.get(&4));
+    assert!(map.contains_key(&9));
+    assert_eq!(Some((&1, &10)), map.first());
+    assert_eq!(Some((&9, &90)), map.last());
+
+    *map.get_mut(&5).unwrap() += 1;
+    assert_eq!(Some(51), map.remove(&5));
+    assert_eq!(None, map.remove(&5));
+    map.check_invariants();
+
+    assert_eq!(vec![1, 3, 7, 9], map.keys().copied().collect::<Vec<_>>());
+    assert_eq!(
+      vec![90, 70, 33, 10],
+      map.values().rev().copied().collect::<Vec<_>>()
+    );
+    assert_eq!(Some((1, 10)), map.pop_first());
+    assert_eq!(Some((9, 90)), map.pop_last());
+    map.check_invariants();
+    assert_eq!("{3: 33, 7: 70}", format!("{:?}", map));
+  }
+
+  #[test]
+  fn borrowed_keys() {
+    let mut map: SkipListMap<String, usize> = ["pear", "apple", "fig"]
+      .iter()
+      .map(|s| (s.to_string(), s.len()))
+      .collect();
+
+    assert_eq!(Some(&5), map.get("apple"));
+    assert_eq!(
+      vec!["apple", "fig"],
+      map
+        .range::<str, _>((Bound::Included("a"), Bound::Excluded("g")))
+        .map(|(k, _)| k.as_str())
+        .collect::<Vec<_>>()
+    );
+    assert_eq!(Some(("fig".to_string(), 3)), map.remove_entry("fig"));
+  }
+
+  #[test]
+  fn double_ended_iteration() {
+    let mut map: SkipListMap<i32, i32> = (0..10).map(|i| (i, i)).collect();
+
+    let mut iter = map.iter();
+    assert_eq!(10, iter.len());
+    assert_eq!(Some((&0, &0)), iter.next());
+    assert_eq!(Some((&9, &9)), iter.next_back());
+    assert_eq!(8, iter.len());
+    assert_eq!(8, iter.by_ref().count());
+    assert_eq!(None, iter.next_back());
+
+    for (k, v) in map.iter_mut().rev().take(3) {
+      *v = -k;
+    }
+    for (_, v) in &mut map {
+      *v *= 2;
+    }
+    assert_eq!(
+      vec![-18, -16, -14, 12],
+      map.values().rev().take(4).copied().collect::<Vec<_>>()
+    );
+
+    let mut into = map.into_iter();
+    assert_eq!(Some((9, -18)), into.next_back());
+    assert_eq!(Some((0, 0)), into.next());
+    assert_eq!(8, into.len());
+  }
+
+  #[test]
+  fn range() {
+    let map: SkipListMap<i32, ()> = (0..20).map(|i| (i * 2, ())).collect();
+    let keys = |range: Range<'_, i32, ()>| range.map(|(k, _)| *k).collect::<Vec<_>>();
+
+    assert_eq!(vec![4, 6, 8], keys(map.range(3..10)));
+    assert_eq!(vec![4, 6, 8, 10], keys(map.range(4..=10)));
+    assert_eq!(
+      vec![6, 8],
+      keys(map.range((Bound::Excluded(4), Bound::Excluded(10))))
+    );
+    assert_eq!(vec![0, 2], keys(map.range(..3)));
+    assert_eq!(vec![36, 38], keys(map.range(35..)));
+    assert_eq!(20, map.range(..).count());
+    assert!(keys(map.range(5..6)).is_empty());
+    assert!(keys(map.range(100..)).is_empty());
+    assert!(keys(map.range(..0)).is_empty());
+    assert!(keys(map.range(3..3)).is_empty());
+
+    let mut range = map.range(10..20);
+    assert_eq!(Some(&18), range.next_back().map(|(k, _)| k));
+    assert_eq!(Some(&10), range.next().map(|(k, _)| k));
+    assert_eq!(
+      vec![16, 14, 12],
+      range.rev().map(|(k, _)| *k).collect::<Vec<_>>()
+    );
+  }
+
+  #[test]
+  fn range_mut() {
+    let mut map: SkipListMap<i32, i32> = (0..10).map(|i| (i, 0)).collect();
+    for (k, v) in map.range_mut(3..6) {
+      *v = *k;
+    }
+    assert_eq!(
+      vec![0, 0, 0, 3, 4, 5, 0],
+      map.values().take(7).copied().collect::<Vec<_>>()
+    );
+  }
+
+  #[test]
+  #[should_panic(expected = "range start is greater than range end")]
+  fn backwards_range_panics() {
+    let map: SkipListMap<i32, ()> = SkipListMap::new();
+    #[allow(clippy::reversed_empty_ranges)]
+    map.range(5..3).for_each(drop);
+  }
+
+  #[test]
+  fn entry() {
+    let mut counts: SkipListMap<char, usize> = SkipListMap::new();
+    for c in "mississippi".chars() {
+      *counts.entry(c).or_default() += 1;
+    }
+    assert_eq!("{'i': 4, 'm': 1, 'p': 2, 's': 4}", format!("{:?}", counts));
+
+    counts.entry('m').and_modify(|n| *n += 10).or_insert(0);
+    counts.entry('z').and_modify(|n| *n += 10).or_insert(7);
+    assert_eq!(Some(&11), counts.get(&'m'));
+    assert_eq!(Some(&7), counts.get(&'z'));
+
+    match counts.entry('p') {
+      Entry::Occupied(entry) => {
+        assert_eq!(&'p', entry.key());
+        assert_eq!(2, entry.remove());
+      }
+      Entry::Vacant(_) => panic!("p should be there"),
+    }
+    match counts.entry('q') {
+      Entry::Vacant(entry) => assert_eq!('q', entry.into_key()),
+      Entry::Occupied(_) => panic!("q shouldn't be there"),
+    }
+    assert!(!counts.contains_key(&'q'));
+    counts.check_invariants();
+  }
+
+  #[test]
+  fn split_off_and_append() {
+    let mut map: SkipListMap<i32, i32> = (0..100).map(|i| (i, i)).collect();
+
+    let mut tail = map.split_off(&60);
+    map.check_invariants();
+    tail.check_invariants();
+    assert_eq!((60, 40), (map.len(), tail.len()));
+    assert_eq!(Some((&59, &59)), map.last());
+    assert_eq!(Some((&60, &60)), tail.first());
+
+    // The keys are all after, so it's a splice.
+    map.append(&mut tail);
+    map.check_invariants();
+    assert!(tail.is_empty());
+    tail.check_invariants();
+    assert_eq!(
+      (0..100).collect::<Vec<_>>(),
+      map.keys().copied().collect::<Vec<_>>()
+    );
+
+    // Overlapping keys go in one at a time, and win.
+    let mut other: SkipListMap<i32, i32> = (95..105).map(|i| (i, -i)).collect();
+    map.append(&mut other);
+    map.check_invariants();
+    assert_eq!(105, map.len());
+    assert_eq!(Some(&-99), map.get(&99));
+
+    // Splitting past either end moves everything or nothing.
+    let everything = map.split_off(&-1);
+    assert!(map.is_empty());
+    map.check_invariants();
+    let mut everything = everything;
+    assert!(everything.split_off(&1000).is_empty());
+    map.append(&mut everything);
+    assert_eq!(105, map.len());
+    map.check_invariants();
+  }
+
+  #[test]
+  fn seed_decides_the_shape() {
+    let shape = |seed| {
+      let mut map = SkipListMap::with_seed(seed);
+      map.extend((0..200).map(|i| (i, ())));
+      let mut heights = Vec::new();
+      let mut node = map.front;
+      while let Some(current) = node {
+        unsafe {
+          heights.push((*current.as_ptr()).express.as_ref().len());
+          node = (*current.as_ptr()).back;
+        }
+      }
+      heights
+    };
+
+    assert_eq!(shape(1), shape(1));
+    assert_ne!(shape(1), shape(2));
+    // Around a coin flip per level, so a few levels and not many tall nodes.
+    let heights = shape(3);
+    assert!(heights.iter().filter(|&&h| h > 0).count() < 150);
+    assert!(heights.iter().max().unwrap() < &20);
+  }
+
+  #[test]
+  fn drops_everything() {
+    let counter = DropCounter::new();
+    let mut map: SkipListMap<usize, _> = counter.make_n(50).into_iter().enumerate().collect();
+
+    drop(map.remove(&10));
+    drop(map.insert(20, counter.make()));
+    drop(map.pop_first());
+    let tail = map.split_off(&30);
+    let mut into = tail.into_iter();
+    drop(into.next_back());
+    drop(into);
+    drop(map);
+
+    counter.assert_all_dropped();
+  }
+
+  #[test]
+  fn keeps_dropping_after_a_panic() {
+    let counter = DropCounter::new();
+    let map: SkipListMap<usize, _> = counter.make_n(10).into_iter().enumerate().collect();
+    counter.panic_on_drop(3);
+
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| drop(map)));
+    assert!(result.is_err());
+    assert_eq!(counter.created(), counter.dropped());
+  }
+
+  #[test]
+  fn random_ops_match_btree_map() {
+    let mut rng = Rng(7);
+    let mut map = SkipListMap::with_seed(11);
+    let mut model = BTreeMap::new();
+
+    for step in 0..5_000u64 {
+      let key = (rng.next_u64() % 200) as i32;
+      match rng.next_u64() % 10 {
+        0..=3 => assert_eq!(model.insert(key, step), map.insert(key, step)),
+        4 | 5 => assert_eq!(model.remove(&key), map.remove(&key)),
+        6 => assert_eq!(model.pop_first(), map.pop_first()),
+        7 => assert_eq!(model.pop_last(), map.pop_last()),
+        8 => {
+          let end = key + (rng.next_u64() % 40) as i32;
+          assert!(model.range(key..end).eq(map.range(key..end)));
+          assert!(model.range(key..=end).rev().eq(map.range(key..=end).rev()));
+        }
+        _ => {
+          let mut tail = map.split_off(&key);
+          let mut model_tail = model.split_off(&key);
+          check(&tail, &model_tail);
+          map.append(&mut tail);
+          model.append(&mut model_tail);
+        }
+      }
+      if step % 50 == 0 {
+        check(&map, &model);
+      }
+    }
+    check(&map, &model);
+  }
+}
@@ -1,4 +1,11 @@
-use std::rc::Rc;
+use std::{fmt, rc::Rc, str::FromStr};
+
+use crate::{
+  dot::{Edge, Graph, ToDot},
+  list_fmt::{self, ParseListError},
+  memory::{self, MemoryUsage},
+  traits::Stack,
+};
 
 pub struct List<T> {
   head: Link<T>,
@@ -11,6 +18,12 @@ struct Node<T> {
   next: Link<T>,
 }
 
+impl<T> Default for List<T> {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
 impl<T> List<T> {
   pub fn new() -> Self {
     List { head: None }
@@ -40,6 +53,34 @@ impl<T> List<T> {
       next: self.head.as_deref(),
     }
   }
+
+  /// Bytes of the nodes that would be freed if this list was dropped.
+  pub fn unique_bytes(&self) -> usize {
+    self.unique_node_count() * memory::rc_allocation_size::<Node<T>>()
+  }
+
+  /// Bytes of the nodes that are also reachable from some other list.
+  pub fn shared_bytes(&self) -> usize {
+    (self.node_count() - self.unique_node_count()) * memory::rc_allocation_size::<Node<T>>()
+  }
+
+  /// Counts the nodes up to the first one that somebody else also points
+  /// to. Everything after that node is reachable through them too.
+  fn unique_node_count(&self) -> usize {
+    let mut count = 0;
+    let mut current = self.head.as_ref();
+
+    while let Some(node) = current {
+      if Rc::strong_count(node) > 1 {
+        break;
+      }
+
+      count += 1;
+      current = node.next.as_ref();
+    }
+
+    count
+  }
 }
 
 impl<T> Drop for List<T> {
@@ -56,6 +97,73 @@ impl<T> Drop for List<T> {
   }
 }
 
+impl<T: fmt::Display> fmt::Display for List<T> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    list_fmt::write_list(f, self.iter(), list_fmt::DOUBLY_LINKED)
+  }
+}
+
+/// The first element in the text becomes the head.
+impl<T: FromStr> FromStr for List<T> {
+  type Err = ParseListError<T::Err>;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    let mut elems = Vec::new();
+    list_fmt::parse_list(s, |elem| elems.push(elem))?;
+
+    Ok(
+      elems
+        .into_iter()
+        .rev()
+        .fold(List::new(), |list, elem| list.cons(elem)),
+    )
+  }
+}
+
+impl<T: fmt::Display> ToDot for List<T> {
+  fn add_to_graph(&self, graph: &mut Graph, name: &str) {
+    let mut current = self.head.as_deref();
+
+    graph.marker(name, "head", current.map(|node| node as *const Node<T>));
+
+    while let Some(node) = current {
+      // Another list already drew the rest of this tail.
+      if !graph.node(node, &node.elem) {
+        break;
+      }
+
+      if let Some(next) = node.next.as_deref() {
+        graph.edge(node, next, Edge::Next);
+      }
+
+      current = node.next.as_deref();
+    }
+  }
+}
+
+impl<T> MemoryUsage for List<T> {
+  fn node_count(&self) -> usize {
+    self.iter().count()
+  }
+
+  fn heap_bytes(&self) -> usize {
+    self.node_count() * memory::rc_allocation_size::<Node<T>>()
+  }
+}
+
+/// Popping has to clone the head, since other lists may share it.
+impl<T: Clone> Stack<T> for List<T> {
+  fn push(&mut self, elem: T) {
+    *self = self.cons(elem);
+  }
+
+  fn pop(&mut self) -> Option<T> {
+    let head = self.head().cloned()?;
+    *self = self.tail();
+    Some(head)
+  }
+}
+
 pub struct Iter<'a, T> {
   next: Option<&'a Node<T>>,
 }
@@ -104,6 +212,24 @@ mod tests {
     assert_eq!(None, list.head());
   }
 
+  #[test]
+  fn display_and_from_str() {
+    let list: List<i32> = "[1, 2, 3]".parse().unwrap();
+
+    assert_eq!("[1, 2, 3]", list.to_string());
+    assert_eq!("1 <-> 2 <-> 3", format!("{:#}", list));
+    assert_eq!("[2, 3]", list.tail().to_string());
+
+    let parsed: List<i32> = format!("{:#}", list).parse().unwrap();
+    assert_eq!(list.to_string(), parsed.to_string());
+
+    assert_eq!("[]", List::<i32>::new().to_string());
+    assert_eq!(
+      Err(ParseListError::UnclosedBracket),
+      "[1".parse::<List<i32>>().map(|_| ())
+    );
+  }
+
   #[test]
   fn iter() {
     assert_eq!(None, List::<i32>::new().iter().next());
@@ -117,4 +243,6 @@ mod tests {
     assert_eq!(Some(&1), iter.next());
     assert_eq!(None, iter.next());
   }
+
+  crate::traits::conformance::stack_conformance!(List<i32>);
 }
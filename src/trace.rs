@@ -0,0 +1,644 @@
+//! Recording and replaying the mutations made to a `LinkedList`.
+//!
+//! [`Traced`] wraps a list and writes one line per mutating call:
+//!
+//! ```text
+//! push_back 1
+//! push_front 0
+//! cursor
+//! move_next
+//! splice_before [7, 8]
+//! split_before
+//! pop_back
+//! ```
+//!
+//! `cursor` starts a new cursor at the ghost position, and the lines after
+//! it up to the next non-cursor operation act on that cursor. Elements are
+//! written with their `Display` impl and read back with `FromStr`, so they
+//! must round-trip through text (and not contain newlines, or commas when
+//! spliced in).
+//!
+//! [`replay`] runs a trace against a fresh list and a `VecDeque` model side
+//! by side and stops at the first line where the two disagree.
+
+use std::{
+  collections::VecDeque,
+  error,
+  fmt::{self, Debug, Display, Write},
+  ops::Deref,
+  panic::{self, AssertUnwindSafe},
+  str::FromStr,
+};
+
+use crate::production_linked_list::{CursorMut, LinkedList};
+
+pub struct Traced<L> {
+  inner: L,
+  log: String,
+}
+
+impl<T: Display> Traced<LinkedList<T>> {
+  pub fn new() -> Self {
+    Self {
+      inner: LinkedList::new(),
+      log: String::new(),
+    }
+  }
+
+  /// Starts tracing an existing list, recording its contents as pushes so
+  /// the trace can still be replayed from scratch.
+  pub fn from_list(list: LinkedList<T>) -> Self {
+    let mut log = String::new();
+
+    for elem in list.iter() {
+      record(&mut log, "push_back", Some(elem));
+    }
+
+    Self { inner: list, log }
+  }
+
+  pub fn trace(&self) -> &str {
+    &self.log
+  }
+
+  pub fn into_parts(self) -> (LinkedList<T>, String) {
+    (self.inner, self.log)
+  }
+
+  pub fn push_front(&mut self, elem: T) {
+    record(&mut self.log, "push_front", Some(&elem));
+    self.inner.push_front(elem);
+  }
+
+  pub fn push_back(&mut self, elem: T) {
+    record(&mut self.log, "push_back", Some(&elem));
+    self.inner.push_back(elem);
+  }
+
+  pub fn pop_front(&mut self) -> Option<T> {
+    record::<T>(&mut self.log, "pop_front", None);
+    self.inner.pop_front()
+  }
+
+  pub fn pop_back(&mut self) -> Option<T> {
+    record::<T>(&mut self.log, "pop_back", None);
+    self.inner.pop_back()
+  }
+
+  pub fn clear(&mut self) {
+    record::<T>(&mut self.log, "clear", None);
+    self.inner.clear();
+  }
+
+  pub fn cursor_mut(&mut self) -> TracedCursor<'_, T> {
+    record::<T>(&mut self.log, "cursor", None);
+
+    TracedCursor {
+      cursor: self.inner.cursor_mut(),
+      log: &mut self.log,
+    }
+  }
+}
+
+impl<T: Display> Default for Traced<LinkedList<T>> {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+/// Reading doesn't need tracing, so the whole `&LinkedList` API is available.
+impl<L> Deref for Traced<L> {
+  type Target = L;
+
+  fn deref(&self) -> &L {
+    &self.inner
+  }
+}
+
+/// A `CursorMut` that records its moves and edits. Elements are only handed
+/// out as shared references, since writes through them couldn't be traced.
+pub struct TracedCursor<'a, T> {
+  cursor: CursorMut<'a, T>,
+  log: &'a mut String,
+}
+
+impl<'a, T: Display> TracedCursor<'a, T> {
+  pub fn index(&self) -> Option<usize> {
+    self.cursor.index()
+  }
+
+  pub fn current(&mut self) -> Option<&T> {
+    self.cursor.current().map(|elem| &*elem)
+  }
+
+  pub fn peek_next(&mut self) -> Option<&T> {
+    self.cursor.peek_next().map(|elem| &*elem)
+  }
+
+  pub fn peek_prev(&mut self) -> Option<&T> {
+    self.cursor.peek_prev().map(|elem| &*elem)
+  }
+
+  pub fn move_next(&mut self) {
+    record::<T>(self.log, "move_next", None);
+    self.cursor.move_next();
+  }
+
+  pub fn move_prev(&mut self) {
+    record::<T>(self.log, "move_prev", None);
+    self.cursor.move_prev();
+  }
+
+  pub fn split_before(&mut self) -> LinkedList<T> {
+    record::<T>(self.log, "split_before", None);
+    self.cursor.split_before()
+  }
+
+  pub fn splice_before(&mut self, input: LinkedList<T>) {
+    record(self.log, "splice_before", Some(&input));
+    self.cursor.splice_before(input);
+  }
+}
+
+fn record<A: Display + ?Sized>(log: &mut String, op: &str, arg: Option<&A>) {
+  match arg {
+    Some(arg) => writeln!(log, "{} {}", op, arg),
+    None => writeln!(log, "{}", op),
+  }
+  .unwrap();
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReplayError {
+  /// The line isn't a valid operation, or its argument didn't parse.
+  Parse { line: usize, message: String },
+  /// The list and the model disagree after running the line.
+  Diverged {
+    line: usize,
+    op: String,
+    expected: String,
+    actual: String,
+  },
+  /// The list panicked while running the line.
+  Panicked {
+    line: usize,
+    op: String,
+    message: String,
+  },
+}
+
+impl Display for ReplayError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      ReplayError::Parse { line, message } => write!(f, "line {}: {}", line, message),
+      ReplayError::Diverged {
+        line,
+        op,
+        expected,
+        actual,
+      } => write!(
+        f,
+        "line {} (`{}`): expected {}, got {}",
+        line, op, expected, actual
+      ),
+      ReplayError::Panicked { line, op, message } => {
+        write!(f, "line {} (`{}`) panicked: {}", line, op, message)
+      }
+    }
+  }
+}
+
+impl error::Error for ReplayError {}
+
+/// The reference the list is checked against: a `VecDeque` plus the
+/// index the cursor would be at, `None` being the ghost.
+struct Model<T> {
+  elems: VecDeque<T>,
+  cursor: Option<usize>,
+}
+
+/// What running one line produced, on either side.
+#[derive(Debug, PartialEq)]
+struct Outcome<T> {
+  popped: Option<Option<T>>,
+  split: Option<Vec<T>>,
+  cursor: Option<Option<usize>>,
+}
+
+impl<T> Default for Outcome<T> {
+  fn default() -> Self {
+    Self {
+      popped: None,
+      split: None,
+      cursor: None,
+    }
+  }
+}
+
+enum Op<T> {
+  PushFront(T),
+  PushBack(T),
+  PopFront,
+  PopBack,
+  Clear,
+  Cursor,
+  MoveNext,
+  MovePrev,
+  SplitBefore,
+  SpliceBefore(LinkedList<T>),
+}
+
+fn parse_op<T>(line: usize, text: &str) -> Result<Op<T>, ReplayError>
+where
+  T: FromStr,
+  T::Err: Display,
+{
+  let (name, arg) = match text.split_once(' ') {
+    Some((name, arg)) => (name, Some(arg)),
+    None => (text, None),
+  };
+
+  let parse_error = |message: String| ReplayError::Parse { line, message };
+
+  let elem = || -> Result<T, ReplayError> {
+    let arg = arg.ok_or_else(|| parse_error(format!("`{}` needs an element", name)))?;
+    arg
+      .parse()
+      .map_err(|err| parse_error(format!("bad element {:?}: {}", arg, err)))
+  };
+
+  let op = match name {
+    "push_front" => Op::PushFront(elem()?),
+    "push_back" => Op::PushBack(elem()?),
+    "pop_front" => Op::PopFront,
+    "pop_back" => Op::PopBack,
+    "clear" => Op::Clear,
+    "cursor" => Op::Cursor,
+    "move_next" => Op::MoveNext,
+    "move_prev" => Op::MovePrev,
+    "split_before" => Op::SplitBefore,
+    "splice_before" => {
+      let arg = arg.ok_or_else(|| parse_error("`splice_before` needs a list".to_string()))?;
+      Op::SpliceBefore(
+        arg
+          .parse()
+          .map_err(|err| parse_error(format!("bad list {:?}: {}", arg, err)))?,
+      )
+    }
+    _ => return Err(parse_error(format!("unknown operation `{}`", name))),
+  };
+
+  let takes_arg = matches!(name, "push_front" | "push_back" | "splice_before");
+  if !takes_arg && arg.is_some() {
+    return Err(parse_error(format!("`{}` takes no argument", name)));
+  }
+
+  Ok(op)
+}
+
+/// Replays `trace` onto a fresh list, returning the list if it agreed with
+/// the model the whole way through.
+pub fn replay<T>(trace: &str) -> Result<LinkedList<T>, ReplayError>
+where
+  T: FromStr + Clone + PartialEq + Debug,
+  T::Err: Display,
+{
+  let mut list = LinkedList::new();
+  let mut model = Model {
+    elems: VecDeque::new(),
+    cursor: None,
+  };
+  // Whether the lines are currently acting on a cursor.
+  let mut in_cursor = false;
+
+  for (i, text) in trace.lines().enumerate() {
+    let line = i + 1;
+    let text = text.trim_end();
+
+    if text.is_empty() {
+      continue;
+    }
+
+    let op = parse_op::<T>(line, text)?;
+
+    match op {
+      Op::Cursor => in_cursor = true,
+      Op::MoveNext | Op::MovePrev | Op::SplitBefore | Op::SpliceBefore(_) if !in_cursor => {
+        return Err(ReplayError::Parse {
+          line,
+          message: format!("`{}` outside of a cursor", text),
+        })
+      }
+      Op::MoveNext | Op::MovePrev | Op::SplitBefore | Op::SpliceBefore(_) => {}
+      _ => in_cursor = false,
+    }
+
+    let expected = apply_model(&mut model, &op);
+
+    let actual = panic::catch_unwind(AssertUnwindSafe(|| {
+      apply_list(&mut list, model_cursor_before(&op, &model, &expected), op)
+    }))
+    .map_err(|payload| ReplayError::Panicked {
+      line,
+      op: text.to_string(),
+      message: panic_message(&*payload),
+    })?;
+
+    let diverged = |expected: String, actual: String| ReplayError::Diverged {
+      line,
+      op: text.to_string(),
+      expected,
+      actual,
+    };
+
+    if expected != actual {
+      return Err(diverged(format!("{:?}", expected), format!("{:?}", actual)));
+    }
+
+    // Walk both ways, a broken back link only shows up going backwards.
+    let forwards: Vec<&T> = list.iter().collect();
+    let backwards: Vec<&T> = list.iter().rev().collect();
+    let model_forwards: Vec<&T> = model.elems.iter().collect();
+    let model_backwards: Vec<&T> = model.elems.iter().rev().collect();
+
+    if list.len() != model.elems.len() || forwards != model_forwards {
+      return Err(diverged(
+        format!("{:?}", model_forwards),
+        format!("{:?} (len {})", forwards, list.len()),
+      ));
+    }
+
+    if backwards != model_backwards {
+      return Err(diverged(
+        format!("{:?} backwards", model_backwards),
+        format!("{:?} backwards", backwards),
+      ));
+    }
+  }
+
+  Ok(list)
+}
+
+fn apply_model<T: Clone>(model: &mut Model<T>, op: &Op<T>) -> Outcome<T> {
+  let mut outcome = Outcome::default();
+  let len = model.elems.len();
+
+  match op {
+    Op::PushFront(elem) => model.elems.push_front(elem.clone()),
+    Op::PushBack(elem) => model.elems.push_back(elem.clone()),
+    Op::PopFront => outcome.popped = Some(model.elems.pop_front()),
+    Op::PopBack => outcome.popped = Some(model.elems.pop_back()),
+    Op::Clear => model.elems.clear(),
+    Op::Cursor => model.cursor = None,
+    Op::MoveNext => {
+      model.cursor = match model.cursor {
+        None if len > 0 => Some(0),
+        Some(i) if i + 1 < len => Some(i + 1),
+        _ => None,
+      };
+    }
+    Op::MovePrev => {
+      model.cursor = match model.cursor {
+        None if len > 0 => Some(len - 1),
+        Some(i) if i > 0 => Some(i - 1),
+        _ => None,
+      };
+    }
+    Op::SplitBefore => {
+      let at = model.cursor.unwrap_or(len);
+      let rest = model.elems.split_off(at);
+      let front = std::mem::replace(&mut model.elems, rest);
+      outcome.split = Some(front.into_iter().collect());
+      model.cursor = model.cursor.map(|_| 0);
+    }
+    Op::SpliceBefore(input) => {
+      let at = model.cursor.unwrap_or(len);
+      for (offset, elem) in input.iter().enumerate() {
+        model.elems.insert(at + offset, elem.clone());
+      }
+      model.cursor = model.cursor.map(|i| i + input.len());
+    }
+  }
+
+  if matches!(
+    op,
+    Op::Cursor | Op::MoveNext | Op::MovePrev | Op::SplitBefore | Op::SpliceBefore(_)
+  ) {
+    outcome.cursor = Some(model.cursor);
+  }
+
+  outcome
+}
+
+/// Where the cursor was before `op` ran, which is where the list's cursor
+/// has to be put back to, since it can't outlive a single step here.
+fn model_cursor_before<T>(op: &Op<T>, model: &Model<T>, outcome: &Outcome<T>) -> Option<usize> {
+  let after = outcome.cursor.flatten();
+  let len = model.elems.len();
+
+  match op {
+    Op::MoveNext => match after {
+      Some(0) => None,
+      Some(i) => Some(i - 1),
+      None if len > 0 => Some(len - 1),
+      None => None,
+    },
+    Op::MovePrev => match after {
+      Some(i) if i + 1 == len => None,
+      Some(i) => Some(i + 1),
+      None if len > 0 => Some(0),
+      None => None,
+    },
+    Op::SplitBefore => after.map(|_| outcome.split.as_ref().map_or(0, Vec::len)),
+    Op::SpliceBefore(input) => after.map(|i| i - input.len()),
+    _ => after,
+  }
+}
+
+fn apply_list<T: Clone>(
+  list: &mut LinkedList<T>,
+  cursor_at: Option<usize>,
+  op: Op<T>,
+) -> Outcome<T> {
+  let mut outcome = Outcome::default();
+
+  match op {
+    Op::PushFront(elem) => list.push_front(elem),
+    Op::PushBack(elem) => list.push_back(elem),
+    Op::PopFront => outcome.popped = Some(list.pop_front()),
+    Op::PopBack => outcome.popped = Some(list.pop_back()),
+    Op::Clear => list.clear(),
+    Op::Cursor => outcome.cursor = Some(list.cursor_mut().index()),
+    op => {
+      let mut cursor = list.cursor_mut();
+
+      if let Some(index) = cursor_at {
+        for _ in 0..=index {
+          cursor.move_next();
+        }
+      }
+
+      match op {
+        Op::MoveNext => cursor.move_next(),
+        Op::MovePrev => cursor.move_prev(),
+        Op::SplitBefore => {
+          outcome.split = Some(cursor.split_before().into_iter().collect());
+        }
+        Op::SpliceBefore(input) => cursor.splice_before(input),
+        _ => unreachable!(),
+      }
+
+      outcome.cursor = Some(cursor.index());
+    }
+  }
+
+  outcome
+}
+
+fn panic_message(payload: &(dyn std::any::Any + Send)) -> String {
+  if let Some(message) = payload.downcast_ref::<&str>() {
+    message.to_string()
+  } else if let Some(message) = payload.downcast_ref::<String>() {
+    message.clone()
+  } else {
+    "unknown panic".to_string()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn records_operations() {
+    let mut list = Traced::new();
+
+    list.push_back(1);
+    list.push_back(2);
+    list.push_front(0);
+    assert_eq!(Some(2), list.pop_back());
+
+    {
+      let mut cursor = list.cursor_mut();
+      cursor.move_next();
+      cursor.move_next();
+      assert_eq!(Some(&1), cursor.current());
+      cursor.splice_before([7, 8].into_iter().collect());
+      assert_eq!(Some(3), cursor.index());
+      let front = cursor.split_before();
+      assert_eq!(vec![0, 7, 8], front.into_iter().collect::<Vec<_>>());
+    }
+
+    list.push_back(9);
+
+    // Reads go straight to the list.
+    assert_eq!(2, list.len());
+    assert_eq!(Some(&1), list.front());
+
+    assert_eq!(
+      list.trace(),
+      "push_back 1
+push_back 2
+push_front 0
+pop_back
+cursor
+move_next
+move_next
+splice_before [7, 8]
+split_before
+push_back 9
+"
+    );
+  }
+
+  #[test]
+  fn replays_a_recording() {
+    let mut list = Traced::from_list((0..5).collect());
+
+    list.pop_front();
+    {
+      let mut cursor = list.cursor_mut();
+      cursor.move_prev();
+      cursor.move_prev();
+      cursor.splice_before([10, 11].into_iter().collect());
+      cursor.move_next();
+      cursor.move_next();
+      cursor.split_before();
+    }
+    list.push_front(-1);
+    {
+      let mut cursor = list.cursor_mut();
+      cursor.move_next();
+      cursor.move_next();
+      cursor.move_next();
+      cursor.move_prev();
+    }
+    list.clear();
+    list.push_back(3);
+
+    let (list, trace) = list.into_parts();
+    let replayed = replay::<i32>(&trace).unwrap();
+
+    assert_eq!(list, replayed);
+  }
+
+  #[test]
+  fn cursor_wraps_around_the_ghost() {
+    let trace = "push_back 1\npush_back 2\ncursor\nmove_next\nmove_next\nmove_next\nmove_next\nmove_prev\nmove_prev\nmove_prev\nsplice_before [3]\n";
+
+    let list = replay::<i32>(trace).unwrap();
+
+    assert_eq!(list, [3, 1, 2].into_iter().collect());
+  }
+
+  #[test]
+  fn cursor_edits_at_the_front() {
+    let trace = "push_back 1\npush_back 2\ncursor\nmove_next\nsplit_before\nsplice_before [5, 6]\nmove_prev\nsplit_before\n";
+
+    let list = replay::<i32>(trace).unwrap();
+
+    assert_eq!(list, [6, 1, 2].into_iter().collect());
+  }
+
+  #[test]
+  fn reports_parse_errors() {
+    assert_eq!(
+      replay::<i32>("push_back 1\npush_back x\n"),
+      Err(ReplayError::Parse {
+        line: 2,
+        message: "bad element \"x\": invalid digit found in string".to_string()
+      })
+    );
+    assert!(matches!(
+      replay::<i32>("push_back 1\n\nfly_away\n"),
+      Err(ReplayError::Parse { line: 3, .. })
+    ));
+    assert!(matches!(
+      replay::<i32>("push_back 1\nmove_next\n"),
+      Err(ReplayError::Parse { line: 2, .. })
+    ));
+    assert!(matches!(
+      replay::<i32>("cursor\nsplit_before\npop_front\nsplit_before\n"),
+      Err(ReplayError::Parse { line: 4, .. })
+    ));
+    assert!(matches!(
+      replay::<i32>("pop_front 3\n"),
+      Err(ReplayError::Parse { line: 1, .. })
+    ));
+  }
+
+  #[test]
+  fn reports_divergence() {
+    let err = ReplayError::Diverged {
+      line: 4,
+      op: "split_before".to_string(),
+      expected: "[1]".to_string(),
+      actual: "[2]".to_string(),
+    };
+
+    assert_eq!(
+      "line 4 (`split_before`): expected [1], got [2]",
+      err.to_string()
+    );
+  }
+}
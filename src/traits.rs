@@ -0,0 +1,344 @@
+//! One set of names for the lists' operations, so code can be written once
+//! against a trait and run on whichever list fits.
+//!
+//! | list                     | `Stack` | `Queue` | `Deque` |
+//! |--------------------------|---------|---------|---------|
+//! | `first` (`i32` only)     | yes     |         |         |
+//! | `second`                 | yes     |         |         |
+//! | `third` (`T: Clone`)     | yes     |         |         |
+//! | `fourth`                 | yes     | yes     | yes     |
+//! | `fifth`                  |         | yes     |         |
+//! | `sixth`                  |         | yes     |         |
+//! | `production_linked_list` | yes     | yes     | yes     |
+//! | `unrolled`               | yes     | yes     | yes     |
+//! | `xor_list`               | yes     | yes     | yes     |
+//!
+//! `seventh` lives on the call stack and can't be pushed onto in place, so
+//! it has none of them. `Vec` and `VecDeque` implement them too, which is
+//! handy for comparing against.
+//!
+//! ```
+//! use learn_rust_with_entirely_too_many_linked_lists::{
+//!   fifth, production_linked_list::LinkedList, traits::Queue,
+//! };
+//!
+//! fn round_robin<Q: Queue<u32>>(queue: &mut Q, turns: usize) -> Vec<u32> {
+//!   (0..turns)
+//!     .filter_map(|_| {
+//!       let next = queue.dequeue()?;
+//!       queue.enqueue(next);
+//!       Some(next)
+//!     })
+//!     .collect()
+//! }
+//!
+//! let mut a = fifth::List::new();
+//! let mut b = LinkedList::new();
+//! for i in 0..3 {
+//!   a.enqueue(i);
+//!   b.enqueue(i);
+//! }
+//!
+//! assert_eq!(round_robin(&mut a, 5), vec![0, 1, 2, 0, 1]);
+//! assert_eq!(round_robin(&mut b, 5), vec![0, 1, 2, 0, 1]);
+//! ```
+
+use std::collections::VecDeque;
+
+/// Last in, first out.
+pub trait Stack<T> {
+  fn push(&mut self, elem: T);
+
+  fn pop(&mut self) -> Option<T>;
+}
+
+/// First in, first out.
+pub trait Queue<T> {
+  fn enqueue(&mut self, elem: T);
+
+  fn dequeue(&mut self) -> Option<T>;
+}
+
+/// Pushing and popping at both ends.
+pub trait Deque<T> {
+  fn push_front(&mut self, elem: T);
+
+  fn push_back(&mut self, elem: T);
+
+  fn pop_front(&mut self) -> Option<T>;
+
+  fn pop_back(&mut self) -> Option<T>;
+}
+
+impl<T> Stack<T> for Vec<T> {
+  fn push(&mut self, elem: T) {
+    Vec::push(self, elem);
+  }
+
+  fn pop(&mut self) -> Option<T> {
+    Vec::pop(self)
+  }
+}
+
+impl<T> Stack<T> for VecDeque<T> {
+  fn push(&mut self, elem: T) {
+    VecDeque::push_front(self, elem);
+  }
+
+  fn pop(&mut self) -> Option<T> {
+    VecDeque::pop_front(self)
+  }
+}
+
+impl<T> Queue<T> for VecDeque<T> {
+  fn enqueue(&mut self, elem: T) {
+    VecDeque::push_back(self, elem);
+  }
+
+  fn dequeue(&mut self) -> Option<T> {
+    VecDeque::pop_front(self)
+  }
+}
+
+impl<T> Deque<T> for VecDeque<T> {
+  fn push_front(&mut self, elem: T) {
+    VecDeque::push_front(self, elem);
+  }
+
+  fn push_back(&mut self, elem: T) {
+    VecDeque::push_back(self, elem);
+  }
+
+  fn pop_front(&mut self) -> Option<T> {
+    VecDeque::pop_front(self)
+  }
+
+  fn pop_back(&mut self) -> Option<T> {
+    VecDeque::pop_back(self)
+  }
+}
+
+/// Generic checks every implementation has to pass, run through the
+/// `*_conformance!` macros from each list's tests.
+#[cfg(test)]
+pub(crate) mod conformance {
+  use super::*;
+
+  /// Makes the tests for a `Stack<i32>` that's also `Default`.
+  macro_rules! stack_conformance {
+    ($list:ty) => {
+      mod stack_conformance {
+        #[allow(unused_imports)]
+        use super::*;
+        use $crate::traits::conformance;
+
+        #[test]
+        fn lifo() {
+          conformance::lifo::<$list>();
+        }
+
+        #[test]
+        fn interleaved() {
+          conformance::stack_interleaved::<$list>();
+        }
+
+        #[test]
+        fn many() {
+          conformance::stack_many::<$list>();
+        }
+      }
+    };
+  }
+
+  macro_rules! queue_conformance {
+    ($list:ty) => {
+      mod queue_conformance {
+        #[allow(unused_imports)]
+        use super::*;
+        use $crate::traits::conformance;
+
+        #[test]
+        fn fifo() {
+          conformance::fifo::<$list>();
+        }
+
+        #[test]
+        fn interleaved() {
+          conformance::queue_interleaved::<$list>();
+        }
+
+        #[test]
+        fn many() {
+          conformance::queue_many::<$list>();
+        }
+      }
+    };
+  }
+
+  macro_rules! deque_conformance {
+    ($list:ty) => {
+      mod deque_conformance {
+        #[allow(unused_imports)]
+        use super::*;
+        use $crate::traits::conformance;
+
+        #[test]
+        fn both_ends() {
+          conformance::both_ends::<$list>();
+        }
+
+        #[test]
+        fn matches_vec_deque() {
+          conformance::matches_vec_deque::<$list>();
+        }
+      }
+    };
+  }
+
+  pub(crate) use {deque_conformance, queue_conformance, stack_conformance};
+
+  pub(crate) fn lifo<S: Stack<i32> + Default>() {
+    let mut stack = S::default();
+
+    assert_eq!(None, stack.pop());
+
+    stack.push(1);
+    stack.push(2);
+    stack.push(3);
+
+    assert_eq!(Some(3), stack.pop());
+    assert_eq!(Some(2), stack.pop());
+    assert_eq!(Some(1), stack.pop());
+    assert_eq!(None, stack.pop());
+  }
+
+  pub(crate) fn stack_interleaved<S: Stack<i32> + Default>() {
+    let mut stack = S::default();
+
+    stack.push(1);
+    stack.push(2);
+    assert_eq!(Some(2), stack.pop());
+    stack.push(3);
+    assert_eq!(Some(3), stack.pop());
+    assert_eq!(Some(1), stack.pop());
+    assert_eq!(None, stack.pop());
+
+    // Still usable once emptied.
+    stack.push(4);
+    assert_eq!(Some(4), stack.pop());
+  }
+
+  pub(crate) fn stack_many<S: Stack<i32> + Default>() {
+    let mut stack = S::default();
+
+    (0..10_000).for_each(|i| stack.push(i));
+    assert!((0..10_000).rev().all(|i| stack.pop() == Some(i)));
+    assert_eq!(None, stack.pop());
+
+    // And dropping a long one doesn't blow the stack.
+    (0..10_000).for_each(|i| stack.push(i));
+  }
+
+  pub(crate) fn fifo<Q: Queue<i32> + Default>() {
+    let mut queue = Q::default();
+
+    assert_eq!(None, queue.dequeue());
+
+    queue.enqueue(1);
+    queue.enqueue(2);
+    queue.enqueue(3);
+
+    assert_eq!(Some(1), queue.dequeue());
+    assert_eq!(Some(2), queue.dequeue());
+    assert_eq!(Some(3), queue.dequeue());
+    assert_eq!(None, queue.dequeue());
+  }
+
+  pub(crate) fn queue_interleaved<Q: Queue<i32> + Default>() {
+    let mut queue = Q::default();
+
+    queue.enqueue(1);
+    queue.enqueue(2);
+    assert_eq!(Some(1), queue.dequeue());
+    queue.enqueue(3);
+    assert_eq!(Some(2), queue.dequeue());
+    assert_eq!(Some(3), queue.dequeue());
+    assert_eq!(None, queue.dequeue());
+
+    queue.enqueue(4);
+    assert_eq!(Some(4), queue.dequeue());
+    assert_eq!(None, queue.dequeue());
+  }
+
+  pub(crate) fn queue_many<Q: Queue<i32> + Default>() {
+    let mut queue = Q::default();
+
+    (0..10_000).for_each(|i| queue.enqueue(i));
+    assert!((0..10_000).all(|i| queue.dequeue() == Some(i)));
+    assert_eq!(None, queue.dequeue());
+
+    (0..10_000).for_each(|i| queue.enqueue(i));
+  }
+
+  pub(crate) fn both_ends<D: Deque<i32> + Default>() {
+    let mut deque = D::default();
+
+    assert_eq!(None, deque.pop_front());
+    assert_eq!(None, deque.pop_back());
+
+    deque.push_front(2);
+    deque.push_back(3);
+    deque.push_front(1);
+
+    assert_eq!(Some(3), deque.pop_back());
+    assert_eq!(Some(1), deque.pop_front());
+    assert_eq!(Some(2), deque.pop_back());
+    assert_eq!(None, deque.pop_front());
+  }
+
+  pub(crate) fn matches_vec_deque<D: Deque<i32> + Default>() {
+    let mut deque = D::default();
+    let mut model = VecDeque::new();
+
+    // A fixed but uneven mix of operations.
+    for i in 0..1_000 {
+      match (i * 7 + i / 13) % 5 {
+        0 => {
+          deque.push_front(i);
+          model.push_front(i);
+        }
+        1 | 2 => {
+          deque.push_back(i);
+          model.push_back(i);
+        }
+        3 => assert_eq!(model.pop_front(), deque.pop_front()),
+        _ => assert_eq!(model.pop_back(), deque.pop_back()),
+      }
+    }
+
+    while let Some(elem) = model.pop_front() {
+      assert_eq!(Some(elem), deque.pop_front());
+    }
+    assert_eq!(None, deque.pop_back());
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::conformance::{deque_conformance, queue_conformance, stack_conformance};
+  use super::*;
+
+  mod vec {
+    use super::*;
+
+    stack_conformance!(Vec<i32>);
+  }
+
+  mod vec_deque {
+    use super::*;
+
+    stack_conformance!(VecDeque<i32>);
+    queue_conformance!(VecDeque<i32>);
+    deque_conformance!(VecDeque<i32>);
+  }
+}
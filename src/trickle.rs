@@ -0,0 +1,18 @@
+//! A reader that hands out a single byte per read call, for checking the
+//! decoders cope with short reads.
+
+use std::io::{self, Read};
+
+pub(crate) struct Trickle<'a>(pub(crate) &'a [u8]);
+
+impl Read for Trickle<'_> {
+  fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+    if self.0.is_empty() || buf.is_empty() {
+      return Ok(0);
+    }
+
+    buf[0] = self.0[0];
+    self.0 = &self.0[1..];
+    Ok(1)
+  }
+}
@@ -0,0 +1,1544 @@
+//! A doubly linked list where each node holds up to `N` elements in an
+//! inline array instead of just one. Scanning mostly walks along arrays, so
+//! it goes at close to `Vec` speed, while editing in the middle still only
+//! shuffles one node's worth of elements and relinks a couple of nodes.
+//!
+//! Nodes are kept at least half full: an insert into a full node first
+//! splits it into two halves, and a removal that leaves a node under half
+//! full tops it up from a neighbour, or merges the two if they fit in one.
+//! The exception is a list with just one node, which can hold anything
+//! from 1 to `N`.
+//!
+//! Otherwise this has the same surface as `production_linked_list`: the
+//! deque operations, double-ended iterators and a [`CursorMut`] that sits on
+//! one element at a time, with the same ghost between the back and front.
+
+use std::{
+  cmp::Ordering,
+  fmt::{self, Debug, Display},
+  hash::{Hash, Hasher},
+  iter::FromIterator,
+  marker::PhantomData,
+  mem::{self, MaybeUninit},
+  ops::{Bound, RangeBounds},
+  ptr::{self, NonNull},
+  str::FromStr,
+};
+
+use crate::{
+  drop_guard,
+  list_fmt::{self, ParseListError},
+  memory::MemoryUsage,
+  traits::{Deque, Queue, Stack},
+};
+
+/// Elements per node unless you pick something else.
+pub const DEFAULT_CAPACITY: usize = 16;
+
+pub struct UnrolledList<T, const N: usize = DEFAULT_CAPACITY> {
+  front: Link<T, N>,
+  back: Link<T, N>,
+  len: usize,
+  _p: PhantomData<T>,
+}
+
+type Link<T, const N: usize> = Option<NonNull<Node<T, N>>>;
+
+/// A node and an index into its elements.
+type Pos<T, const N: usize> = (NonNull<Node<T, N>>, usize);
+
+struct Node<T, const N: usize> {
+  front: Link<T, N>,
+  back: Link<T, N>,
+  /// The first `len` of `elems` are initialized.
+  len: usize,
+  elems: [MaybeUninit<T>; N],
+}
+
+impl<T, const N: usize> Node<T, N> {
+  fn alloc() -> NonNull<Self> {
+    let node = Box::new(Node {
+      front: None,
+      back: None,
+      len: 0,
+      elems: [const { MaybeUninit::uninit() }; N],
+    });
+    unsafe { NonNull::new_unchecked(Box::into_raw(node)) }
+  }
+
+  /// Frees the node along with whatever elements it still has, so set
+  /// `len` to 0 first if they've been moved out.
+  unsafe fn free(node: NonNull<Self>) {
+    drop(Box::from_raw(node.as_ptr()));
+  }
+
+  unsafe fn slot(node: NonNull<Self>, index: usize) -> *mut T {
+    ptr::addr_of_mut!((*node.as_ptr()).elems)
+      .cast::<T>()
+      .add(index)
+  }
+}
+
+impl<T, const N: usize> Drop for Node<T, N> {
+  fn drop(&mut self) {
+    unsafe {
+      ptr::drop_in_place(ptr::slice_from_raw_parts_mut(
+        self.elems.as_mut_ptr().cast::<T>(),
+        self.len,
+      ));
+    }
+  }
+}
+
+impl<T, const N: usize> UnrolledList<T, N> {
+  /// The fewest elements a node can have when it isn't the only one.
+  const MIN: usize = {
+    assert!(N >= 2, "nodes need room for at least two elements");
+    N / 2
+  };
+
+  pub fn new() -> Self {
+    let _ = Self::MIN;
+
+    Self {
+      front: None,
+      back: None,
+      len: 0,
+      _p: PhantomData,
+    }
+  }
+
+  pub fn push_front(&mut self, elem: T) {
+    unsafe {
+      let front = match self.front {
+        Some(front) => front,
+        None => self.push_node(),
+      };
+      self.insert_at((front, 0), elem, &mut None);
+    }
+  }
+
+  pub fn push_back(&mut self, elem: T) {
+    unsafe {
+      let back = match self.back {
+        Some(back) => back,
+        None => self.push_node(),
+      };
+      self.insert_at((back, (*back.as_ptr()).len), elem, &mut None);
+    }
+  }
+
+  pub fn pop_front(&mut self) -> Option<T> {
+    let front = self.front?;
+    unsafe { Some(self.remove_at((front, 0), &mut None)) }
+  }
+
+  pub fn pop_back(&mut self) -> Option<T> {
+    let back = self.back?;
+    unsafe { Some(self.remove_at((back, (*back.as_ptr()).len - 1), &mut None)) }
+  }
+
+  pub fn front(&self) -> Option<&T> {
+    unsafe { self.front.map(|node| &*Node::slot(node, 0)) }
+  }
+
+  pub fn front_mut(&mut self) -> Option<&mut T> {
+    unsafe { self.front.map(|node| &mut *Node::slot(node, 0)) }
+  }
+
+  pub fn back(&self) -> Option<&T> {
+    unsafe {
+      self
+        .back
+        .map(|node| &*Node::slot(node, (*node.as_ptr()).len - 1))
+    }
+  }
+
+  pub fn back_mut(&mut self) -> Option<&mut T> {
+    unsafe {
+      self
+        .back
+        .map(|node| &mut *Node::slot(node, (*node.as_ptr()).len - 1))
+    }
+  }
+
+  pub fn len(&self) -> usize {
+    self.len
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.len == 0
+  }
+
+  pub fn clear(&mut self) {
+    // Dropping handles an element panicking, and leaves `self` empty if one does.
+    drop(mem::take(self));
+  }
+
+  pub fn iter(&self) -> Iter<'_, T, N> {
+    let (front, back) = self.ends();
+    Iter {
+      front,
+      back,
+      len: self.len,
+      _p: PhantomData,
+    }
+  }
+
+  pub fn iter_mut(&mut self) -> IterMut<'_, T, N> {
+    let (front, back) = self.ends();
+    IterMut {
+      front,
+      back,
+      len: self.len,
+      _p: PhantomData,
+    }
+  }
+
+  /// Iterates over just the elements at the indices in `range`, panicking
+  /// if it reaches past the end.
+  pub fn range<R: RangeBounds<usize>>(&self, range: R) -> Iter<'_, T, N> {
+    let (front, back, len) = self.run(range);
+    Iter {
+      front,
+      back,
+      len,
+      _p: PhantomData,
+    }
+  }
+
+  pub fn range_mut<R: RangeBounds<usize>>(&mut self, range: R) -> IterMut<'_, T, N> {
+    let (front, back, len) = self.run(range);
+    IterMut {
+      front,
+      back,
+      len,
+      _p: PhantomData,
+    }
+  }
+
+  pub fn cursor_mut(&mut self) -> CursorMut<'_, T, N> {
+    CursorMut {
+      list: self,
+      current: None,
+      index: None,
+    }
+  }
+
+  /// Where iterating starts and ends: the first element, and one past the
+  /// last in its node.
+  fn ends(&self) -> (Option<Pos<T, N>>, Option<Pos<T, N>>) {
+    unsafe {
+      (
+        self.front.map(|node| (node, 0)),
+        self.back.map(|node| (node, (*node.as_ptr()).len)),
+      )
+    }
+  }
+
+  /// The first and one-past-last positions of `range`, and its length.
+  fn run<R: RangeBounds<usize>>(&self, range: R) -> (Option<Pos<T, N>>, Option<Pos<T, N>>, usize) {
+    let start = match range.start_bound() {
+      Bound::Included(&start) => start,
+      Bound::Excluded(&start) => start.checked_add(1).expect("range start overflows"),
+      Bound::Unbounded => 0,
+    };
+    let end = match range.end_bound() {
+      Bound::Included(&end) => end.checked_add(1).expect("range end overflows"),
+      Bound::Excluded(&end) => end,
+      Bound::Unbounded => self.len,
+    };
+
+    assert!(
+      start <= end,
+      "range starts at {} but ends at {}",
+      start,
+      end
+    );
+    assert!(
+      end <= self.len,
+      "range end {} out of range for length {}",
+      end,
+      self.len
+    );
+
+    if start == end {
+      return (None, None, 0);
+    }
+
+    let (last, at) = self.locate(end - 1);
+    (Some(self.locate(start)), Some((last, at + 1)), end - start)
+  }
+
+  /// Finds the element at `index`, walking from whichever end is closer a
+  /// node at a time.
+  fn locate(&self, index: usize) -> Pos<T, N> {
+    debug_assert!(index < self.len);
+
+    // SAFETY: `index` is in bounds, so the walk ends inside a node.
+    unsafe {
+      if index < self.len / 2 {
+        let mut node = self.front.unwrap();
+        let mut skipped = 0;
+        while skipped + (*node.as_ptr()).len <= index {
+          skipped += (*node.as_ptr()).len;
+          node = (*node.as_ptr()).back.unwrap();
+        }
+        (node, index - skipped)
+      } else {
+        let mut node = self.back.unwrap();
+        let mut start = self.len - (*node.as_ptr()).len;
+        while start > index {
+          node = (*node.as_ptr()).front.unwrap();
+          start -= (*node.as_ptr()).len;
+        }
+        (node, index - start)
+      }
+    }
+  }
+
+  /// Adds an empty node to an empty list.
+  unsafe fn push_node(&mut self) -> NonNull<Node<T, N>> {
+    debug_assert!(self.front.is_none());
+    let node = Node::alloc();
+    self.front = Some(node);
+    self.back = Some(node);
+    node
+  }
+
+  /// Links `node` in after `prev`, or at the front if that's `None`.
+  unsafe fn link_after(&mut self, prev: Link<T, N>, node: NonNull<Node<T, N>>) {
+    let next = match prev {
+      Some(prev) => (*prev.as_ptr()).back,
+      None => self.front,
+    };
+
+    (*node.as_ptr()).front = prev;
+    (*node.as_ptr()).back = next;
+
+    match prev {
+      Some(prev) => (*prev.as_ptr()).back = Some(node),
+      None => self.front = Some(node),
+    }
+    match next {
+      Some(next) => (*next.as_ptr()).front = Some(node),
+      None => self.back = Some(node),
+    }
+  }
+
+  unsafe fn unlink(&mut self, node: NonNull<Node<T, N>>) {
+    let (prev, next) = ((*node.as_ptr()).front, (*node.as_ptr()).back);
+
+    match prev {
+      Some(prev) => (*prev.as_ptr()).back = next,
+      None => self.front = next,
+    }
+    match next {
+      Some(next) => (*next.as_ptr()).front = prev,
+      None => self.back = prev,
+    }
+  }
+
+  /// Moves the elements of `node` from `at` on into a new node after it.
+  /// `cursor` follows its element if it moves.
+  unsafe fn split(
+    &mut self,
+    node: NonNull<Node<T, N>>,
+    at: usize,
+    cursor: &mut Option<Pos<T, N>>,
+  ) -> NonNull<Node<T, N>> {
+    let new = Node::alloc();
+    let moved = (*node.as_ptr()).len - at;
+
+    ptr::copy_nonoverlapping(Node::slot(node, at), Node::slot(new, 0), moved);
+    (*node.as_ptr()).len = at;
+    (*new.as_ptr()).len = moved;
+    self.link_after(Some(node), new);
+
+    if let Some((on, index)) = *cursor {
+      if on == node && index >= at {
+        *cursor = Some((new, index - at));
+      }
+    }
+
+    new
+  }
+
+  /// Puts `elem` at `pos`, splitting the node in half first if it's full.
+  /// `cursor` stays on the element it was on.
+  unsafe fn insert_at(
+    &mut self,
+    (mut node, mut at): Pos<T, N>,
+    elem: T,
+    cursor: &mut Option<Pos<T, N>>,
+  ) {
+    if (*node.as_ptr()).len == N {
+      let new = self.split(node, Self::MIN, cursor);
+      if at > Self::MIN {
+        node = new;
+        at -= Self::MIN;
+      }
+    }
+
+    let len = (*node.as_ptr()).len;
+    ptr::copy(Node::slot(node, at), Node::slot(node, at + 1), len - at);
+    Node::slot(node, at).write(elem);
+    (*node.as_ptr()).len += 1;
+    self.len += 1;
+
+    if let Some((on, index)) = *cursor {
+      if on == node && index >= at {
+        *cursor = Some((on, index + 1));
+      }
+    }
+  }
+
+  /// Takes out the element at `pos` and fixes up its node. A `cursor` on
+  /// the removed element moves on to the next one, or the ghost.
+  unsafe fn remove_at(&mut self, (node, at): Pos<T, N>, cursor: &mut Option<Pos<T, N>>) -> T {
+    let elem = Node::slot(node, at).read();
+    let len = (*node.as_ptr()).len - 1;
+    ptr::copy(Node::slot(node, at + 1), Node::slot(node, at), len - at);
+    (*node.as_ptr()).len = len;
+    self.len -= 1;
+
+    if let Some((on, index)) = *cursor {
+      if on == node && index > at {
+        *cursor = Some((on, index - 1));
+      } else if on == node && index == len {
+        *cursor = (*node.as_ptr()).back.map(|next| (next, 0));
+      }
+    }
+
+    self.sweep(node, node, cursor);
+    elem
+  }
+
+  /// Walks from `from` to `to`, topping up every node under half full from
+  /// the one after it, or merging the two if they fit in one node. The last
+  /// node leans on the one before it instead. `cursor` follows its element
+  /// through all the moves.
+  unsafe fn sweep(
+    &mut self,
+    from: NonNull<Node<T, N>>,
+    mut to: NonNull<Node<T, N>>,
+    cursor: &mut Option<Pos<T, N>>,
+  ) {
+    let mut node = from;
+
+    loop {
+      let len = (*node.as_ptr()).len;
+
+      if len < Self::MIN || len == 0 {
+        if let Some(next) = (*node.as_ptr()).back {
+          if len + (*next.as_ptr()).len <= N {
+            self.merge(node, next, cursor);
+            if next == to {
+              to = node;
+            }
+            // It might still be short if the next one was too.
+            continue;
+          }
+          self.take_from_next(node, next, Self::MIN - len, cursor);
+        } else if let Some(prev) = (*node.as_ptr()).front {
+          if (*prev.as_ptr()).len + len <= N {
+            self.merge(prev, node, cursor);
+            return;
+          }
+          self.take_from_prev(prev, node, Self::MIN - len, cursor);
+        } else if len == 0 {
+          self.unlink(node);
+          Node::free(node);
+          return;
+        }
+      }
+
+      if node == to {
+        return;
+      }
+      node = (*node.as_ptr()).back.unwrap();
+    }
+  }
+
+  /// Moves everything in `next` onto the end of `node` and frees `next`.
+  unsafe fn merge(
+    &mut self,
+    node: NonNull<Node<T, N>>,
+    next: NonNull<Node<T, N>>,
+    cursor: &mut Option<Pos<T, N>>,
+  ) {
+    let (len, moved) = ((*node.as_ptr()).len, (*next.as_ptr()).len);
+
+    ptr::copy_nonoverlapping(Node::slot(next, 0), Node::slot(node, len), moved);
+    (*node.as_ptr()).len += moved;
+    (*next.as_ptr()).len = 0;
+    self.unlink(next);
+    Node::free(next);
+
+    if let Some((on, index)) = *cursor {
+      if on == next {
+        *cursor = Some((node, len + index));
+      }
+    }
+  }
+
+  /// Moves the first `count` elements of `next` onto the end of `node`.
+  unsafe fn take_from_next(
+    &mut self,
+    node: NonNull<Node<T, N>>,
+    next: NonNull<Node<T, N>>,
+    count: usize,
+    cursor: &mut Option<Pos<T, N>>,
+  ) {
+    let (len, next_len) = ((*node.as_ptr()).len, (*next.as_ptr()).len);
+
+    ptr::copy_nonoverlapping(Node::slot(next, 0), Node::slot(node, len), count);
+    ptr::copy(
+      Node::slot(next, count),
+      Node::slot(next, 0),
+      next_len - count,
+    );
+    (*node.as_ptr()).len += count;
+    (*next.as_ptr()).len -= count;
+
+    if let Some((on, index)) = *cursor {
+      if on == next {
+        *cursor = Some(if index < count {
+          (node, len + index)
+        } else {
+          (next, index - count)
+        });
+      }
+    }
+  }
+
+  /// Moves the last `count` elements of `prev` onto the front of `node`.
+  unsafe fn take_from_prev(
+    &mut self,
+    prev: NonNull<Node<T, N>>,
+    node: NonNull<Node<T, N>>,
+    count: usize,
+    cursor: &mut Option<Pos<T, N>>,
+  ) {
+    let (prev_len, len) = ((*prev.as_ptr()).len, (*node.as_ptr()).len);
+    let kept = prev_len - count;
+
+    ptr::copy(Node::slot(node, 0), Node::slot(node, count), len);
+    ptr::copy_nonoverlapping(Node::slot(prev, kept), Node::slot(node, 0), count);
+    (*prev.as_ptr()).len = kept;
+    (*node.as_ptr()).len += count;
+
+    if let Some((on, index)) = *cursor {
+      if on == node {
+        *cursor = Some((node, index + count));
+      } else if on == prev && index >= kept {
+        *cursor = Some((node, index - kept));
+      }
+    }
+  }
+
+  /// Unlinks the front node along with its elements.
+  fn pop_node(&mut self) -> Option<Box<Node<T, N>>> {
+    let front = self.front?;
+    unsafe {
+      self.unlink(front);
+      self.len -= (*front.as_ptr()).len;
+      Some(Box::from_raw(front.as_ptr()))
+    }
+  }
+
+  fn node_count(&self) -> usize {
+    let mut count = 0;
+    let mut node = self.front;
+    while let Some(current) = node {
+      count += 1;
+      node = unsafe { (*current.as_ptr()).back };
+    }
+    count
+  }
+}
+
+unsafe impl<T: Send, const N: usize> Send for UnrolledList<T, N> {}
+unsafe impl<T: Sync, const N: usize> Sync for UnrolledList<T, N> {}
+
+unsafe impl<T: Sync, const N: usize> Send for Iter<'_, T, N> {}
+unsafe impl<T: Sync, const N: usize> Sync for Iter<'_, T, N> {}
+
+unsafe impl<T: Send, const N: usize> Send for IterMut<'_, T, N> {}
+unsafe impl<T: Sync, const N: usize> Sync for IterMut<'_, T, N> {}
+
+impl<T, const N: usize> Drop for UnrolledList<T, N> {
+  fn drop(&mut self) {
+    // A node drops all its elements even if one of them panics, and the
+    // guard carries on with the rest of the nodes.
+    drop_guard::drop_all_with_guard(self, Self::pop_node);
+  }
+}
+
+impl<T, const N: usize> Default for UnrolledList<T, N> {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl<T: Clone, const N: usize> Clone for UnrolledList<T, N> {
+  fn clone(&self) -> Self {
+    self.iter().cloned().collect()
+  }
+}
+
+impl<T, const N: usize> Extend<T> for UnrolledList<T, N> {
+  fn extend<I>(&mut self, iter: I)
+  where
+    I: IntoIterator<Item = T>,
+  {
+    for item in iter.into_iter() {
+      self.push_back(item);
+    }
+  }
+}
+
+impl<T, const N: usize> FromIterator<T> for UnrolledList<T, N> {
+  fn from_iter<I>(iter: I) -> Self
+  where
+    I: IntoIterator<Item = T>,
+  {
+    let mut list = Self::new();
+    list.extend(iter);
+    list
+  }
+}
+
+impl<T: Debug, const N: usize> Debug for UnrolledList<T, N> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_list().entries(self).finish()
+  }
+}
+
+impl<T: Display, const N: usize> Display for UnrolledList<T, N> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    list_fmt::write_list(f, self, list_fmt::DOUBLY_LINKED)
+  }
+}
+
+impl<T, const N: usize> MemoryUsage for UnrolledList<T, N> {
+  fn node_count(&self) -> usize {
+    UnrolledList::node_count(self)
+  }
+
+  /// Every node is the full array, however many elements are in it.
+  fn heap_bytes(&self) -> usize {
+    UnrolledList::node_count(self) * mem::size_of::<Node<T, N>>()
+  }
+}
+
+impl<T, const N: usize> Stack<T> for UnrolledList<T, N> {
+  fn push(&mut self, elem: T) {
+    UnrolledList::push_front(self, elem);
+  }
+
+  fn pop(&mut self) -> Option<T> {
+    UnrolledList::pop_front(self)
+  }
+}
+
+impl<T, const N: usize> Queue<T> for UnrolledList<T, N> {
+  fn enqueue(&mut self, elem: T) {
+    UnrolledList::push_back(self, elem);
+  }
+
+  fn dequeue(&mut self) -> Option<T> {
+    UnrolledList::pop_front(self)
+  }
+}
+
+impl<T, const N: usize> Deque<T> for UnrolledList<T, N> {
+  fn push_front(&mut self, elem: T) {
+    UnrolledList::push_front(self, elem);
+  }
+
+  fn push_back(&mut self, elem: T) {
+    UnrolledList::push_back(self, elem);
+  }
+
+  fn pop_front(&mut self) -> Option<T> {
+    UnrolledList::pop_front(self)
+  }
+
+  fn pop_back(&mut self) -> Option<T> {
+    UnrolledList::pop_back(self)
+  }
+}
+
+impl<T: FromStr, const N: usize> FromStr for UnrolledList<T, N> {
+  type Err = ParseListError<T::Err>;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    let mut list = Self::new();
+    list_fmt::parse_list(s, |elem| list.push_back(elem))?;
+    Ok(list)
+  }
+}
+
+impl<T: PartialEq, const N: usize> PartialEq for UnrolledList<T, N> {
+  fn eq(&self, other: &Self) -> bool {
+    self.len() == other.len() && self.iter().eq(other)
+  }
+}
+
+impl<T: Eq, const N: usize> Eq for UnrolledList<T, N> {}
+
+impl<T: PartialOrd, const N: usize> PartialOrd for UnrolledList<T, N> {
+  fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+    self.iter().partial_cmp(other)
+  }
+}
+
+impl<T: Ord, const N: usize> Ord for UnrolledList<T, N> {
+  fn cmp(&self, other: &Self) -> Ordering {
+    self.iter().cmp(other)
+  }
+}
+
+impl<T: Hash, const N: usize> Hash for UnrolledList<T, N> {
+  fn hash<H>(&self, state: &mut H)
+  where
+    H: Hasher,
+  {
+    self.len().hash(state);
+
+    for item in self.iter() {
+      item.hash(state);
+    }
+  }
+}
+
+/// `front` is the next element to hand out from the front, `back` is one
+/// past the next from the back, and `len` stops them crossing.
+pub struct Iter<'a, T, const N: usize> {
+  front: Option<Pos<T, N>>,
+  back: Option<Pos<T, N>>,
+  len: usize,
+  _p: PhantomData<&'a T>,
+}
+
+impl<'a, T, const N: usize> IntoIterator for &'a UnrolledList<T, N> {
+  type IntoIter = Iter<'a, T, N>;
+  type Item = &'a T;
+
+  fn into_iter(self) -> Self::IntoIter {
+    self.iter()
+  }
+}
+
+impl<'a, T, const N: usize> Iterator for Iter<'a, T, N> {
+  type Item = &'a T;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    if self.len == 0 {
+      return None;
+    }
+
+    unsafe {
+      let (node, at) = self.front?;
+      self.len -= 1;
+      self.front = if at + 1 < (*node.as_ptr()).len {
+        Some((node, at + 1))
+      } else {
+        (*node.as_ptr()).back.map(|next| (next, 0))
+      };
+      Some(&*Node::slot(node, at))
+    }
+  }
+
+  fn size_hint(&self) -> (usize, Option<usize>) {
+    (self.len, Some(self.len))
+  }
+}
+
+impl<'a, T, const N: usize> DoubleEndedIterator for Iter<'a, T, N> {
+  fn next_back(&mut self) -> Option<Self::Item> {
+    if self.len == 0 {
+      return None;
+    }
+
+    unsafe {
+      let (node, end) = self.back?;
+      self.len -= 1;
+      self.back = if end > 1 {
+        Some((node, end - 1))
+      } else {
+        (*node.as_ptr())
+          .front
+          .map(|prev| (prev, (*prev.as_ptr()).len))
+      };
+      Some(&*Node::slot(node, end - 1))
+    }
+  }
+}
+
+impl<'a, T, const N: usize> ExactSizeIterator for Iter<'a, T, N> {
+  fn len(&self) -> usize {
+    self.len
+  }
+}
+
+pub struct IntoIter<T, const N: usize> {
+  list: UnrolledList<T, N>,
+}
+
+impl<T, const N: usize> IntoIterator for UnrolledList<T, N> {
+  type IntoIter = IntoIter<T, N>;
+  type Item = T;
+
+  fn into_iter(self) -> Self::IntoIter {
+    IntoIter { list: self }
+  }
+}
+
+impl<T, const N: usize> Iterator for IntoIter<T, N> {
+  type Item = T;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    self.list.pop_front()
+  }
+
+  fn size_hint(&self) -> (usize, Option<usize>) {
+    (self.list.len, Some(self.list.len))
+  }
+}
+
+impl<T, const N: usize> DoubleEndedIterator for IntoIter<T, N> {
+  fn next_back(&mut self) -> Option<Self::Item> {
+    self.list.pop_back()
+  }
+}
+
+impl<T, const N: usize> ExactSizeIterator for IntoIter<T, N> {
+  fn len(&self) -> usize {
+    self.list.len
+  }
+}
+
+pub struct IterMut<'a, T, const N: usize> {
+  front: Option<Pos<T, N>>,
+  back: Option<Pos<T, N>>,
+  len: usize,
+  _p: PhantomData<&'a mut T>,
+}
+
+impl<'a, T, const N: usize> IntoIterator for &'a mut UnrolledList<T, N> {
+  type IntoIter = IterMut<'a, T, N>;
+  type Item = &'a mut T;
+
+  fn into_iter(self) -> Self::IntoIter {
+    self.iter_mut()
+  }
+}
+
+impl<'a, T, const N: usize> Iterator for IterMut<'a, T, N> {
+  type Item = &'a mut T;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    if self.len == 0 {
+      return None;
+    }
+
+    unsafe {
+      let (node, at) = self.front?;
+      self.len -= 1;
+      self.front = if at + 1 < (*node.as_ptr()).len {
+        Some((node, at + 1))
+      } else {
+        (*node.as_ptr()).back.map(|next| (next, 0))
+      };
+      Some(&mut *Node::slot(node, at))
+    }
+  }
+
+  fn size_hint(&self) -> (usize, Option<usize>) {
+    (self.len, Some(self.len))
+  }
+}
+
+impl<'a, T, const N: usize> DoubleEndedIterator for IterMut<'a, T, N> {
+  fn next_back(&mut self) -> Option<Self::Item> {
+    if self.len == 0 {
+      return None;
+    }
+
+    unsafe {
+      let (node, end) = self.back?;
+      self.len -= 1;
+      self.back = if end > 1 {
+        Some((node, end - 1))
+      } else {
+        (*node.as_ptr())
+          .front
+          .map(|prev| (prev, (*prev.as_ptr()).len))
+      };
+      Some(&mut *Node::slot(node, end - 1))
+    }
+  }
+}
+
+impl<'a, T, const N: usize> ExactSizeIterator for IterMut<'a, T, N> {
+  fn len(&self) -> usize {
+    self.len
+  }
+}
+
+/// Sits on one element, or on the ghost between the back and the front,
+/// and can edit the list around itself.
+pub struct CursorMut<'a, T, const N: usize = DEFAULT_CAPACITY> {
+  list: &'a mut UnrolledList<T, N>,
+  current: Option<Pos<T, N>>,
+  index: Option<usize>,
+}
+
+impl<'a, T, const N: usize> CursorMut<'a, T, N> {
+  pub fn index(&self) -> Option<usize> {
+    self.index
+  }
+
+  fn next_pos(&self) -> Option<Pos<T, N>> {
+    unsafe {
+      match self.current {
+        Some((node, at)) if at + 1 < (*node.as_ptr()).len => Some((node, at + 1)),
+        Some((node, _)) => (*node.as_ptr()).back.map(|next| (next, 0)),
+        // The ghost sits between the back and the front.
+        None => self.list.front.map(|front| (front, 0)),
+      }
+    }
+  }
+
+  fn prev_pos(&self) -> Option<Pos<T, N>> {
+    unsafe {
+      match self.current {
+        Some((node, at)) if at > 0 => Some((node, at - 1)),
+        Some((node, _)) => (*node.as_ptr())
+          .front
+          .map(|prev| (prev, (*prev.as_ptr()).len - 1)),
+        // The ghost sits between the back and the front.
+        None => self.list.back.map(|back| (back, (*back.as_ptr()).len - 1)),
+      }
+    }
+  }
+
+  pub fn move_next(&mut self) {
+    self.current = self.next_pos();
+    self.index = match (self.current, self.index) {
+      (None, _) => None,
+      (Some(_), Some(index)) => Some(index + 1),
+      (Some(_), None) => Some(0),
+    };
+  }
+
+  pub fn move_prev(&mut self) {
+    self.current = self.prev_pos();
+    self.index = match (self.current, self.index) {
+      (None, _) => None,
+      (Some(_), Some(index)) => Some(index - 1),
+      (Some(_), None) => Some(self.list.len - 1),
+    };
+  }
+
+  pub fn current(&mut self) -> Option<&mut T> {
+    unsafe { self.current.map(|(node, at)| &mut *Node::slot(node, at)) }
+  }
+
+  pub fn peek_next(&mut self) -> Option<&mut T> {
+    unsafe { self.next_pos().map(|(node, at)| &mut *Node::slot(node, at)) }
+  }
+
+  pub fn peek_prev(&mut self) -> Option<&mut T> {
+    unsafe { self.prev_pos().map(|(node, at)| &mut *Node::slot(node, at)) }
+  }
+
+  /// Inserts just before the cursor, which stays where it is. At the ghost
+  /// that's the back of the list.
+  pub fn insert_before(&mut self, elem: T) {
+    match self.current {
+      Some(pos) => unsafe {
+        self.list.insert_at(pos, elem, &mut self.current);
+        *self.index.as_mut().unwrap() += 1;
+      },
+      None => self.list.push_back(elem),
+    }
+  }
+
+  /// Inserts just after the cursor, which stays where it is. At the ghost
+  /// that's the front of the list.
+  pub fn insert_after(&mut self, elem: T) {
+    match self.current {
+      Some((node, at)) => unsafe {
+        self.list.insert_at((node, at + 1), elem, &mut self.current);
+      },
+      None => self.list.push_front(elem),
+    }
+  }
+
+  /// Takes out the element under the cursor, which moves on to the next
+  /// one.
+  pub fn remove_current(&mut self) -> Option<T> {
+    let pos = self.current?;
+    let elem = unsafe { self.list.remove_at(pos, &mut self.current) };
+    if self.current.is_none() {
+      self.index = None;
+    }
+    Some(elem)
+  }
+
+  pub fn split_before(&mut self) -> UnrolledList<T, N> {
+    let Some((mut node, at)) = self.current else {
+      // We're at the ghost, just replace our list with an empty one.
+      return mem::take(self.list);
+    };
+
+    unsafe {
+      // Cut the node first, so the split falls between two nodes.
+      if at > 0 {
+        node = self.list.split(node, at, &mut self.current);
+      }
+
+      let index = self.index.unwrap();
+      let mut output = UnrolledList::new();
+
+      if let Some(prev) = (*node.as_ptr()).front {
+        (*prev.as_ptr()).back = None;
+        (*node.as_ptr()).front = None;
+
+        output.front = self.list.front;
+        output.back = Some(prev);
+        output.len = index;
+
+        self.list.front = Some(node);
+        self.list.len -= index;
+
+        // The nodes either side of the cut might be short now.
+        output.sweep(prev, prev, &mut None);
+        self.list.sweep(node, node, &mut self.current);
+      }
+
+      self.index = Some(0);
+      output
+    }
+  }
+
+  pub fn splice_before(&mut self, mut input: UnrolledList<T, N>) {
+    let (Some(in_front), Some(in_back)) = (input.front.take(), input.back.take()) else {
+      return;
+    };
+    let in_len = mem::take(&mut input.len);
+
+    unsafe {
+      match self.current {
+        Some((mut node, at)) => {
+          if at > 0 {
+            node = self.list.split(node, at, &mut self.current);
+          }
+
+          let prev = (*node.as_ptr()).front;
+          match prev {
+            Some(prev) => (*prev.as_ptr()).back = Some(in_front),
+            None => self.list.front = Some(in_front),
+          }
+          (*in_front.as_ptr()).front = prev;
+          (*in_back.as_ptr()).back = Some(node);
+          (*node.as_ptr()).front = Some(in_back);
+
+          self.list.len += in_len;
+          *self.index.as_mut().unwrap() += in_len;
+
+          // Only the nodes at the two joins can be short. Sweeping the back
+          // one can merge all of `input` into `prev`, so look up what
+          // follows `prev` afterwards rather than trusting `in_front`.
+          self.list.sweep(in_back, node, &mut self.current);
+          let front = prev.or(self.list.front).unwrap();
+          let to = (*front.as_ptr()).back.unwrap_or(front);
+          self.list.sweep(front, to, &mut self.current);
+        }
+        None => {
+          let old_back = self.list.back;
+          match old_back {
+            Some(back) => (*back.as_ptr()).back = Some(in_front),
+            None => self.list.front = Some(in_front),
+          }
+          (*in_front.as_ptr()).front = old_back;
+          self.list.back = Some(in_back);
+          self.list.len += in_len;
+
+          self
+            .list
+            .sweep(old_back.unwrap_or(in_front), in_front, &mut self.current);
+        }
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+impl<T, const N: usize> UnrolledList<T, N> {
+  /// Walks the nodes checking their links agree both ways, with `front`
+  /// and `back`, and that every node is within its bounds and the lengths
+  /// add up to `len`. Panics at the first that doesn't.
+  fn check_invariants(&self) {
+    let (Some(front), Some(back)) = (self.front, self.back) else {
+      assert!(
+        self.front.is_none() && self.back.is_none(),
+        "only one of front and back is set"
+      );
+      assert_eq!(self.len, 0, "empty list with a non-zero len");
+      return;
+    };
+
+    unsafe {
+      assert!(
+        (*front.as_ptr()).front.is_none(),
+        "front node has a previous node"
+      );
+      let only_node = front == back;
+      let (mut node, mut count, mut total) = (front, 0, 0);
+
+      loop {
+        let len = (*node.as_ptr()).len;
+        assert!(
+          len >= 1 && len <= N,
+          "node {} holds {} elements",
+          count,
+          len
+        );
+        assert!(
+          only_node || len >= Self::MIN,
+          "node {} is under half full with {} elements",
+          count,
+          len
+        );
+        total += len;
+
+        match (*node.as_ptr()).back {
+          Some(next) => {
+            assert_eq!(
+              (*next.as_ptr()).front,
+              Some(node),
+              "node {} doesn't point back",
+              count + 1
+            );
+            node = next;
+            count += 1;
+          }
+          None => break,
+        }
+      }
+
+      assert_eq!(node, back, "back isn't the last node");
+      assert_eq!(total, self.len, "len doesn't match the nodes");
+    }
+  }
+}
+
+#[cfg(test)]
+impl<T, const N: usize> CursorMut<'_, T, N> {
+  /// The list's invariants, plus the cursor being on the element its index
+  /// says.
+  fn check_invariants(&self) {
+    self.list.check_invariants();
+
+    match (self.current, self.index) {
+      (None, None) => {}
+      (Some(pos), Some(index)) => {
+        assert!(index < self.list.len, "index {} out of bounds", index);
+        assert!(
+          self.list.locate(index) == pos,
+          "cursor isn't at index {}",
+          index
+        );
+      }
+      _ => panic!("cursor index and position disagree"),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{drop_count::DropCounter, rng::Rng};
+  use std::collections::VecDeque;
+
+  type Small<T> = UnrolledList<T, 4>;
+
+  #[test]
+  fn deque_basics() {
+    let mut list = Small::new();
+    assert_eq!(None, list.pop_front());
+    assert_eq!(None, list.back());
+
+    for i in 0..10 {
+      list.push_back(i);
+      list.push_front(-i);
+      list.check_invariants();
+    }
+    assert_eq!(20, list.len());
+    assert_eq!((Some(&-9), Some(&9)), (list.front(), list.back()));
+
+    *list.front_mut().unwrap() = 100;
+    *list.back_mut().unwrap() = 200;
+    assert_eq!(Some(100), list.pop_front());
+    assert_eq!(Some(200), list.pop_back());
+
+    while list.len() > 1 {
+      list.pop_back();
+      list.check_invariants();
+    }
+    assert_eq!(Some(-8), list.pop_front());
+    assert!(list.is_empty());
+    list.check_invariants();
+  }
+
+  #[test]
+  fn nodes_stay_half_full() {
+    let mut list: UnrolledList<u32, 8> = (0..1000).collect();
+    list.check_invariants();
+    assert!(MemoryUsage::node_count(&list) <= 1000 / 4 + 1);
+
+    // Removing every other element through a cursor merges nodes as it goes.
+    let mut cursor = list.cursor_mut();
+    cursor.move_next();
+    while cursor.index().is_some() {
+      cursor.remove_current();
+      cursor.move_next();
+    }
+    list.check_invariants();
+    assert_eq!(500, list.len());
+    assert!(MemoryUsage::node_count(&list) <= 500 / 4 + 1);
+    assert!(list.iter().copied().eq((1..1000).step_by(2)));
+  }
+
+  #[test]
+  fn iterators() {
+    let mut list: Small<i32> = (0..10).collect();
+
+    let mut iter = list.iter();
+    assert_eq!(10, iter.len());
+    assert_eq!((Some(&0), Some(&9)), (iter.next(), iter.next_back()));
+    assert_eq!(
+      vec![&1, &2, &8, &7],
+      vec![
+        iter.next().unwrap(),
+        iter.next().unwrap(),
+        iter.next_back().unwrap(),
+        iter.next_back().unwrap()
+      ]
+    );
+    assert_eq!(vec![&3, &4, &5, &6], iter.collect::<Vec<_>>());
+
+    for elem in list.iter_mut().rev().take(3) {
+      *elem *= 10;
+    }
+    for elem in &mut list {
+      *elem += 1;
+    }
+    assert_eq!(
+      vec![1, 2, 3, 4, 5, 6, 7, 71, 81, 91],
+      list.iter().copied().collect::<Vec<_>>()
+    );
+
+    let mut into = list.into_iter();
+    assert_eq!(Some(91), into.next_back());
+    assert_eq!(Some(1), into.next());
+    assert_eq!(8, into.len());
+  }
+
+  #[test]
+  fn range() {
+    let mut list: Small<i32> = (0..20).collect();
+
+    assert!(list.range(3..11).copied().eq(3..11));
+    assert!(list.range(..=4).rev().copied().eq((0..=4).rev()));
+    assert!(list.range(15..).copied().eq(15..20));
+    assert_eq!(0, list.range(7..7).count());
+
+    for elem in list.range_mut(5..8) {
+      *elem = 0;
+    }
+    assert_eq!(
+      vec![4, 0, 0, 0, 8],
+      list.range(4..9).copied().collect::<Vec<_>>()
+    );
+  }
+
+  #[test]
+  #[should_panic(expected = "range end 21 out of range for length 20")]
+  fn range_past_the_end() {
+    let list: Small<i32> = (0..20).collect();
+    list.range(5..21);
+  }
+
+  #[test]
+  fn cursor_moves_like_production() {
+    let mut list: Small<i32> = (0..6).collect();
+    let mut cursor = list.cursor_mut();
+
+    assert_eq!(None, cursor.current());
+    assert_eq!(Some(&mut 0), cursor.peek_next());
+    assert_eq!(Some(&mut 5), cursor.peek_prev());
+
+    for i in 0..6 {
+      cursor.move_next();
+      assert_eq!(Some(i as usize), cursor.index());
+      assert_eq!(Some(i), cursor.current().copied());
+      cursor.check_invariants();
+    }
+    cursor.move_next();
+    assert_eq!(None, cursor.index());
+
+    cursor.move_prev();
+    assert_eq!(
+      (Some(5), Some(5)),
+      (cursor.index(), cursor.current().copied())
+    );
+    cursor.move_prev();
+    assert_eq!(Some(&mut 3), cursor.peek_prev());
+    assert_eq!(Some(&mut 5), cursor.peek_next());
+  }
+
+  #[test]
+  fn cursor_edits() {
+    let mut list: Small<i32> = (0..8).collect();
+    let mut cursor = list.cursor_mut();
+
+    cursor.move_next();
+    cursor.move_next();
+    cursor.insert_before(10);
+    cursor.insert_after(11);
+    cursor.check_invariants();
+    assert_eq!(
+      (Some(2), Some(1)),
+      (cursor.index(), cursor.current().copied())
+    );
+
+    assert_eq!(Some(1), cursor.remove_current());
+    assert_eq!(Some(&mut 11), cursor.current());
+    cursor.check_invariants();
+
+    let front = cursor.split_before();
+    cursor.check_invariants();
+    assert_eq!(Some(0), cursor.index());
+    assert_eq!(vec![0, 10], front.iter().copied().collect::<Vec<_>>());
+
+    cursor.move_next();
+    cursor.move_next();
+    cursor.splice_before(front);
+    cursor.check_invariants();
+    assert_eq!(Some(&mut 3), cursor.current());
+    assert_eq!(Some(4), cursor.index());
+
+    // Removing the last element lands on the ghost.
+    cursor.move_prev();
+    cursor.move_prev();
+    cursor.move_prev();
+    cursor.move_prev();
+    cursor.move_prev();
+    assert_eq!(None, cursor.index());
+    cursor.insert_after(-1);
+    cursor.insert_before(99);
+    cursor.move_prev();
+    assert_eq!(Some(99), cursor.remove_current());
+    assert_eq!(None, cursor.index());
+    cursor.check_invariants();
+
+    assert_eq!(
+      vec![-1, 11, 2, 0, 10, 3, 4, 5, 6, 7],
+      list.iter().copied().collect::<Vec<_>>()
+    );
+  }
+
+  #[test]
+  fn splice_merging_into_both_neighbours() {
+    // The split leaves [0, 1, 2, 3] and [4], and both joins want to merge,
+    // so the input ends up folded into the node before it.
+    let mut list: UnrolledList<i32> = (0..5).collect();
+    let mut cursor = list.cursor_mut();
+    for _ in 0..5 {
+      cursor.move_next();
+    }
+    cursor.splice_before(UnrolledList::from_iter([9]));
+    cursor.check_invariants();
+    assert_eq!((Some(5), Some(&mut 4)), (cursor.index(), cursor.current()));
+    assert_eq!(
+      vec![0, 1, 2, 3, 9, 4],
+      list.iter().copied().collect::<Vec<_>>()
+    );
+  }
+
+  #[test]
+  fn random_ops_match_vec_deque() {
+    random_ops_with::<4>();
+    random_ops_with::<5>();
+    random_ops_with::<16>();
+  }
+
+  fn random_ops_with<const N: usize>() {
+    let mut rng = Rng(0x2545_f491_4f6c_dd1d);
+    let mut next = move |n: usize| rng.below(n);
+
+    let mut list: UnrolledList<usize, N> = UnrolledList::new();
+    let mut model = VecDeque::new();
+    let mut index: Option<usize> = None;
+
+    for step in 0..5_000 {
+      let mut cursor = list.cursor_mut();
+      // Put the cursor back where the model says it was.
+      if let Some(i) = index {
+        for _ in 0..=i {
+          cursor.move_next();
+        }
+      }
+
+      match next(11) {
+        0 => {
+          cursor.insert_before(step);
+          match index {
+            Some(i) => {
+              model.insert(i, step);
+              index = Some(i + 1);
+            }
+            None => model.push_back(step),
+          }
+        }
+        1 => {
+          cursor.insert_after(step);
+          match index {
+            Some(i) => model.insert(i + 1, step),
+            None => model.push_front(step),
+          }
+        }
+        2 => {
+          let removed = cursor.remove_current();
+          assert_eq!(index.map(|i| model.remove(i).unwrap()), removed);
+          if index.is_some_and(|i| i == model.len()) {
+            index = None;
+          }
+        }
+        3 => {
+          cursor.move_next();
+          index = cursor.index();
+        }
+        4 => {
+          cursor.move_prev();
+          index = cursor.index();
+        }
+        5 => {
+          let front = cursor.split_before();
+          front.check_invariants();
+          let split = index.unwrap_or(model.len());
+          let rest = model.split_off(split);
+          assert!(front.iter().eq(model.iter()));
+          cursor.splice_before(front);
+          model.extend(rest);
+        }
+        6 => {
+          // Splice in a fresh list of a random length, up to a few nodes.
+          let len = next(3 * N);
+          let input: UnrolledList<usize, N> = (0..len).map(|i| step * 100 + i).collect();
+          cursor.splice_before(input);
+          let at = index.unwrap_or(model.len());
+          for i in (0..len).rev() {
+            model.insert(at, step * 100 + i);
+          }
+          index = index.map(|i| i + len);
+        }
+        7 => {
+          list.push_front(step);
+          model.push_front(step);
+          index = index.map(|i| i + 1);
+        }
+        8 => {
+          assert_eq!(model.pop_back(), list.pop_back());
+          if index.is_some_and(|i| i >= model.len()) {
+            index = None;
+          }
+        }
+        9 => {
+          list.push_back(step);
+          model.push_back(step);
+        }
+        _ => {
+          assert_eq!(model.pop_front(), list.pop_front());
+          index = match index {
+            Some(0) | None => None,
+            Some(i) => Some(i - 1),
+          };
+        }
+      }
+
+      list.check_invariants();
+      if step % 100 == 0 {
+        assert!(list.iter().eq(model.iter()));
+        assert!(list.iter().rev().eq(model.iter().rev()));
+      }
+    }
+
+    assert!(list.iter().eq(model.iter()));
+  }
+
+  #[test]
+  fn drops_everything() {
+    let counter = DropCounter::new();
+    let mut list: Small<_> = counter.make_n(30).into_iter().collect();
+
+    let mut cursor = list.cursor_mut();
+    for _ in 0..10 {
+      cursor.move_next();
+    }
+    drop(cursor.remove_current());
+    let front = cursor.split_before();
+    drop(front);
+    drop(list.pop_back());
+    list.clear();
+    assert!(list.is_empty());
+
+    let list: Small<_> = counter.make_n(20).into_iter().collect();
+    let mut into = list.into_iter();
+    drop(into.next_back());
+    drop(into);
+
+    counter.assert_all_dropped();
+  }
+
+  #[test]
+  fn keeps_dropping_after_a_panic() {
+    let counter = DropCounter::new();
+    let list: Small<_> = counter.make_n(20).into_iter().collect();
+    counter.panic_on_drop(6);
+
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| drop(list)));
+    assert!(result.is_err());
+    assert_eq!(counter.created(), counter.dropped());
+  }
+
+  #[test]
+  fn traits_and_formatting() {
+    let list: UnrolledList<i32> = "[1, 2, 3]".parse().unwrap();
+    assert_eq!("[1, 2, 3]", list.to_string());
+    assert_eq!("1 <-> 2 <-> 3", format!("{:#}", list));
+    assert_eq!(Ok(list.clone()), format!("{:#}", list).parse());
+    assert_eq!(list, list.clone());
+    assert!(list < "[1, 2, 4]".parse().unwrap());
+  }
+
+  #[test]
+  fn zero_sized_elements() {
+    let mut list: Small<()> = std::iter::repeat_n((), 50).collect();
+    list.check_invariants();
+    assert_eq!(50, list.iter().count());
+    assert_eq!(Some(()), list.pop_back());
+    assert_eq!(49, list.len());
+  }
+
+  crate::traits::conformance::stack_conformance!(UnrolledList<i32, 4>);
+
+  crate::traits::conformance::queue_conformance!(UnrolledList<i32, 4>);
+
+  crate::traits::conformance::deque_conformance!(UnrolledList<i32, 4>);
+}
@@ -0,0 +1,636 @@
+//! A doubly linked list with one link field per node instead of two: each
+//! node stores the address of the node before it XORed with the address of
+//! the node after it. Walking needs the address you came from to XOR back
+//! out, so you can only get around from either end, but either end works
+//! the same way, which makes `reverse` a swap of `head` and `tail`.
+//!
+//! An address with the XOR in it isn't a pointer anything could have
+//! provenance for, so the links go through the exposed provenance APIs:
+//! every node's address is exposed when it's linked in, and
+//! `with_exposed_provenance_mut` picks that back up on the way out. Miri
+//! understands this (it warns about it unless told not to), run the tests
+//! under it with:
+//!
+//! ```terminal
+//! MIRIFLAGS="-Zmiri-permissive-provenance" cargo +nightly miri test xor_list
+//! ```
+//!
+//! What it saves is one pointer per node, which `MemoryUsage` shows next to
+//! `production_linked_list`.
+
+use std::{
+  fmt::{self, Debug, Display},
+  iter::FromIterator,
+  marker::PhantomData,
+  mem,
+  ptr::{self, NonNull},
+};
+
+use crate::{
+  drop_guard, list_fmt,
+  memory::MemoryUsage,
+  traits::{Deque, Queue, Stack},
+};
+
+pub struct XorList<T> {
+  head: Link<T>,
+  tail: Link<T>,
+  len: usize,
+  _p: PhantomData<Box<Node<T>>>,
+}
+
+type Link<T> = Option<NonNull<Node<T>>>;
+
+struct Node<T> {
+  /// The addresses of the nodes either side XORed together, with 0 for no
+  /// node.
+  both: usize,
+  elem: T,
+}
+
+/// The address of `link`, exposed so `link_at` can turn it back into a
+/// pointer, or 0 for `None`.
+fn addr<T>(link: Link<T>) -> usize {
+  link.map_or(0, |node| node.as_ptr().expose_provenance())
+}
+
+fn link_at<T>(addr: usize) -> Link<T> {
+  NonNull::new(ptr::with_exposed_provenance_mut(addr))
+}
+
+/// The neighbour of `node` that isn't `from`.
+unsafe fn step<T>(node: NonNull<Node<T>>, from: Link<T>) -> Link<T> {
+  link_at((*node.as_ptr()).both ^ addr(from))
+}
+
+impl<T> XorList<T> {
+  pub fn new() -> Self {
+    Self {
+      head: None,
+      tail: None,
+      len: 0,
+      _p: PhantomData,
+    }
+  }
+
+  pub fn len(&self) -> usize {
+    self.len
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.len == 0
+  }
+
+  pub fn push_front(&mut self, elem: T) {
+    Self::push(&mut self.head, &mut self.tail, elem);
+    self.len += 1;
+  }
+
+  pub fn push_back(&mut self, elem: T) {
+    Self::push(&mut self.tail, &mut self.head, elem);
+    self.len += 1;
+  }
+
+  pub fn pop_front(&mut self) -> Option<T> {
+    let elem = Self::pop(&mut self.head, &mut self.tail)?;
+    self.len -= 1;
+    Some(elem)
+  }
+
+  pub fn pop_back(&mut self) -> Option<T> {
+    let elem = Self::pop(&mut self.tail, &mut self.head)?;
+    self.len -= 1;
+    Some(elem)
+  }
+
+  /// Both ends look the same from inside, so which one is `end` decides
+  /// whether this is a push to the front or the back.
+  fn push(end: &mut Link<T>, other_end: &mut Link<T>, elem: T) {
+    unsafe {
+      let new = NonNull::new_unchecked(Box::into_raw(Box::new(Node {
+        // Nothing on the outside, `end` on the inside.
+        both: addr(*end),
+        elem,
+      })));
+
+      match *end {
+        // The old end had nothing outside it, now it has the new node.
+        Some(old) => (*old.as_ptr()).both ^= addr(Some(new)),
+        None => *other_end = Some(new),
+      }
+
+      *end = Some(new);
+    }
+  }
+
+  fn pop(end: &mut Link<T>, other_end: &mut Link<T>) -> Option<T> {
+    end.map(|node| unsafe {
+      let boxed = Box::from_raw(node.as_ptr());
+      let inner = link_at(boxed.both);
+
+      match inner {
+        Some(inner) => (*inner.as_ptr()).both ^= addr(Some(node)),
+        None => *other_end = None,
+      }
+
+      *end = inner;
+      boxed.elem
+    })
+  }
+
+  pub fn front(&self) -> Option<&T> {
+    unsafe { self.head.map(|node| &(*node.as_ptr()).elem) }
+  }
+
+  pub fn front_mut(&mut self) -> Option<&mut T> {
+    unsafe { self.head.map(|node| &mut (*node.as_ptr()).elem) }
+  }
+
+  pub fn back(&self) -> Option<&T> {
+    unsafe { self.tail.map(|node| &(*node.as_ptr()).elem) }
+  }
+
+  pub fn back_mut(&mut self) -> Option<&mut T> {
+    unsafe { self.tail.map(|node| &mut (*node.as_ptr()).elem) }
+  }
+
+  /// Turns the list around without touching a single node.
+  pub fn reverse(&mut self) {
+    mem::swap(&mut self.head, &mut self.tail);
+  }
+
+  pub fn clear(&mut self) {
+    // Dropping handles an element panicking, and leaves `self` empty if one does.
+    drop(mem::take(self));
+  }
+
+  pub fn iter(&self) -> Iter<'_, T> {
+    Iter {
+      front: self.head,
+      front_prev: None,
+      back: self.tail,
+      back_next: None,
+      len: self.len,
+      _p: PhantomData,
+    }
+  }
+
+  pub fn iter_mut(&mut self) -> IterMut<'_, T> {
+    IterMut {
+      front: self.head,
+      front_prev: None,
+      back: self.tail,
+      back_next: None,
+      len: self.len,
+      _p: PhantomData,
+    }
+  }
+}
+
+unsafe impl<T: Send> Send for XorList<T> {}
+unsafe impl<T: Sync> Sync for XorList<T> {}
+
+unsafe impl<T: Sync> Send for Iter<'_, T> {}
+unsafe impl<T: Sync> Sync for Iter<'_, T> {}
+
+unsafe impl<T: Send> Send for IterMut<'_, T> {}
+unsafe impl<T: Sync> Sync for IterMut<'_, T> {}
+
+impl<T> Drop for XorList<T> {
+  fn drop(&mut self) {
+    drop_guard::drop_all_with_guard(self, Self::pop_front);
+  }
+}
+
+impl<T> Default for XorList<T> {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl<T: Clone> Clone for XorList<T> {
+  fn clone(&self) -> Self {
+    self.iter().cloned().collect()
+  }
+}
+
+impl<T> Extend<T> for XorList<T> {
+  fn extend<I>(&mut self, iter: I)
+  where
+    I: IntoIterator<Item = T>,
+  {
+    for item in iter.into_iter() {
+      self.push_back(item);
+    }
+  }
+}
+
+impl<T> FromIterator<T> for XorList<T> {
+  fn from_iter<I>(iter: I) -> Self
+  where
+    I: IntoIterator<Item = T>,
+  {
+    let mut list = Self::new();
+    list.extend(iter);
+    list
+  }
+}
+
+impl<T: Debug> Debug for XorList<T> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_list().entries(self).finish()
+  }
+}
+
+impl<T: Display> Display for XorList<T> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    list_fmt::write_list(f, self, list_fmt::DOUBLY_LINKED)
+  }
+}
+
+impl<T: PartialEq> PartialEq for XorList<T> {
+  fn eq(&self, other: &Self) -> bool {
+    self.len() == other.len() && self.iter().eq(other)
+  }
+}
+
+impl<T: Eq> Eq for XorList<T> {}
+
+impl<T> MemoryUsage for XorList<T> {
+  fn node_count(&self) -> usize {
+    self.len
+  }
+
+  fn heap_bytes(&self) -> usize {
+    self.len * mem::size_of::<Node<T>>()
+  }
+}
+
+impl<T> Stack<T> for XorList<T> {
+  fn push(&mut self, elem: T) {
+    XorList::push_front(self, elem);
+  }
+
+  fn pop(&mut self) -> Option<T> {
+    XorList::pop_front(self)
+  }
+}
+
+impl<T> Queue<T> for XorList<T> {
+  fn enqueue(&mut self, elem: T) {
+    XorList::push_back(self, elem);
+  }
+
+  fn dequeue(&mut self) -> Option<T> {
+    XorList::pop_front(self)
+  }
+}
+
+impl<T> Deque<T> for XorList<T> {
+  fn push_front(&mut self, elem: T) {
+    XorList::push_front(self, elem);
+  }
+
+  fn push_back(&mut self, elem: T) {
+    XorList::push_back(self, elem);
+  }
+
+  fn pop_front(&mut self) -> Option<T> {
+    XorList::pop_front(self)
+  }
+
+  fn pop_back(&mut self) -> Option<T> {
+    XorList::pop_back(self)
+  }
+}
+
+/// Each end remembers the node it came from, which it needs to take the
+/// next step.
+pub struct Iter<'a, T> {
+  front: Link<T>,
+  front_prev: Link<T>,
+  back: Link<T>,
+  back_next: Link<T>,
+  len: usize,
+  _p: PhantomData<&'a T>,
+}
+
+impl<'a, T> IntoIterator for &'a XorList<T> {
+  type IntoIter = Iter<'a, T>;
+  type Item = &'a T;
+
+  fn into_iter(self) -> Self::IntoIter {
+    self.iter()
+  }
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+  type Item = &'a T;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    if self.len == 0 {
+      return None;
+    }
+
+    self.front.map(|node| unsafe {
+      self.len -= 1;
+      self.front = step(node, self.front_prev);
+      self.front_prev = Some(node);
+      &(*node.as_ptr()).elem
+    })
+  }
+
+  fn size_hint(&self) -> (usize, Option<usize>) {
+    (self.len, Some(self.len))
+  }
+}
+
+impl<'a, T> DoubleEndedIterator for Iter<'a, T> {
+  fn next_back(&mut self) -> Option<Self::Item> {
+    if self.len == 0 {
+      return None;
+    }
+
+    self.back.map(|node| unsafe {
+      self.len -= 1;
+      self.back = step(node, self.back_next);
+      self.back_next = Some(node);
+      &(*node.as_ptr()).elem
+    })
+  }
+}
+
+impl<'a, T> ExactSizeIterator for Iter<'a, T> {
+  fn len(&self) -> usize {
+    self.len
+  }
+}
+
+pub struct IterMut<'a, T> {
+  front: Link<T>,
+  front_prev: Link<T>,
+  back: Link<T>,
+  back_next: Link<T>,
+  len: usize,
+  _p: PhantomData<&'a mut T>,
+}
+
+impl<'a, T> IntoIterator for &'a mut XorList<T> {
+  type IntoIter = IterMut<'a, T>;
+  type Item = &'a mut T;
+
+  fn into_iter(self) -> Self::IntoIter {
+    self.iter_mut()
+  }
+}
+
+impl<'a, T> Iterator for IterMut<'a, T> {
+  type Item = &'a mut T;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    if self.len == 0 {
+      return None;
+    }
+
+    self.front.map(|node| unsafe {
+      self.len -= 1;
+      self.front = step(node, self.front_prev);
+      self.front_prev = Some(node);
+      &mut (*node.as_ptr()).elem
+    })
+  }
+
+  fn size_hint(&self) -> (usize, Option<usize>) {
+    (self.len, Some(self.len))
+  }
+}
+
+impl<'a, T> DoubleEndedIterator for IterMut<'a, T> {
+  fn next_back(&mut self) -> Option<Self::Item> {
+    if self.len == 0 {
+      return None;
+    }
+
+    self.back.map(|node| unsafe {
+      self.len -= 1;
+      self.back = step(node, self.back_next);
+      self.back_next = Some(node);
+      &mut (*node.as_ptr()).elem
+    })
+  }
+}
+
+impl<'a, T> ExactSizeIterator for IterMut<'a, T> {
+  fn len(&self) -> usize {
+    self.len
+  }
+}
+
+pub struct IntoIter<T> {
+  list: XorList<T>,
+}
+
+impl<T> IntoIterator for XorList<T> {
+  type IntoIter = IntoIter<T>;
+  type Item = T;
+
+  fn into_iter(self) -> Self::IntoIter {
+    IntoIter { list: self }
+  }
+}
+
+impl<T> Iterator for IntoIter<T> {
+  type Item = T;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    self.list.pop_front()
+  }
+
+  fn size_hint(&self) -> (usize, Option<usize>) {
+    (self.list.len, Some(self.list.len))
+  }
+}
+
+impl<T> DoubleEndedIterator for IntoIter<T> {
+  fn next_back(&mut self) -> Option<Self::Item> {
+    self.list.pop_back()
+  }
+}
+
+impl<T> ExactSizeIterator for IntoIter<T> {
+  fn len(&self) -> usize {
+    self.list.len
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{drop_count::DropCounter, production_linked_list::LinkedList, rng::Rng};
+  use std::collections::VecDeque;
+
+  #[test]
+  fn basics() {
+    let mut list = XorList::new();
+    assert_eq!(None, list.pop_front());
+    assert_eq!(None, list.pop_back());
+
+    list.push_back(2);
+    list.push_front(1);
+    list.push_back(3);
+    assert_eq!(3, list.len());
+    assert_eq!((Some(&1), Some(&3)), (list.front(), list.back()));
+
+    *list.front_mut().unwrap() *= 10;
+    *list.back_mut().unwrap() *= 10;
+    assert_eq!(Some(10), list.pop_front());
+    assert_eq!(Some(30), list.pop_back());
+    assert_eq!(Some(2), list.pop_back());
+    assert_eq!(None, list.pop_front());
+    assert!(list.is_empty());
+
+    // Still works after going empty.
+    list.push_front(4);
+    assert_eq!((Some(&4), Some(&4)), (list.front(), list.back()));
+  }
+
+  #[test]
+  fn iterates_both_ways() {
+    let mut list: XorList<i32> = (0..6).collect();
+
+    assert!(list.iter().copied().eq(0..6));
+    assert!(list.iter().rev().copied().eq((0..6).rev()));
+
+    let mut iter = list.iter();
+    assert_eq!((Some(&0), Some(&5)), (iter.next(), iter.next_back()));
+    assert_eq!((Some(&1), Some(&4)), (iter.next(), iter.next_back()));
+    assert_eq!(2, iter.len());
+    assert_eq!(vec![&2, &3], iter.collect::<Vec<_>>());
+
+    for elem in &mut list {
+      *elem *= 2;
+    }
+    for elem in list.iter_mut().rev().take(2) {
+      *elem += 1;
+    }
+    assert_eq!("[0, 2, 4, 6, 9, 11]", list.to_string());
+
+    let mut into = list.into_iter();
+    assert_eq!(Some(11), into.next_back());
+    assert_eq!(Some(0), into.next());
+    assert_eq!(vec![2, 4, 6, 9], into.collect::<Vec<_>>());
+  }
+
+  #[test]
+  fn reverse() {
+    let mut list: XorList<i32> = (0..5).collect();
+    list.reverse();
+    assert!(list.iter().copied().eq((0..5).rev()));
+
+    // Pushing and popping carry on from the new ends.
+    list.push_front(10);
+    list.push_back(-1);
+    assert_eq!(Some(-1), list.pop_back());
+    assert_eq!(Some(0), list.pop_back());
+    list.reverse();
+    assert_eq!(
+      vec![1, 2, 3, 4, 10],
+      list.iter().copied().collect::<Vec<_>>()
+    );
+
+    let mut empty = XorList::<i32>::new();
+    empty.reverse();
+    assert!(empty.is_empty());
+  }
+
+  #[test]
+  fn random_ops_match_vec_deque() {
+    let mut rng = Rng(0x853c_49e6_748f_ea9b);
+    let mut list = XorList::new();
+    let mut model = VecDeque::new();
+
+    for i in 0..2_000 {
+      match rng.below(6) {
+        0 | 1 => {
+          list.push_front(i);
+          model.push_front(i);
+        }
+        2 | 3 => {
+          list.push_back(i);
+          model.push_back(i);
+        }
+        4 => assert_eq!(model.pop_front(), list.pop_front()),
+        _ => {
+          list.reverse();
+          model.make_contiguous().reverse();
+          assert_eq!(model.pop_back(), list.pop_back());
+        }
+      }
+
+      assert_eq!(model.len(), list.len());
+      if i % 100 == 0 {
+        assert!(list.iter().eq(model.iter()));
+        assert!(list.iter().rev().eq(model.iter().rev()));
+      }
+    }
+  }
+
+  #[test]
+  fn saves_a_pointer_per_node() {
+    fn saving<T: Default>() -> usize {
+      let xor: XorList<T> = (0..100).map(|_| T::default()).collect();
+      let production: LinkedList<T> = (0..100).map(|_| T::default()).collect();
+      (production.heap_bytes() - xor.heap_bytes()) / 100
+    }
+
+    let pointer = mem::size_of::<usize>();
+    assert_eq!(pointer, saving::<u64>());
+    assert_eq!(pointer, saving::<String>());
+    assert_eq!(pointer, saving::<[u64; 5]>());
+    assert_eq!(100, XorList::<u8>::from_iter(0..100).node_count());
+  }
+
+  #[test]
+  fn drops_everything() {
+    let counter = DropCounter::new();
+    let mut list: XorList<_> = counter.make_n(20).into_iter().collect();
+
+    drop(list.pop_front());
+    list.reverse();
+    drop(list.pop_front());
+    let mut into = list.into_iter();
+    drop(into.next_back());
+    drop(into);
+
+    let mut list: XorList<_> = counter.make_n(5).into_iter().collect();
+    list.clear();
+    assert!(list.is_empty());
+
+    counter.assert_all_dropped();
+  }
+
+  #[test]
+  fn keeps_dropping_after_a_panic() {
+    let counter = DropCounter::new();
+    let list: XorList<_> = counter.make_n(10).into_iter().collect();
+    counter.panic_on_drop(4);
+
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| drop(list)));
+    assert!(result.is_err());
+    assert_eq!(counter.created(), counter.dropped());
+  }
+
+  #[test]
+  fn traits() {
+    let list: XorList<i32> = (1..4).collect();
+    assert_eq!(list, list.clone());
+    assert_eq!("[1, 2, 3]", format!("{:?}", list));
+    assert_eq!("1 <-> 2 <-> 3", format!("{:#}", list));
+  }
+
+  crate::traits::conformance::stack_conformance!(XorList<i32>);
+
+  crate::traits::conformance::queue_conformance!(XorList<i32>);
+
+  crate::traits::conformance::deque_conformance!(XorList<i32>);
+}
@@ -0,0 +1,31 @@
+//! Runs with a `FreeList` as the global allocator, which has to be its own
+//! test binary since there's only one per program.
+
+use learn_rust_with_entirely_too_many_linked_lists::{
+  free_list_alloc::{Fit, GlobalFreeList},
+  production_linked_list::LinkedList,
+};
+
+const HEAP_LEN: usize = 32 << 20;
+static mut HEAP: [u8; HEAP_LEN] = [0; HEAP_LEN];
+
+#[global_allocator]
+static ALLOC: GlobalFreeList =
+  unsafe { GlobalFreeList::new(&raw mut HEAP as *mut u8, HEAP_LEN, Fit::First) };
+
+#[test]
+fn allocations_come_from_the_free_list() {
+  let before = ALLOC.stats();
+  assert!(before.used > 0);
+
+  let list: LinkedList<String> = (0..1000).map(|i| i.to_string()).collect();
+  let during = ALLOC.stats();
+  assert!(during.used >= before.used + 1000 * 32);
+  assert_eq!(
+    999,
+    list.iter().next_back().unwrap().parse::<i32>().unwrap()
+  );
+
+  drop(list);
+  assert_eq!(before.used, ALLOC.stats().used);
+}